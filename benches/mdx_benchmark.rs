@@ -1,18 +1,53 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use mdx::parse;
+use mdx::{
+    incremental::{Document, Edit},
+    parse,
+    stream::Blocks,
+    ParseOptions, Span,
+};
+
+mod parse;
 
 pub fn criterion_benchmark(c: &mut Criterion) {
-    c.bench_function("parse", |b| {
+    parse::parse_benchmarks(c);
+    c.bench_function("parse containers", |b| {
         b.iter(|| {
             parse(black_box(
                 "# Some
 
----
+> quoted
+> - list
+
+- one
+- two
+  1. nested
 
-## Content",
+<Note type=\"info\">
+Content
+</Note>",
             ))
         })
     });
+
+    // typed into near the middle
+    let text = parse::large_document();
+    let at = text.find("Section 600").unwrap();
+    let mut document = Document::parse(text.as_str(), ParseOptions::default());
+    c.bench_function("edit a large document", |b| {
+        b.iter(|| {
+            document.edit(Edit {
+                span: Span::new(at, at),
+                text: black_box("x"),
+            });
+            document.edit(Edit {
+                span: Span::new(at, at + 1),
+                text: "",
+            })
+        })
+    });
+    c.bench_function("stream a large document", |b| {
+        b.iter(|| Blocks::new(black_box(text.as_bytes()), ParseOptions::default()).count())
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
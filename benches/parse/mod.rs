@@ -0,0 +1,30 @@
+//! Benchmarks that only use `parse`, so they also build against
+//! older versions of the crate. `make bench-baseline` runs them
+//! against the commit before block containers were added.
+use criterion::{black_box, Criterion};
+use mdx::parse;
+
+/// About 5,000 lines of headings and paragraphs
+pub fn large_document() -> String {
+    (0..1250)
+        .map(|i| format!("## Section {}\n\nSome *text* for it.\n\n", i))
+        .collect()
+}
+
+pub fn parse_benchmarks(c: &mut Criterion) {
+    c.bench_function("parse", |b| {
+        b.iter(|| {
+            parse(black_box(
+                "# Some
+
+---
+
+## Content",
+            ))
+        })
+    });
+    let text = large_document();
+    c.bench_function("parse a large document", |b| {
+        b.iter(|| parse(black_box(&text)))
+    });
+}
@@ -1,32 +1,118 @@
-use nom::{
-    character::complete::*, multi::many1_count, IResult,
+use crate::{
+    mdx_error::{MdxError, Span},
+    toc::Toc,
 };
-use nom_supreme::{
-    error::ErrorTree,
-    final_parser::{final_parser, Location},
+use nom::{
+    character::complete::*,
+    error::{ErrorKind, ParseError},
+    IResult,
 };
-use std::fmt;
+use nom_supreme::error::ErrorTree;
+use std::{borrow::Cow, fmt};
 
+pub mod attributes;
+pub mod block_quotes;
+pub mod code_spans;
+pub mod directives;
+pub(crate) mod emphasis;
+pub mod esm;
 pub mod fenced_codeblocks;
+pub mod frontmatter;
 pub mod headings;
+pub mod inlines;
+pub mod jsx;
+pub(crate) mod lines;
+pub mod lists;
+pub mod math;
 pub mod paragraphs;
+pub mod slugs;
+pub mod strikethrough;
+pub mod tables;
 pub mod thematic_breaks;
 
+pub use attributes::{attributes, Attribute};
+pub use block_quotes::{block_quote, BlockQuote};
+pub use code_spans::{inline_code, InlineCode};
+pub use directives::{
+    container_directive, leaf_directive, text_directive, ContainerDirective,
+    LeafDirective, TextDirective,
+};
+pub use esm::{esm, Esm, Import};
 pub use fenced_codeblocks::FencedCodeblock;
-pub use headings::{atx_heading, ATXHeading};
+pub use frontmatter::{frontmatter, Frontmatter, FrontmatterKind};
+pub use headings::{
+    atx_heading, atx_heading_with_options, setext_heading, setext_heading_with_options,
+    ATXHeading,
+};
+pub use inlines::{inlines, inlines_with_options, text_content, Inline};
+pub use jsx::{
+    expression, flow_expression, jsx_element, Expression, JsxAttribute, JsxAttributeValue,
+    JsxElement,
+};
+pub use lists::{list, List, ListItem};
+pub use math::{inline_math, math, InlineMath, Math};
 pub use paragraphs::{paragraph, Paragraph};
+pub use strikethrough::{strikethrough, Strikethrough};
+pub use tables::{table, Align, Table};
 pub use thematic_breaks::{thematic_break, ThematicBreak};
 
 use self::fenced_codeblocks::fenced_codeblock;
 
-// TODO: maybe get rid of Copy/Clone here?
-// it's required by fold_many0
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+// Block quotes, lists, JSX elements and container directives
+// own their children, so this can't be Copy
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum MdxAst<'a> {
     ATXHeading(ATXHeading<'a>),
     ThematicBreak(ThematicBreak),
     Paragraph(Paragraph<'a>),
     Codeblock(FencedCodeblock<'a>),
+    Math(Math<'a>),
+    Table(Table<'a>),
+    Frontmatter(Frontmatter<'a>),
+    LeafDirective(LeafDirective<'a>),
+    ContainerDirective(ContainerDirective<'a>),
+    BlockQuote(BlockQuote<'a>),
+    List(List<'a>),
+    JsxElement(JsxElement<'a>),
+    Esm(Esm<'a>),
+    Expression(Expression<'a>),
+    /// A generated table of contents, see `crate::inject_toc`
+    Toc(Toc),
+}
+impl<'a> MdxAst<'a> {
+    /// Copies everything the node borrows from the input, so it
+    /// can outlive it
+    pub fn into_owned(self) -> MdxAst<'static> {
+        match self {
+            MdxAst::ATXHeading(atx) => MdxAst::ATXHeading(atx.into_owned()),
+            MdxAst::ThematicBreak(brk) => MdxAst::ThematicBreak(brk),
+            MdxAst::Paragraph(para) => MdxAst::Paragraph(para.into_owned()),
+            MdxAst::Codeblock(codeblock) => {
+                MdxAst::Codeblock(codeblock.into_owned())
+            }
+            MdxAst::Math(math) => MdxAst::Math(math.into_owned()),
+            MdxAst::Table(table) => MdxAst::Table(table.into_owned()),
+            MdxAst::Frontmatter(frontmatter) => {
+                MdxAst::Frontmatter(frontmatter.into_owned())
+            }
+            MdxAst::LeafDirective(leaf) => {
+                MdxAst::LeafDirective(leaf.into_owned())
+            }
+            MdxAst::ContainerDirective(container) => {
+                MdxAst::ContainerDirective(container.into_owned())
+            }
+            MdxAst::BlockQuote(quote) => MdxAst::BlockQuote(quote.into_owned()),
+            MdxAst::List(list) => MdxAst::List(list.into_owned()),
+            MdxAst::JsxElement(element) => {
+                MdxAst::JsxElement(element.into_owned())
+            }
+            MdxAst::Esm(esm) => MdxAst::Esm(esm.into_owned()),
+            MdxAst::Expression(expression) => {
+                MdxAst::Expression(expression.into_owned())
+            }
+            MdxAst::Toc(toc) => MdxAst::Toc(toc),
+        }
+    }
 }
 impl<'a> fmt::Display for MdxAst<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -41,68 +127,576 @@ impl<'a> fmt::Display for MdxAst<'a> {
             MdxAst::Codeblock(codeblock) => {
                 write!(f, "{}", codeblock)
             }
+            MdxAst::Math(math) => write!(f, "{}", math),
+            MdxAst::Table(table) => write!(f, "{}", table),
+            MdxAst::Frontmatter(frontmatter) => write!(f, "{}", frontmatter),
+            MdxAst::LeafDirective(leaf) => write!(f, "{}", leaf),
+            MdxAst::ContainerDirective(container) => {
+                write!(f, "{}", container)
+            }
+            MdxAst::BlockQuote(quote) => write!(f, "{}", quote),
+            MdxAst::List(list) => write!(f, "{}", list),
+            MdxAst::JsxElement(element) => write!(f, "{}", element),
+            MdxAst::Esm(esm) => write!(f, "{}", esm),
+            MdxAst::Expression(expression) => write!(f, "{}", expression),
+            MdxAst::Toc(toc) => write!(f, "{}", toc),
         }
     }
 }
 
-pub fn mdx_elements(
+/// Nodes borrow from the input where they can. This turns a
+/// borrowed string into an owned one, for `into_owned`.
+fn owned(value: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(value.into_owned())
+}
+
+/// Parses the block content of a container node that starts at
+/// `input`. Content that had to be rewritten, like a block quote
+/// with its `>` markers stripped, doesn't live as long as the
+/// input, so the children parsed from it are made owned.
+fn container_children<'a>(
+    input: &'a str,
+    content: Cow<'a, str>,
+    options: ParseOptions,
+) -> Result<Vec<MdxAst<'a>>, nom::Err<ErrorTree<&'a str>>> {
+    if content.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    match content {
+        Cow::Borrowed(content) => Ok(mdx_elements_internal(options)(content)?.1),
+        Cow::Owned(content) => match mdx_elements_internal(options)(&content) {
+            Ok((_, children)) => {
+                Ok(children.into_iter().map(MdxAst::into_owned).collect())
+            }
+            // what went wrong can't be pointed at in the input, but
+            // failures that rule out everything else still have to
+            // be failures
+            Err(nom::Err::Failure(_)) => Err(nom::Err::Failure(
+                ErrorTree::from_error_kind(input, ErrorKind::Many1),
+            )),
+            Err(_) => Err(nom::Err::Error(ErrorTree::from_error_kind(
+                input,
+                ErrorKind::Many1,
+            ))),
+        },
+    }
+}
+
+/// The block content of a container node, and where it came from
+/// in the container's input, so children parsed from it can be
+/// found in the source again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Content<'a> {
+    pub text: Cow<'a, str>,
+    /// Where each piece of `text` starts in it, and the source it
+    /// came from. Pieces are whole lines, joined by `\n`, or all of
+    /// `text` if it's a slice of the input.
+    pieces: Vec<(usize, Span)>,
+    /// Where the lazy continuation lines start in `text`, which
+    /// don't have the container's markers in the source
+    lazy: Vec<usize>,
+}
+impl<'a> Content<'a> {
+    /// Content that's the slice of the input at `start`
+    pub(crate) fn slice(text: &'a str, start: usize) -> Self {
+        Content {
+            text: Cow::Borrowed(text),
+            pieces: vec![(0, Span::new(start, start + text.len()))],
+            lazy: vec![],
+        }
+    }
+
+    /// Content made up of `lines`, each with the source line it was
+    /// taken from, without its line ending. `lazy` are the indexes of
+    /// the lazy continuation lines.
+    pub(crate) fn lines(lines: Vec<(Cow<'a, str>, Span)>, lazy: &[usize]) -> Self {
+        let mut pieces = Vec::with_capacity(lines.len());
+        let mut offset = 0;
+        for (line, source) in &lines {
+            pieces.push((offset, *source));
+            offset += line.len() + 1;
+        }
+        let lazy = lazy.iter().map(|&i| pieces[i].0).collect();
+        let mut lines: Vec<_> = lines.into_iter().map(|(line, _)| line).collect();
+        let text = if lines.len() == 1 {
+            lines.pop().unwrap()
+        } else {
+            Cow::Owned(lines.join("\n"))
+        };
+        Content { text, pieces, lazy }
+    }
+
+    /// Whether any of the lines `span` of `text` is on is a lazy
+    /// continuation line
+    pub(crate) fn has_lazy_lines(&self, span: Span) -> bool {
+        self.lazy.iter().any(|&start| {
+            start < span.end && (span.start <= start || !self.text[start..span.start].contains('\n'))
+        })
+    }
+
+    /// Where `offset` in `text` is in `input`. Containers only take
+    /// markers and indentation off the start of a line, and put back
+    /// what's left of a tab as spaces, so a piece always ends like its
+    /// source does. Offsets in those spaces are where the rest of the
+    /// line starts.
+    pub(crate) fn source_offset(&self, input: &str, offset: usize) -> usize {
+        self.locate(input, offset).0
+    }
+
+    /// How many of the spaces put back for a tab are at `offset` or
+    /// after it, which the tab in the source already stands for
+    pub(crate) fn tab_spaces(&self, input: &str, offset: usize) -> usize {
+        self.locate(input, offset).1
+    }
+
+    fn locate(&self, input: &str, offset: usize) -> (usize, usize) {
+        let i = self.pieces.partition_point(|&(start, _)| start <= offset);
+        let (start, source) = match i.checked_sub(1) {
+            Some(i) => self.pieces[i],
+            None => return (0, 0),
+        };
+        let end = match self.pieces.get(i) {
+            Some(&(next, _)) => next - 1,
+            None => self.text.len(),
+        };
+        let piece = &self.text[start..end];
+        let original = &input[source.start..source.end];
+        let added = (0..=piece.len())
+            .find(|&i| piece.is_char_boundary(i) && original.ends_with(&piece[i..]))
+            .unwrap_or(piece.len());
+        let column = (offset - start).min(piece.len());
+        let source_offset = source.end - (piece.len() - added) + column.saturating_sub(added);
+        (source_offset, added.saturating_sub(column))
+    }
+}
+
+/// Syntax extensions that aren't part of CommonMark or MDX
+/// itself. Everything is off by default.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct ParseOptions {
+    /// `$$` math blocks and `$` inline math, as in remark-math
+    pub math: bool,
+    /// `:::container`, `::leaf` and `:text` directives, as in
+    /// remark-directive
+    pub directives: bool,
+    /// trailing `{#id .class}` attributes on headings
+    pub heading_attributes: bool,
+    /// GitHub style slugs for every heading without an `#id`
+    pub heading_ids: bool,
+    /// GitHub flavored markdown: tables, task list items and
+    /// `~~strikethrough~~`, as in remark-gfm
+    pub gfm: bool,
+    /// YAML or TOML frontmatter at the start of the document, as in
+    /// remark-frontmatter
+    pub frontmatter: bool,
+}
+
+pub fn mdx_elements(input: &str) -> Result<Vec<MdxAst<'_>>, MdxError> {
+    mdx_elements_with_options(input, ParseOptions::default())
+}
+
+pub fn mdx_elements_with_options(
     input: &str,
-) -> Result<Vec<MdxAst>, ErrorTree<Location>> {
-    final_parser(mdx_elements_internal)(input)
+    options: ParseOptions,
+) -> Result<Vec<MdxAst<'_>>, MdxError> {
+    let mut ast = match document(options)(without_bom(input)) {
+        Ok((_, ast)) => ast,
+        Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => {
+            return Err(MdxError::from_error_tree(input, error))
+        }
+        Err(nom::Err::Incomplete(_)) => {
+            unreachable!("only complete parsers are used")
+        }
+    };
+    if options.heading_ids {
+        slugs::add_heading_ids(&mut ast, options, &mut slugs::Slugger::default());
+    }
+    Ok(ast)
 }
-fn mdx_elements_internal(
+
+/// Parses as much of the input as it can. A block that can't be
+/// parsed is reported, and parsing picks up again after the next
+/// blank line. Input without errors parses the same as it does with
+/// `mdx_elements_with_options`.
+///
+/// Some things that do parse are reported as warnings, like a code
+/// fence that's never closed and turns the rest of the document
+/// into code.
+pub fn mdx_elements_recovering(
     input: &str,
-) -> IResult<&str, Vec<MdxAst>, ErrorTree<&str>> {
-    let (input, _) = multispace0(input)?;
-    let (input, result) = nom::multi::separated_list1(
-        many1_count(newline),
-        mdx_ast,
-    )(input)?;
-    let (input, _) = multispace0(input)?;
-    let (input, _) = nom::combinator::eof(input)?;
-    Ok((input, result))
-}
-
-fn mdx_ast(
+    options: ParseOptions,
+) -> (Vec<MdxAst<'_>>, Vec<MdxError>) {
+    let (blocks, diagnostics) = blocks_recovering(input, options);
+    let mut ast: Vec<_> = blocks.into_iter().map(|(_, node)| node).collect();
+    if options.heading_ids {
+        slugs::add_heading_ids(&mut ast, options, &mut slugs::Slugger::default());
+    }
+    (ast, diagnostics)
+}
+
+/// The blocks `mdx_elements_recovering` finds, each with where it is
+/// in `input`, before heading ids are added
+pub(crate) fn blocks_recovering(
     input: &str,
-) -> IResult<&str, MdxAst, ErrorTree<&str>> {
-    nom::branch::alt((
-        ast_atx_heading,
-        ast_thematic_break,
-        ast_codeblock,
-        ast_paragraph,
-    ))(input)
+    options: ParseOptions,
+) -> (Vec<(Span, MdxAst<'_>)>, Vec<MdxError>) {
+    let mut ast = vec![];
+    let mut diagnostics = vec![];
+    let mut rest = without_bom(input).trim_start_matches([' ', '\t', '\r', '\n']);
+    while !rest.trim().is_empty() {
+        let parsed = if ast.is_empty() && diagnostics.is_empty() {
+            first_ast(options)(rest)
+        } else {
+            mdx_ast(options)(rest)
+        };
+        match parsed {
+            Ok((after, node)) => {
+                if let MdxAst::Codeblock(_) = node {
+                    if let Some(warning) = fenced_codeblocks::unclosed_fence(rest) {
+                        diagnostics.push(warning.shift(input.len() - rest.len()));
+                    }
+                }
+                let start = input.len() - rest.len();
+                ast.push((Span::new(start, input.len() - after.len()), node));
+                let line = after.trim_start_matches([' ', '\t']);
+                if line.is_empty() || line.starts_with(['\n', '\r']) {
+                    rest = skip_blank_lines(after);
+                } else {
+                    diagnostics.push(MdxError::from_error_tree(
+                        input,
+                        ErrorTree::from_error_kind(line, ErrorKind::Eof),
+                    ));
+                    rest = after_next_blank_line(line);
+                }
+            }
+            Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => {
+                let error = MdxError::from_error_tree(input, error);
+                let offset = error.span.start.max(input.len() - rest.len());
+                rest = after_next_blank_line(&input[offset..]);
+                diagnostics.push(error);
+            }
+            Err(nom::Err::Incomplete(_)) => {
+                unreachable!("only complete parsers are used")
+            }
+        }
+    }
+    (ast, diagnostics)
+}
+
+/// A byte order mark isn't part of the document. Errors still
+/// point into the whole input, BOM included.
+fn without_bom(input: &str) -> &str {
+    input.strip_prefix('\u{feff}').unwrap_or(input)
+}
+
+/// Skips the line ending after a block, and any blank lines after it
+fn skip_blank_lines(mut input: &str) -> &str {
+    loop {
+        let line = input.trim_start_matches([' ', '\t']);
+        match lines::eol(line) {
+            Ok((next, _)) => input = next,
+            Err(_) if line.is_empty() => return line,
+            Err(_) => return input,
+        }
+    }
+}
+
+/// Where parsing resumes after an error
+fn after_next_blank_line(input: &str) -> &str {
+    let mut offset = 0;
+    for line in lines::split_lines(input) {
+        offset += line.len();
+        if offset > line.len() && line.trim().is_empty() {
+            return skip_blank_lines(&input[offset..]);
+        }
+    }
+    ""
+}
+
+fn mdx_elements_internal<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<MdxAst<'a>>, ErrorTree<&'a str>>
+{
+    move |input| {
+        let (input, _) = multispace0(input)?;
+        // blocks are separated by line endings, and by any lines
+        // that are only whitespace
+        let (input, result) =
+            nom::multi::separated_list1(lines::separator, mdx_ast(options))(input)?;
+        let (input, _) = multispace0(input)?;
+        let (input, _) = nom::combinator::eof(input)?;
+        Ok((input, result))
+    }
+}
+
+/// The blocks of a whole document, which unlike the content of a
+/// container can start with frontmatter. A document can also be
+/// empty, or nothing but blank lines.
+fn document<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<MdxAst<'a>>, ErrorTree<&'a str>>
+{
+    move |input| {
+        let (input, _) = multispace0(input)?;
+        if input.is_empty() {
+            return Ok((input, vec![]));
+        }
+        let (input, first) = first_ast(options)(input)?;
+        let (input, mut rest) = nom::multi::many0(nom::sequence::preceded(
+            lines::separator,
+            mdx_ast(options),
+        ))(input)?;
+        rest.insert(0, first);
+        let (input, _) = multispace0(input)?;
+        let (input, _) = nom::combinator::eof(input)?;
+        Ok((input, rest))
+    }
+}
+
+/// The first block of a document, which is the only one that can
+/// be frontmatter
+pub(crate) fn first_ast<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    move |input| {
+        if options.frontmatter {
+            if let Ok((rest, frontmatter)) = frontmatter(input) {
+                return Ok((rest, MdxAst::Frontmatter(frontmatter)));
+            }
+        }
+        mdx_ast(options)(input)
+    }
+}
+
+pub(crate) fn mdx_ast<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    use nom::branch::alt;
+    move |input| {
+        // every block other than a paragraph or a table starts with
+        // a marker, so only the blocks the first character could
+        // start are tried. They're tried in the same order either
+        // way, and tables go where paragraphs would.
+        let first = input.trim_start_matches([' ', '\t']).chars().next();
+        match first {
+            Some('#') => alt((
+                ast_atx_heading(options),
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+            Some('-') | Some('*') | Some('_') | Some('+') => alt((
+                ast_thematic_break,
+                ast_list(options),
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+            Some('`') | Some('~') => alt((
+                ast_codeblock,
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+            Some('$') => alt((
+                ast_math(options),
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+            Some(':') => alt((
+                ast_container_directive(options),
+                ast_leaf_directive(options),
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+            Some('>') => alt((
+                ast_block_quote(options),
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+            Some('0'..='9') => alt((
+                ast_list(options),
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+            Some('<') => alt((
+                ast_jsx_element(options),
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+            Some('i') | Some('e') => alt((
+                ast_esm,
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+            Some('{') => alt((
+                ast_expression,
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+            _ => alt((
+                ast_table(options),
+                ast_setext_heading(options),
+                ast_paragraph,
+            ))(input),
+        }
+    }
+}
+
+/// Fails right away for syntax extensions that are turned off,
+/// so they can sit in an `alt` with everything else
+fn disabled<T>(input: &str) -> IResult<&str, T, ErrorTree<&str>> {
+    Err(nom::Err::Error(ErrorTree::from_error_kind(
+        input,
+        ErrorKind::Alt,
+    )))
 }
 
 /// We have to wrap the structs to fit in the MdxAst
-fn ast_atx_heading(
+fn ast_atx_heading<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    move |input| {
+        let (input, atx) = atx_heading_with_options(options)(input)?;
+        Ok((input, MdxAst::ATXHeading(atx)))
+    }
+}
+
+fn ast_esm(input: &str) -> IResult<&str, MdxAst<'_>, ErrorTree<&str>> {
+    let (input, esm) = esm(input)?;
+    Ok((input, MdxAst::Esm(esm)))
+}
+
+fn ast_expression(
     input: &str,
-) -> IResult<&str, MdxAst, ErrorTree<&str>> {
-    let (input, atx) = atx_heading(input)?;
-    Ok((input, MdxAst::ATXHeading(atx)))
+) -> IResult<&str, MdxAst<'_>, ErrorTree<&str>> {
+    let (input, expression) = flow_expression(input)?;
+    Ok((input, MdxAst::Expression(expression)))
+}
+
+fn ast_setext_heading<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    move |input| {
+        let (input, setext) = setext_heading_with_options(options)(input)?;
+        Ok((input, MdxAst::ATXHeading(setext)))
+    }
 }
 
 fn ast_thematic_break(
     input: &str,
-) -> IResult<&str, MdxAst, ErrorTree<&str>> {
+) -> IResult<&str, MdxAst<'_>, ErrorTree<&str>> {
     let (input, thematic_break) = thematic_break(input)?;
     Ok((input, MdxAst::ThematicBreak(thematic_break)))
 }
 
 fn ast_paragraph(
     input: &str,
-) -> IResult<&str, MdxAst, ErrorTree<&str>> {
+) -> IResult<&str, MdxAst<'_>, ErrorTree<&str>> {
     let (input, paragraph) = paragraph(input)?;
     Ok((input, MdxAst::Paragraph(paragraph)))
 }
 
 fn ast_codeblock(
     input: &str,
-) -> IResult<&str, MdxAst, ErrorTree<&str>> {
+) -> IResult<&str, MdxAst<'_>, ErrorTree<&str>> {
     let (input, codeblock) = fenced_codeblock(input)?;
     Ok((input, MdxAst::Codeblock(codeblock)))
 }
 
+fn ast_math<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    move |input| {
+        if !options.math {
+            return disabled(input);
+        }
+        let (input, math) = math(input)?;
+        Ok((input, MdxAst::Math(math)))
+    }
+}
+
+fn ast_table<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    move |input| {
+        if !options.gfm {
+            return disabled(input);
+        }
+        let (input, table) = table(input)?;
+        Ok((input, MdxAst::Table(table)))
+    }
+}
+
+fn ast_leaf_directive<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    move |input| {
+        if !options.directives {
+            return disabled(input);
+        }
+        let (input, leaf) = leaf_directive(input)?;
+        Ok((input, MdxAst::LeafDirective(leaf)))
+    }
+}
+
+fn ast_container_directive<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    move |input| {
+        if !options.directives {
+            return disabled(input);
+        }
+        let (input, container) = container_directive(options)(input)?;
+        Ok((input, MdxAst::ContainerDirective(container)))
+    }
+}
+
+fn ast_block_quote<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    move |input| {
+        let (input, quote) = block_quote(options)(input)?;
+        Ok((input, MdxAst::BlockQuote(quote)))
+    }
+}
+
+fn ast_list<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    move |input| {
+        let (input, list) = list(options)(input)?;
+        Ok((input, MdxAst::List(list)))
+    }
+}
+
+fn ast_jsx_element<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MdxAst<'a>, ErrorTree<&'a str>>
+{
+    move |input| {
+        let (input, element) = jsx_element(options)(input)?;
+        Ok((input, MdxAst::JsxElement(element)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,12 +704,14 @@ mod tests {
     #[test]
     fn parse_heading() {
         assert_eq!(
-            mdx_ast("# boop").unwrap(),
+            mdx_ast(ParseOptions::default())("# boop").unwrap(),
             (
                 "",
                 MdxAst::ATXHeading(ATXHeading {
                     level: 1,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }),
             )
         );
@@ -123,7 +719,7 @@ mod tests {
     #[test]
     fn parse_thematic_break() {
         assert_eq!(
-            mdx_ast("---").unwrap(),
+            mdx_ast(ParseOptions::default())("---").unwrap(),
             (
                 "",
                 MdxAst::ThematicBreak(ThematicBreak {
@@ -137,13 +733,13 @@ mod tests {
     #[test]
     fn parse_codeblock() {
         assert_eq!(
-            mdx_ast("```\n\nconst t = {}\n```").unwrap(),
+            mdx_ast(ParseOptions::default())("```\n\nconst t = {}\n```").unwrap(),
             (
                 "",
                 MdxAst::Codeblock(FencedCodeblock {
-                    language: "",
-                    infostring: "",
-                    code: "\nconst t = {}\n"
+                    language: "".into(),
+                    infostring: "".into(),
+                    code: "\nconst t = {}\n".into()
                 }),
             )
         );
@@ -173,13 +769,54 @@ mod tests_2 {
             vec![
                 MdxAst::ATXHeading(ATXHeading {
                     level: 1,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }),
                 MdxAst::ATXHeading(ATXHeading {
                     level: 2,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }),
             ]
         );
     }
+
+    #[test]
+    fn unclosed_fence_runs_to_the_end() {
+        let input = "# One\n\n```js\nlet a = 1;\n\nlet b = 2;\n\n## Two";
+        let ast = mdx_elements(input).unwrap();
+        assert_eq!(ast.len(), 2);
+        assert!(
+            matches!(&ast[1], MdxAst::Codeblock(codeblock) if codeblock.code == "let a = 1;\n\nlet b = 2;\n\n## Two")
+        );
+
+        let (recovered, diagnostics) = mdx_elements_recovering(input, ParseOptions::default());
+        assert_eq!(recovered, ast);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, crate::mdx_error::ErrorCode::UnclosedFence);
+        assert_eq!(diagnostics[0].span, crate::mdx_error::Span::new(7, 10));
+        assert_eq!(diagnostics[0].severity, crate::processor::Severity::Warning);
+        assert_eq!(
+            diagnostics[0].help.as_deref(),
+            Some("add a closing fence (```) after the code, or everything after it is code")
+        );
+    }
+
+    #[test]
+    fn parse_empty_document() {
+        for input in ["", "\n", " \r\n\t\n", "\u{feff}"] {
+            assert_eq!(mdx_elements(input).unwrap(), vec![], "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn recover_without_errors_matches_strict() {
+        let input = "\n  # One\n\n- a\n- b\n   \n> quote\n\n---\n";
+        assert_eq!(
+            mdx_elements_recovering(input, ParseOptions::default()),
+            (mdx_elements(input).unwrap(), vec![])
+        );
+    }
 }
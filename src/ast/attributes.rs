@@ -0,0 +1,186 @@
+use super::owned;
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, take_while, take_while1},
+    character::complete::{char, multispace0, satisfy},
+    combinator::{opt, recognize},
+    multi::many0,
+    sequence::{delimited, pair, preceded, terminated},
+    IResult,
+};
+use nom_supreme::error::ErrorTree;
+use std::{borrow::Cow, fmt};
+
+/// A single attribute in a `{#id .class key=value}` list.
+///
+/// `#id` and `.class` shortcuts are stored with an `id` and
+/// `class` key respectively, in the order they were written.
+/// Attributes without a value have an empty `value`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Attribute<'a> {
+    pub key: Cow<'a, str>,
+    pub value: Cow<'a, str>,
+}
+impl<'a> Attribute<'a> {
+    pub fn into_owned(self) -> Attribute<'static> {
+        Attribute {
+            key: owned(self.key),
+            value: owned(self.value),
+        }
+    }
+}
+impl<'a> fmt::Display for Attribute<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.key.as_ref() {
+            "id" if is_shortcut_value(&self.value) => write!(f, "#{}", self.value),
+            "class" if is_shortcut_value(&self.value) => write!(f, ".{}", self.value),
+            key if self.value.is_empty() => write!(f, "{}", key),
+            key if self.value.contains('"') => write!(f, "{}='{}'", key, self.value),
+            key => write!(f, "{}=\"{}\"", key, self.value),
+        }
+    }
+}
+
+/// Writes a list of attributes back out, including the braces.
+/// Writes nothing for an empty list.
+pub fn fmt_attributes(f: &mut fmt::Formatter, attributes: &[Attribute]) -> fmt::Result {
+    if attributes.is_empty() {
+        return Ok(());
+    }
+    write!(f, "{{")?;
+    for (i, attribute) in attributes.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", attribute)?;
+    }
+    write!(f, "}}")
+}
+
+fn is_shortcut_char(c: char) -> bool {
+    !c.is_whitespace() && !"\"'<=>`{}.#".contains(c)
+}
+
+fn is_shortcut_value(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(is_shortcut_char)
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_:.".contains(c)
+}
+
+fn shortcut<'a>(
+    marker: char,
+    key: &'static str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Attribute<'a>, ErrorTree<&'a str>> {
+    move |input| {
+        let (input, value) = preceded(char(marker), take_while1(is_shortcut_char))(input)?;
+        Ok((
+            input,
+            Attribute {
+                key: key.into(),
+                value: value.into(),
+            },
+        ))
+    }
+}
+
+fn quoted(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    alt((
+        delimited(char('"'), recognize(opt(is_not("\""))), char('"')),
+        delimited(char('\''), recognize(opt(is_not("'"))), char('\'')),
+    ))(input)
+}
+
+fn key_value(input: &str) -> IResult<&str, Attribute<'_>, ErrorTree<&str>> {
+    let (input, key) = recognize(pair(
+        satisfy(|c| c.is_ascii_alphabetic() || c == '_' || c == ':'),
+        take_while(is_key_char),
+    ))(input)?;
+    let (input, value) = opt(preceded(
+        char('='),
+        alt((
+            quoted,
+            take_while1(|c: char| !c.is_whitespace() && !"\"'<=>`{}".contains(c)),
+        )),
+    ))(input)?;
+    Ok((
+        input,
+        Attribute {
+            key: key.into(),
+            value: value.unwrap_or("").into(),
+        },
+    ))
+}
+
+/// Attribute lists are wrapped in braces and hold any mix of
+/// `#id`, `.class`, `key=value`, `key="quoted value"` and `key`
+/// separated by whitespace.
+///
+/// ```md
+/// {#intro .note .wide title="An intro"}
+/// ```
+pub fn attributes(input: &str) -> IResult<&str, Vec<Attribute<'_>>, ErrorTree<&str>> {
+    delimited(
+        char('{'),
+        preceded(
+            multispace0,
+            many0(terminated(
+                alt((shortcut('#', "id"), shortcut('.', "class"), key_value)),
+                multispace0,
+            )),
+        ),
+        char('}'),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_attributes() {
+        assert_eq!(
+            attributes("{#intro .note .wide title=\"An intro\" hidden size=3} rest").unwrap(),
+            (
+                " rest",
+                vec![
+                    Attribute {
+                        key: "id".into(),
+                        value: "intro".into()
+                    },
+                    Attribute {
+                        key: "class".into(),
+                        value: "note".into()
+                    },
+                    Attribute {
+                        key: "class".into(),
+                        value: "wide".into()
+                    },
+                    Attribute {
+                        key: "title".into(),
+                        value: "An intro".into()
+                    },
+                    Attribute {
+                        key: "hidden".into(),
+                        value: "".into()
+                    },
+                    Attribute {
+                        key: "size".into(),
+                        value: "3".into()
+                    },
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn parse_attributes_empty() {
+        assert_eq!(attributes("{ }").unwrap(), ("", vec![]));
+    }
+
+    #[test]
+    fn parse_attributes_unclosed() {
+        assert!(attributes("{#intro").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use super::{
+    container_children,
+    lines::{indentation, split_lines, trim_eol},
+    paragraphs::interrupts_paragraph,
+    Content, MdxAst, ParseOptions,
+};
+use crate::{
+    mdx_error::Span,
+    stringify::{self, StringifyOptions},
+};
+use nom::{
+    error::{ErrorKind, ParseError},
+    IResult,
+};
+use nom_supreme::error::ErrorTree;
+use std::{borrow::Cow, fmt};
+
+/// ```md
+/// > Any block content,
+/// > - including lists
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BlockQuote<'a> {
+    pub children: Vec<MdxAst<'a>>,
+}
+impl<'a> BlockQuote<'a> {
+    pub fn into_owned(self) -> BlockQuote<'static> {
+        BlockQuote {
+            children: self.children.into_iter().map(MdxAst::into_owned).collect(),
+        }
+    }
+}
+impl<'a> fmt::Display for BlockQuote<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&stringify::block_quote(self, StringifyOptions::default()))
+    }
+}
+
+/// Strips the `>` and the optional space after it from a line
+/// of a block quote. A tab there only counts as one space, and
+/// whatever else it's worth stays part of the content.
+fn strip_marker(line: &str) -> Option<Cow<'_, str>> {
+    let (columns, indent) = indentation(line);
+    if columns > 3 {
+        return None;
+    }
+    let rest = line[indent..].strip_prefix('>')?;
+    if let Some(rest) = rest.strip_prefix('\t') {
+        let tab = 4 - (columns + 1) % 4;
+        return Some(Cow::Owned(format!("{}{}", " ".repeat(tab - 1), rest)));
+    }
+    Some(Cow::Borrowed(rest.strip_prefix(' ').unwrap_or(rest)))
+}
+
+/// The content of the block quote at the start of `input`, with
+/// its `>` markers stripped, and where the quote ends
+pub(crate) fn quote_content(input: &str) -> Option<(Content<'_>, usize)> {
+    let mut lines = vec![];
+    let mut lazy_lines = vec![];
+    let mut lazy = false;
+    let mut offset = 0;
+    let mut end = 0;
+    for line in split_lines(input) {
+        let content = trim_eol(line);
+        let stripped = if let Some(stripped) = strip_marker(content) {
+            lazy = !stripped.trim().is_empty();
+            stripped
+        } else if lazy && !content.trim().is_empty() && !interrupts_paragraph(content) {
+            lazy_lines.push(lines.len());
+            Cow::Borrowed(content)
+        } else {
+            break;
+        };
+        end = offset + content.len();
+        lines.push((stripped, Span::new(offset, end)));
+        offset += line.len();
+    }
+    if lines.is_empty() {
+        return None;
+    }
+    Some((Content::lines(lines, &lazy_lines), end))
+}
+
+/// Block quotes run for as long as lines start with `>`. A
+/// paragraph inside the quote can also carry on without the
+/// marker, as a lazy continuation line.
+pub fn block_quote<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, BlockQuote<'a>, ErrorTree<&'a str>> {
+    move |input| {
+        let (content, end) = quote_content(input).ok_or_else(|| {
+            nom::Err::Error(ErrorTree::from_error_kind(input, ErrorKind::Char))
+        })?;
+        let children = container_children(input, content.text, options)?;
+        Ok((&input[end..], BlockQuote { children }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{ATXHeading, Paragraph};
+
+    #[test]
+    fn parse_block_quote() {
+        assert_eq!(
+            block_quote(ParseOptions::default())("> # Note\n>\n> some\nlazy text\n\nafter")
+                .unwrap(),
+            (
+                "\n\nafter",
+                BlockQuote {
+                    children: vec![
+                        MdxAst::ATXHeading(ATXHeading {
+                            level: 1,
+                            value: "Note".into(),
+                            id: None,
+                            attributes: vec![]
+                        }),
+                        MdxAst::Paragraph(Paragraph {
+                            words: "some\nlazy text".into()
+                        }),
+                    ]
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_nested_block_quote() {
+        let (_, quote) = block_quote(ParseOptions::default())("> > deep").unwrap();
+        assert_eq!(
+            quote.children,
+            vec![MdxAst::BlockQuote(BlockQuote {
+                children: vec![MdxAst::Paragraph(Paragraph {
+                    words: "deep".into()
+                })]
+            })]
+        );
+    }
+
+    #[test]
+    // the tab after `>` is worth one space of marker and two of
+    // content, which isn't enough to make indented code
+    fn parse_block_quote_tab() {
+        assert_eq!(strip_marker(">\tcode").unwrap(), "  code");
+        assert_eq!(strip_marker("  >\tcode").unwrap(), "code");
+        let (rest, quote) = block_quote(ParseOptions::default())(">\ttext\r\n>\r\n").unwrap();
+        assert_eq!(rest, "\r\n");
+        assert_eq!(
+            quote.children,
+            vec![MdxAst::Paragraph(Paragraph {
+                words: "text".into()
+            })]
+        );
+    }
+
+    #[test]
+    fn stringify_block_quote() {
+        let quote = BlockQuote {
+            children: vec![
+                MdxAst::Paragraph(Paragraph { words: "a".into() }),
+                MdxAst::Paragraph(Paragraph { words: "b".into() }),
+            ],
+        };
+        assert_eq!(quote.to_string(), "> a\n>\n> b");
+    }
+}
@@ -0,0 +1,221 @@
+use super::owned;
+use nom::{
+    bytes::complete::take_while1,
+    error::{ErrorKind, ParseError},
+    IResult,
+};
+use nom_supreme::error::ErrorTree;
+use std::{borrow::Cow, fmt};
+
+/// A code span, delimited by backtick strings of equal length.
+///
+/// `value` borrows the content between the delimiters with a single
+/// leading and trailing space (or line ending) stripped when both are
+/// present. Interior line endings are kept as written.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InlineCode<'a> {
+    pub value: Cow<'a, str>,
+}
+impl<'a> InlineCode<'a> {
+    pub fn into_owned(self) -> InlineCode<'static> {
+        InlineCode {
+            value: owned(self.value),
+        }
+    }
+}
+impl<'a> fmt::Display for InlineCode<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // the fence has to be a backtick run that doesn't
+        // appear in the value, otherwise it'd close early
+        let mut size = 1;
+        while backtick_runs(&self.value).any(|run| run == size) {
+            size += 1;
+        }
+        let fence = "`".repeat(size);
+        let needs_padding = self.value.starts_with('`')
+            || self.value.ends_with('`')
+            || (self.value.starts_with(' ')
+                && self.value.ends_with(' ')
+                && !is_all_spaces(&self.value));
+        if needs_padding {
+            write!(f, "{} {} {}", fence, self.value, fence)
+        } else {
+            write!(f, "{}{}{}", fence, self.value, fence)
+        }
+    }
+}
+
+/// lengths of every maximal run of backticks in `input`
+fn backtick_runs(input: &str) -> impl Iterator<Item = usize> + '_ {
+    input
+        .split(|c| c != '`')
+        .map(str::len)
+        .filter(|len| *len > 0)
+}
+
+fn is_space(c: char) -> bool {
+    c == ' ' || c == '\n' || c == '\r'
+}
+
+fn is_all_spaces(input: &str) -> bool {
+    input.chars().all(is_space)
+}
+
+/// Finds the closing backtick string, which has to be a
+/// maximal run of exactly `size` backticks. Returns the
+/// byte offset of the run.
+fn find_closing_fence(input: &str, size: usize) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(start) = input[offset..].find('`') {
+        let start = offset + start;
+        let run = input[start..]
+            .find(|c| c != '`')
+            .unwrap_or(input.len() - start);
+        if run == size {
+            return Some(start);
+        }
+        offset = start + run;
+    }
+    None
+}
+
+/// Code spans open with a backtick string and close with the next
+/// backtick string of the same length.
+///
+/// ```md
+/// `` code with a ` in it ``
+/// ```
+///
+/// If there's no matching backtick string this fails, and the
+/// opening backticks should be treated as literal text.
+pub fn inline_code(input: &str) -> IResult<&str, InlineCode<'_>, ErrorTree<&str>> {
+    let (rest, fence) = take_while1(|c| c == '`')(input)?;
+    let end = find_closing_fence(rest, fence.len())
+        .ok_or_else(|| nom::Err::Error(ErrorTree::from_error_kind(rest, ErrorKind::TakeUntil)))?;
+    let mut value = &rest[..end];
+    // one space (or line ending) is stripped from both sides,
+    // as long as the content isn't entirely spaces
+    if value.len() >= 2
+        && value.starts_with(is_space)
+        && value.ends_with(is_space)
+        && !is_all_spaces(value)
+    {
+        value = &value[1..value.len() - 1];
+    }
+    Ok((
+        &rest[end + fence.len()..],
+        InlineCode {
+            value: value.into(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_inline_code() {
+        assert_eq!(
+            inline_code("`foo` bar").unwrap(),
+            (
+                " bar",
+                InlineCode {
+                    value: "foo".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_inline_code_double_backticks() {
+        assert_eq!(
+            inline_code("`` foo ` bar ``").unwrap(),
+            (
+                "",
+                InlineCode {
+                    value: "foo ` bar".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_inline_code_strips_one_space() {
+        assert_eq!(
+            inline_code("`  ``  `").unwrap(),
+            (
+                "",
+                InlineCode {
+                    value: " `` ".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    // content that's only spaces is left alone
+    fn parse_inline_code_only_spaces() {
+        assert_eq!(
+            inline_code("`  `").unwrap(),
+            ("", InlineCode { value: "  ".into() })
+        );
+    }
+
+    #[test]
+    fn parse_inline_code_line_endings() {
+        assert_eq!(
+            inline_code("``\nfoo\nbar  \nbaz\n``").unwrap(),
+            (
+                "",
+                InlineCode {
+                    value: "foo\nbar  \nbaz".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    // the closing run has to be the same length
+    fn parse_inline_code_unmatched() {
+        assert!(inline_code("```foo``").is_err());
+    }
+
+    #[test]
+    fn parse_inline_code_skips_longer_runs() {
+        assert_eq!(
+            inline_code("`foo``bar``` baz`").unwrap(),
+            (
+                "",
+                InlineCode {
+                    value: "foo``bar``` baz".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn stringify_inline_code() {
+        assert_eq!(
+            InlineCode {
+                value: "foo".into()
+            }
+            .to_string(),
+            "`foo`"
+        );
+        assert_eq!(
+            InlineCode {
+                value: "foo ` bar".into()
+            }
+            .to_string(),
+            "``foo ` bar``"
+        );
+        assert_eq!(
+            InlineCode {
+                value: "`foo`".into()
+            }
+            .to_string(),
+            "`` `foo` ``"
+        );
+    }
+}
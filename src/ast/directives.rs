@@ -0,0 +1,596 @@
+use super::{
+    attributes::{attributes, fmt_attributes, Attribute},
+    esm::esm,
+    headings::atx_heading,
+    jsx::flow_expression,
+    lines::{eol, indentation, split_lines, trim_eol},
+    owned,
+    thematic_breaks::is_thematic_break,
+    Content, MdxAst, ParseOptions,
+};
+use crate::stringify::{self, StringifyOptions};
+use nom::{
+    branch::alt,
+    bytes::complete::{take_while, take_while1},
+    character::complete::{char, satisfy, space0},
+    combinator::{eof, opt, peek, recognize, verify},
+    error::{ErrorKind, ParseError},
+    multi::fold_many_m_n,
+    sequence::pair,
+    IResult,
+};
+use nom_supreme::{error::ErrorTree, tag::complete::tag};
+use std::{borrow::Cow, fmt};
+
+/// `:name[label]{attributes}`, in the middle of some text
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TextDirective<'a> {
+    pub name: Cow<'a, str>,
+    pub label: Option<Cow<'a, str>>,
+    pub attributes: Vec<Attribute<'a>>,
+}
+impl<'a> TextDirective<'a> {
+    pub fn into_owned(self) -> TextDirective<'static> {
+        TextDirective {
+            name: owned(self.name),
+            label: self.label.map(owned),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(Attribute::into_owned)
+                .collect(),
+        }
+    }
+}
+impl<'a> fmt::Display for TextDirective<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, ":")?;
+        fmt_directive(f, &self.name, self.label.as_deref(), &self.attributes)
+    }
+}
+
+/// `::name[label]{attributes}`, on a line by itself
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LeafDirective<'a> {
+    pub name: Cow<'a, str>,
+    pub label: Option<Cow<'a, str>>,
+    pub attributes: Vec<Attribute<'a>>,
+}
+impl<'a> LeafDirective<'a> {
+    pub fn into_owned(self) -> LeafDirective<'static> {
+        LeafDirective {
+            name: owned(self.name),
+            label: self.label.map(owned),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(Attribute::into_owned)
+                .collect(),
+        }
+    }
+}
+impl<'a> fmt::Display for LeafDirective<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "::")?;
+        fmt_directive(f, &self.name, self.label.as_deref(), &self.attributes)
+    }
+}
+
+/// A directive that wraps other blocks
+///
+/// ```md
+/// :::note[Heads up]{.warning}
+/// Any block content, including other directives
+/// :::
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ContainerDirective<'a> {
+    pub name: Cow<'a, str>,
+    pub label: Option<Cow<'a, str>>,
+    pub attributes: Vec<Attribute<'a>>,
+    pub children: Vec<MdxAst<'a>>,
+}
+impl<'a> ContainerDirective<'a> {
+    pub fn into_owned(self) -> ContainerDirective<'static> {
+        ContainerDirective {
+            name: owned(self.name),
+            label: self.label.map(owned),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(Attribute::into_owned)
+                .collect(),
+            children: self.children.into_iter().map(MdxAst::into_owned).collect(),
+        }
+    }
+}
+impl<'a> ContainerDirective<'a> {
+    /// The `name[label]{attributes}` after the colons that open
+    /// the directive
+    pub(crate) fn opening(&self) -> impl fmt::Display + '_ {
+        struct Opening<'d, 'a>(&'d ContainerDirective<'a>);
+        impl fmt::Display for Opening<'_, '_> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let directive = self.0;
+                fmt_directive(
+                    f,
+                    &directive.name,
+                    directive.label.as_deref(),
+                    &directive.attributes,
+                )
+            }
+        }
+        Opening(self)
+    }
+}
+impl<'a> fmt::Display for ContainerDirective<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&stringify::container_directive(
+            self,
+            StringifyOptions::default(),
+        ))
+    }
+}
+
+fn fmt_directive(
+    f: &mut fmt::Formatter,
+    name: &str,
+    label: Option<&str>,
+    attributes: &[Attribute],
+) -> fmt::Result {
+    write!(f, "{}", name)?;
+    if let Some(label) = label {
+        write!(f, "[{}]", label)?;
+    }
+    fmt_attributes(f, attributes)
+}
+
+fn name(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    recognize(pair(
+        satisfy(|c| c.is_ascii_alphabetic()),
+        take_while(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+    ))(input)
+}
+
+/// Labels are wrapped in brackets, which can be nested as long
+/// as they're balanced. Escaped brackets don't count.
+fn label(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    let (input, _) = char('[')(input)?;
+    let mut depth = 0;
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => depth += 1,
+            ']' if depth == 0 => return Ok((&input[i + 1..], &input[..i])),
+            ']' => depth -= 1,
+            _ => (),
+        }
+    }
+    Err(nom::Err::Error(ErrorTree::from_error_kind(
+        input,
+        ErrorKind::TakeUntil,
+    )))
+}
+
+type DirectiveParts<'a> = (Cow<'a, str>, Option<Cow<'a, str>>, Vec<Attribute<'a>>);
+
+fn directive_parts(input: &str) -> IResult<&str, DirectiveParts<'_>, ErrorTree<&str>> {
+    let (input, name) = name(input)?;
+    let (input, label) = opt(label)(input)?;
+    let (input, attributes) = opt(attributes)(input)?;
+    Ok((
+        input,
+        (
+            name.into(),
+            label.map(Cow::Borrowed),
+            attributes.unwrap_or_default(),
+        ),
+    ))
+}
+
+pub fn text_directive(input: &str) -> IResult<&str, TextDirective<'_>, ErrorTree<&str>> {
+    let (input, _) = char(':')(input)?;
+    let (input, (name, label, attributes)) = directive_parts(input)?;
+    Ok((
+        input,
+        TextDirective {
+            name,
+            label,
+            attributes,
+        },
+    ))
+}
+
+pub fn leaf_directive(input: &str) -> IResult<&str, LeafDirective<'_>, ErrorTree<&str>> {
+    // basically anything can start with 0-3 spaces. We don't really care.
+    let (input, _) = fold_many_m_n(0, 3, tag(" "), 0, |acc: u8, _| acc + 1)(input)?;
+    let (input, _) = tag("::")(input)?;
+    let (input, (name, label, attributes)) = directive_parts(input)?;
+    // nothing else is allowed on the line
+    let (input, _) = space0(input)?;
+    let (input, _) = peek(alt((eol, eof)))(input)?;
+    Ok((
+        input,
+        LeafDirective {
+            name,
+            label,
+            attributes,
+        },
+    ))
+}
+
+/// the number of colons on a line that's nothing but colons,
+/// which is what a closing fence looks like
+fn closing_fence_size(line: &str) -> Option<usize> {
+    let colons = line.len() - line.trim_start_matches(':').len();
+    if colons >= 3 && line[colons..].trim().is_empty() {
+        Some(colons)
+    } else {
+        None
+    }
+}
+
+/// the number of colons on a line that opens a container, which
+/// is at least three colons and a name, label and attributes with
+/// nothing after them
+fn opening_fence_size(line: &str) -> Option<usize> {
+    let colons = line.len() - line.trim_start_matches(':').len();
+    if colons < 3 {
+        return None;
+    }
+    let (rest, _) = directive_parts(&line[colons..]).ok()?;
+    if rest.trim().is_empty() {
+        Some(colons)
+    } else {
+        None
+    }
+}
+
+/// the length of a math fence opening line, which can't have
+/// dollars in its meta
+fn math_fence(line: &str) -> Option<(char, usize)> {
+    let size = line.len() - line.trim_start_matches('$').len();
+    if size >= 2 && !line[size..].contains('$') {
+        Some(('$', size))
+    } else {
+        None
+    }
+}
+
+/// the character and length of a code fence opening or closing line
+fn code_fence(line: &str) -> Option<(char, usize)> {
+    let c = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let size = line.len() - line.trim_start_matches(c).len();
+    if size >= 3 {
+        Some((c, size))
+    } else {
+        None
+    }
+}
+
+/// whatever follows the ESM or expression at the start of `input`
+fn after_javascript(input: &str) -> Option<&str> {
+    match esm(input) {
+        Ok((after, _)) => Some(after),
+        Err(_) => flow_expression(input).ok().map(|(after, _)| after),
+    }
+}
+
+/// Whether a paragraph is open after `line`, which isn't a fence.
+/// Most lines start or carry on one, but blank lines, headings,
+/// thematic breaks and setext underlines end it, and a leaf
+/// directive is a block of its own.
+fn continues_paragraph(line: &str, paragraph: bool) -> bool {
+    let underline = paragraph && line.trim_end().trim_start_matches('=').is_empty();
+    let leaf = !paragraph && leaf_directive(line).is_ok_and(|(rest, _)| rest.trim().is_empty());
+    !(line.trim().is_empty()
+        || underline
+        || leaf
+        || is_thematic_break(line)
+        || atx_heading(line).is_ok())
+}
+
+/// Finds where the content of a container that was opened with
+/// `size` colons ends. Nested containers are tracked so their
+/// closing fences don't close this one, and nothing in fenced
+/// code, math, ESM or expressions counts at all, other than a
+/// closing fence. A nested container can't interrupt a paragraph,
+/// so an opening line there is only more text, as is a fence
+/// that's too short to close anything.
+///
+/// Returns the content and whatever follows the closing fence.
+/// Containers that are never closed run to the end of the input.
+fn split_container_content(input: &str, size: usize, options: ParseOptions) -> (&str, &str) {
+    let mut open = vec![size];
+    let mut code: Option<(char, usize)> = None;
+    // where the ESM or expression the line is in ends
+    let mut javascript = 0;
+    // whether the line before left a paragraph open
+    let mut paragraph = false;
+    let mut offset = 0;
+    for line in split_lines(input) {
+        let content = trim_eol(line);
+        let (columns, indent) = indentation(content);
+        let trimmed = &content[indent..];
+        if let Some((c, code_size)) = code {
+            let run = trimmed.len() - trimmed.trim_start_matches(c).len();
+            if columns <= 3 && run >= code_size && trimmed[run..].trim().is_empty() {
+                code = None;
+            }
+        } else if columns <= 3
+            && closing_fence_size(trimmed)
+                .is_some_and(|closing| open.last().is_some_and(|innermost| closing >= *innermost))
+        {
+            open.pop();
+            if open.is_empty() {
+                let value = trim_eol(&input[..offset]);
+                return (value, &input[offset + content.len()..]);
+            }
+            javascript = 0;
+            paragraph = false;
+        } else if offset < javascript {
+            // only a closing fence counts in ESM or an expression
+        } else if columns <= 3 && paragraph {
+            // of the lines that start something, only code fences
+            // interrupt a paragraph
+            if let Some(fence) = code_fence(trimmed) {
+                code = Some(fence);
+                paragraph = false;
+            } else {
+                paragraph = continues_paragraph(trimmed, true);
+            }
+        } else if columns <= 3 {
+            let math = || math_fence(trimmed).filter(|_| options.math);
+            if let Some(fence) = code_fence(trimmed).or_else(math) {
+                code = Some(fence);
+            } else if let Some(nested) = opening_fence_size(trimmed) {
+                open.push(nested);
+            } else if let Some(after) = after_javascript(&input[offset..]) {
+                javascript = input.len() - after.len();
+            } else {
+                paragraph = continues_paragraph(trimmed, false);
+            }
+        } else {
+            // there's no indented code, so this is paragraph text
+            paragraph = !trimmed.is_empty();
+        }
+        offset += line.len();
+    }
+    (input, "")
+}
+
+/// A container directive's name, label and attributes, and the
+/// content between its fences
+fn container_parts(
+    start: &str,
+    options: ParseOptions,
+) -> IResult<&str, (DirectiveParts<'_>, Content<'_>), ErrorTree<&str>> {
+    // basically anything can start with 0-3 spaces. We don't really care.
+    let (input, _) = fold_many_m_n(0, 3, tag(" "), 0, |acc: u8, _| acc + 1)(start)?;
+    let (input, fence) =
+        verify(take_while1(|c| c == ':'), |fence: &str| fence.len() >= 3)(input)?;
+    let (input, parts) = directive_parts(input)?;
+    let (input, _) = space0(input)?;
+    let (input, _) = alt((eol, eof))(input)?;
+
+    let (content, rest) = split_container_content(input, fence.len(), options);
+    let content = Content::slice(content, start.len() - input.len());
+    Ok((rest, (parts, content)))
+}
+
+/// The content of the container directive at the start of `input`
+pub(crate) fn directive_content(input: &str, options: ParseOptions) -> Option<Content<'_>> {
+    container_parts(input, options).ok().map(|(_, (_, content))| content)
+}
+
+/// Container directives open with three or more colons and close
+/// with a line of at least as many colons. Everything in between
+/// is parsed as regular block content.
+pub fn container_directive<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, ContainerDirective<'a>, ErrorTree<&'a str>> {
+    move |input| {
+        let (input, ((name, label, attributes), content)) = container_parts(input, options)?;
+        let children = super::container_children(input, content.text, options)?;
+        Ok((
+            input,
+            ContainerDirective {
+                name,
+                label,
+                attributes,
+                children,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Paragraph, ThematicBreak};
+
+    #[test]
+    fn parse_text_directive() {
+        assert_eq!(
+            text_directive(":abbr[HTML]{title=\"HyperText Markup Language\"} rest").unwrap(),
+            (
+                " rest",
+                TextDirective {
+                    name: "abbr".into(),
+                    label: Some("HTML".into()),
+                    attributes: vec![Attribute {
+                        key: "title".into(),
+                        value: "HyperText Markup Language".into()
+                    }]
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_text_directive_nested_brackets() {
+        assert_eq!(
+            text_directive(":kbd[a [nested] \\] label]").unwrap(),
+            (
+                "",
+                TextDirective {
+                    name: "kbd".into(),
+                    label: Some("a [nested] \\] label".into()),
+                    attributes: vec![]
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_text_directive_needs_name() {
+        assert!(text_directive(":30").is_err());
+        assert!(text_directive(": note").is_err());
+    }
+
+    #[test]
+    fn parse_leaf_directive() {
+        assert_eq!(
+            leaf_directive("::youtube[Video]{#v123}  \nnext").unwrap(),
+            (
+                "\nnext",
+                LeafDirective {
+                    name: "youtube".into(),
+                    label: Some("Video".into()),
+                    attributes: vec![Attribute {
+                        key: "id".into(),
+                        value: "v123".into()
+                    }]
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_leaf_directive_trailing_content() {
+        assert!(leaf_directive("::youtube and more").is_err());
+    }
+
+    #[test]
+    fn parse_container_directive() {
+        assert_eq!(
+            container_directive(ParseOptions::default())(
+                ":::note[Heads up]{.warning}\nsome text\n\n---\n:::\n\nafter"
+            )
+            .unwrap(),
+            (
+                "\n\nafter",
+                ContainerDirective {
+                    name: "note".into(),
+                    label: Some("Heads up".into()),
+                    attributes: vec![Attribute {
+                        key: "class".into(),
+                        value: "warning".into()
+                    }],
+                    children: vec![
+                        MdxAst::Paragraph(Paragraph {
+                            words: "some text".into()
+                        }),
+                        MdxAst::ThematicBreak(ThematicBreak {
+                            char_count: 3,
+                            break_char: '-'
+                        }),
+                    ]
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_container_directive_nested() {
+        let options = ParseOptions {
+            directives: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            container_directive(options)("::::outer\n:::inner\n:::\n\n```\n:::\n```\n::::")
+                .unwrap(),
+            (
+                "",
+                ContainerDirective {
+                    name: "outer".into(),
+                    label: None,
+                    attributes: vec![],
+                    children: vec![
+                        MdxAst::ContainerDirective(ContainerDirective {
+                            name: "inner".into(),
+                            label: None,
+                            attributes: vec![],
+                            children: vec![]
+                        }),
+                        MdxAst::Codeblock(crate::ast::FencedCodeblock {
+                            language: "".into(),
+                            infostring: "".into(),
+                            code: ":::\n".into()
+                        }),
+                    ]
+                }
+            )
+        );
+    }
+
+    #[test]
+    // a shorter closing fence doesn't close the container
+    fn parse_container_directive_fence_length() {
+        let (rest, directive) =
+            container_directive(ParseOptions::default())("::::note\n:::\n::::").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            directive.children,
+            vec![MdxAst::Paragraph(Paragraph {
+                words: ":::".into()
+            })]
+        );
+    }
+
+    #[test]
+    // nested containers can't interrupt a paragraph, so the opening
+    // line is text and the fence after it closes the outer one
+    fn parse_container_directive_opening_in_paragraph() {
+        let options = ParseOptions {
+            directives: true,
+            ..ParseOptions::default()
+        };
+        let (rest, directive) =
+            container_directive(options)(":::note\nx\n:::note\ny\n:::\nafter").unwrap();
+        assert_eq!(rest, "\nafter");
+        assert_eq!(
+            directive.children,
+            vec![MdxAst::Paragraph(Paragraph {
+                words: "x\n:::note\ny".into()
+            })]
+        );
+    }
+
+    #[test]
+    fn stringify_container_directive() {
+        let directive = ContainerDirective {
+            name: "note".into(),
+            label: Some("Heads up".into()),
+            attributes: vec![Attribute {
+                key: "class".into(),
+                value: "warning".into(),
+            }],
+            children: vec![
+                MdxAst::Paragraph(Paragraph {
+                    words: "one".into(),
+                }),
+                MdxAst::Paragraph(Paragraph {
+                    words: "two".into(),
+                }),
+            ],
+        };
+        assert_eq!(
+            directive.to_string(),
+            ":::note[Heads up]{.warning}\none\n\ntwo\n:::"
+        );
+    }
+}
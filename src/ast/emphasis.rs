@@ -0,0 +1,77 @@
+//! The delimiter runs that emphasis and strong text are made of.
+//!
+//! Emphasis isn't a node of the AST, it's left in the text of the
+//! inline nodes. Formatting rewrites its delimiters and editors
+//! highlight it, so they find the runs the same way here.
+use std::ops::Range;
+
+/// A run of `*` or `_` that might open or close emphasis
+pub(crate) struct DelimiterRun {
+    pub c: char,
+    pub range: Range<usize>,
+    pub left_flanking: bool,
+    pub right_flanking: bool,
+    pub punctuation_before: bool,
+    pub punctuation_after: bool,
+}
+impl DelimiterRun {
+    pub fn can_open(&self) -> bool {
+        self.left_flanking && (self.c == '*' || !self.right_flanking || self.punctuation_before)
+    }
+
+    pub fn can_close(&self) -> bool {
+        self.right_flanking && (self.c == '*' || !self.left_flanking || self.punctuation_after)
+    }
+}
+
+pub(crate) fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || (!c.is_alphanumeric() && !c.is_whitespace())
+}
+
+/// The runs in the parts of `text` in `ranges`, which are its plain
+/// text. Whatever is between them, like a code span, counts as a
+/// letter next to a run.
+pub(crate) fn delimiter_runs(text: &str, ranges: &[Range<usize>]) -> Vec<DelimiterRun> {
+    let in_text = |i: usize| ranges.iter().any(|range| range.contains(&i));
+    let mut runs = vec![];
+    for range in ranges {
+        let mut chars = text[range.clone()].char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if c == '\\' {
+                chars.next();
+                continue;
+            }
+            if c != '*' && c != '_' {
+                continue;
+            }
+            let start = range.start + i;
+            let mut end = start + 1;
+            while chars.next_if(|(_, next)| *next == c).is_some() {
+                end += 1;
+            }
+            let before = match text[..start].chars().next_back() {
+                Some(before) if !in_text(start - before.len_utf8()) => 'a',
+                Some(before) => before,
+                None => ' ',
+            };
+            let after = match text[end..].chars().next() {
+                Some(_) if !in_text(end) => 'a',
+                Some(after) => after,
+                None => ' ',
+            };
+            let left_flanking = !after.is_whitespace()
+                && (!is_punctuation(after) || before.is_whitespace() || is_punctuation(before));
+            let right_flanking = !before.is_whitespace()
+                && (!is_punctuation(before) || after.is_whitespace() || is_punctuation(after));
+            runs.push(DelimiterRun {
+                c,
+                range: start..end,
+                left_flanking,
+                right_flanking,
+                punctuation_before: is_punctuation(before),
+                punctuation_after: is_punctuation(after),
+            });
+        }
+    }
+    runs
+}
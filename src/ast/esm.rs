@@ -0,0 +1,244 @@
+use super::{
+    lines::{split_lines, trim_eol},
+    owned,
+};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till, take_while, take_while1},
+    character::complete::{char, multispace0, multispace1},
+    combinator::{map, opt, recognize},
+    error::{ErrorKind, ParseError},
+    multi::separated_list1,
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
+};
+use nom_supreme::error::ErrorTree;
+use std::{borrow::Cow, fmt};
+
+/// Import and export statements, kept exactly as they were written
+///
+/// ```md
+/// import { Chart } from '../components/chart'
+/// export const meta = { title: 'Sales' }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Esm<'a> {
+    pub value: Cow<'a, str>,
+}
+impl<'a> Esm<'a> {
+    pub fn into_owned(self) -> Esm<'static> {
+        Esm {
+            value: owned(self.value),
+        }
+    }
+}
+impl<'a> Esm<'a> {
+    /// Every binding the `import` declarations in here bring into
+    /// scope. Declarations that can't be read, like ones in the middle
+    /// of a line, are skipped.
+    pub fn imports(&self) -> Vec<Import<'_>> {
+        let value: &str = &self.value;
+        let mut imports = vec![];
+        let mut offset = 0;
+        for line in split_lines(value) {
+            if let Ok((_, (names, source))) = import_declaration(&value[offset..]) {
+                imports.extend(names.into_iter().map(|local| Import {
+                    local,
+                    offset: local.as_ptr() as usize - value.as_ptr() as usize,
+                    source,
+                }));
+            }
+            offset += line.len();
+        }
+        imports
+    }
+}
+
+/// A name an `import` declaration brings into scope, like `Chart` in
+/// `import { Chart } from './chart'`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Import<'e> {
+    /// The name the binding goes by in the document
+    pub local: &'e str,
+    /// Where `local` is, in bytes from the start of the ESM
+    pub offset: usize,
+    /// What it's imported from, like `./chart`
+    pub source: &'e str,
+}
+
+fn identifier(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    recognize(pair(
+        take_while1(|c: char| c.is_alphabetic() || c == '_' || c == '$'),
+        take_while(|c: char| c.is_alphanumeric() || c == '_' || c == '$'),
+    ))(input)
+}
+
+fn string_literal(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    alt((
+        delimited(
+            char('\''),
+            take_till(|c| c == '\'' || c == '\n'),
+            char('\''),
+        ),
+        delimited(char('"'), take_till(|c| c == '"' || c == '\n'), char('"')),
+    ))(input)
+}
+
+/// `name` or `name as local`
+fn import_specifier(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    let (input, name) = alt((identifier, string_literal))(input)?;
+    let (input, alias) = opt(preceded(
+        delimited(multispace1, tag("as"), multispace1),
+        identifier,
+    ))(input)?;
+    Ok((input, alias.unwrap_or(name)))
+}
+
+/// `{ a, b as c }`, which can have a trailing comma
+fn named_imports(input: &str) -> IResult<&str, Vec<&str>, ErrorTree<&str>> {
+    let separator = || delimited(multispace0, char(','), multispace0);
+    delimited(
+        pair(char('{'), multispace0),
+        map(
+            opt(terminated(
+                separated_list1(separator(), import_specifier),
+                opt(separator()),
+            )),
+            Option::unwrap_or_default,
+        ),
+        pair(multispace0, char('}')),
+    )(input)
+}
+
+/// `* as name`
+fn namespace_import(input: &str) -> IResult<&str, Vec<&str>, ErrorTree<&str>> {
+    map(
+        preceded(
+            tuple((char('*'), multispace0, tag("as"), multispace1)),
+            identifier,
+        ),
+        |name| vec![name],
+    )(input)
+}
+
+/// The names an import brings in and where they come from. Imports
+/// only for their side effects, like `import './styles.css'`, don't
+/// bring in any.
+fn import_declaration(input: &str) -> IResult<&str, (Vec<&str>, &str), ErrorTree<&str>> {
+    let (input, _) = terminated(tag("import"), multispace0)(input)?;
+    if let Ok((input, source)) = string_literal(input) {
+        return Ok((input, (vec![], source)));
+    }
+    let default_import = map(
+        pair(
+            identifier,
+            opt(preceded(
+                delimited(multispace0, char(','), multispace0),
+                alt((namespace_import, named_imports)),
+            )),
+        ),
+        |(default, rest)| {
+            let mut names = vec![default];
+            names.extend(rest.unwrap_or_default());
+            names
+        },
+    );
+    let (input, names) = alt((namespace_import, named_imports, default_import))(input)?;
+    let (input, _) = delimited(multispace0, tag("from"), multispace0)(input)?;
+    let (input, source) = string_literal(input)?;
+    Ok((input, (names, source)))
+}
+
+impl<'a> fmt::Display for Esm<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+/// ESM starts with `import` or `export` at the very start of a
+/// line, and runs to the next blank line. The JavaScript itself
+/// isn't checked.
+pub fn esm(input: &str) -> IResult<&str, Esm<'_>, ErrorTree<&str>> {
+    let keyword = ["import", "export"]
+        .iter()
+        .find_map(|keyword| input.strip_prefix(keyword));
+    if !keyword.is_some_and(|rest| rest.starts_with([' ', '\t'])) {
+        return Err(nom::Err::Error(ErrorTree::from_error_kind(
+            input,
+            ErrorKind::Tag,
+        )));
+    }
+    let mut offset = 0;
+    let mut end = 0;
+    for line in split_lines(input) {
+        let content = trim_eol(line);
+        if content.trim().is_empty() {
+            break;
+        }
+        end = offset + content.len();
+        offset += line.len();
+    }
+    Ok((
+        &input[end..],
+        Esm {
+            value: input[..end].into(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_esm() {
+        assert_eq!(
+            esm("import {\n  Chart,\n} from './chart'\nexport const a = 1\n\n# Title").unwrap(),
+            (
+                "\n\n# Title",
+                Esm {
+                    value: "import {\n  Chart,\n} from './chart'\nexport const a = 1".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn imports() {
+        let esm = Esm {
+            value: "import Chart, {Bar as Bars, Line,} from './chart'
+import * as Icons from \"icons\"
+import {
+  Table
+} from '../table'
+import './styles.css'
+export const meta = {}"
+                .into(),
+        };
+        let imports = esm.imports();
+        let names: Vec<_> = imports
+            .iter()
+            .map(|import| (import.local, import.source))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Chart", "./chart"),
+                ("Bars", "./chart"),
+                ("Line", "./chart"),
+                ("Icons", "icons"),
+                ("Table", "../table"),
+            ]
+        );
+        let bars = &imports[1];
+        assert_eq!(&esm.value[bars.offset..bars.offset + 4], "Bars");
+        let table = &imports[4];
+        assert_eq!(&esm.value[table.offset..table.offset + 5], "Table");
+    }
+
+    #[test]
+    fn parse_esm_needs_keyword() {
+        assert!(esm("important things").is_err());
+        assert!(esm(" import a from 'a'").is_err());
+    }
+}
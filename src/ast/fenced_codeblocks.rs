@@ -1,43 +1,74 @@
+use super::{
+    lines::{eol, indentation, split_lines, till_eol, trim_eol},
+    owned,
+};
+use crate::{
+    mdx_error::{ErrorCode, MdxError, Span},
+    processor::Severity,
+    stringify::{self, StringifyOptions},
+};
 use nom::{
     branch::alt,
-    bytes::complete::take_until,
     character::complete::{char, space0},
+    combinator::eof,
     multi::fold_many_m_n,
     IResult,
 };
 use nom_supreme::{error::ErrorTree, tag::complete::tag};
-use std::fmt;
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+use std::{borrow::Cow, fmt};
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct FencedCodeblock<'a> {
-    pub language: &'a str,
-    pub infostring: &'a str,
-    pub code: &'a str,
+    pub language: Cow<'a, str>,
+    pub infostring: Cow<'a, str>,
+    pub code: Cow<'a, str>,
+}
+impl<'a> FencedCodeblock<'a> {
+    pub fn into_owned(self) -> FencedCodeblock<'static> {
+        FencedCodeblock {
+            language: owned(self.language),
+            infostring: owned(self.infostring),
+            code: owned(self.code),
+        }
+    }
 }
 impl fmt::Display for FencedCodeblock<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "```
-{}
-```",
-            self.code.to_string()
-        )
+        f.write_str(&stringify::codeblock(self, StringifyOptions::default()))
     }
 }
 
+/// A closing fence is up to three spaces of indentation, at least
+/// as many of the opening fence's character and nothing else.
+fn is_closing_fence(line: &str, c: char, size: usize) -> bool {
+    let (indent, bytes) = indentation(line);
+    let line = &line[bytes..];
+    let run = line.len() - line.trim_start_matches(c).len();
+    indent <= 3 && run >= size && line[run..].trim().is_empty()
+}
+
 fn parse_infostring(
     input: &str,
 ) -> IResult<&str, &str, ErrorTree<&str>> {
     let (input, _) = space0(input)?;
-    let (input, infostring) = take_until("\n")(input)?;
-    let (input, _) =
-        nom::character::complete::line_ending(input)?;
+    let (input, infostring) = till_eol(input)?;
+    let (input, _) = alt((eol, eof))(input)?;
     Ok((input, infostring))
 }
 
+/// A fence that isn't closed runs to the end of the document, or of
+/// the block quote, list item or other container it's in, as in
+/// CommonMark.
 pub fn fenced_codeblock(
     input: &str,
-) -> IResult<&str, FencedCodeblock, ErrorTree<&str>> {
+) -> IResult<&str, FencedCodeblock<'_>, ErrorTree<&str>> {
+    let (input, (codeblock, _)) = fenced(input)?;
+    Ok((input, codeblock))
+}
+
+/// A code block, and the size of its fence if it's never closed
+fn fenced(
+    input: &str,
+) -> IResult<&str, (FencedCodeblock<'_>, Option<usize>), ErrorTree<&str>> {
     // basically anything can start with 0-3 spaces. We don't really care.
     let (input, _) =
         fold_many_m_n(0, 3, tag(" "), 0, |acc: u8, _| {
@@ -55,29 +86,64 @@ pub fn fenced_codeblock(
         1000,
         char(c),
         0,
-        |acc: u8, _| acc + 1,
+        |acc: usize, _| acc + 1,
     )(input)?;
     let (input, infostring) = parse_infostring(input)?;
-    // infostring parsing
-    // end at newline
-    //code vv
-    // : IResult<&str, &str, ErrorTree<&str>>
-    let (input, code) = take_until("```")(input)?;
-    let (input, _) = tag("```")(input)?;
+    let size = num_break_chars + 1;
+    let mut offset = 0;
+    for line in split_lines(input) {
+        let content = trim_eol(line);
+        if is_closing_fence(content, c, size) {
+            return Ok((
+                &input[offset + content.len()..],
+                (
+                    FencedCodeblock {
+                        language: "".into(),
+                        infostring: infostring.into(),
+                        code: input[..offset].into(),
+                    },
+                    None,
+                ),
+            ));
+        }
+        offset += line.len();
+    }
     Ok((
-        input,
-        FencedCodeblock {
-            language: "",
-            infostring,
-            code: code,
-        },
+        "",
+        (
+            FencedCodeblock {
+                language: "".into(),
+                infostring: infostring.into(),
+                code: input.into(),
+            },
+            Some(size),
+        ),
     ))
 }
 
+/// A warning for the code block at the start of `input`, if its
+/// fence is never closed. That's allowed, but it's usually a
+/// mistake, since everything after it becomes code.
+pub(crate) fn unclosed_fence(input: &str) -> Option<MdxError> {
+    let (_, (_, size)) = fenced(input).ok()?;
+    let size = size?;
+    let (_, indent) = indentation(input);
+    let fence = Span::new(indent, indent + size);
+    let closing = &input[indent..indent + size];
+    Some(
+        MdxError::new(ErrorCode::UnclosedFence, "unclosed code fence", fence)
+            .with_label(fence, "this fence is never closed")
+            .with_help(format!(
+                "add a closing fence ({}) after the code, or everything after it is code",
+                closing
+            ))
+            .with_severity(Severity::Warning),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use nom::{error::ErrorKind, Err::Error};
 
     #[test]
     fn parse_fenced_codeblock() {
@@ -86,9 +152,9 @@ mod tests {
             (
                 "",
                 FencedCodeblock {
-                    language: "",
-                    infostring: "",
-                    code: "\n",
+                    language: "".into(),
+                    infostring: "".into(),
+                    code: "\n".into(),
                 }
             )
         );
@@ -105,9 +171,9 @@ const t = {};
             (
                 "",
                 FencedCodeblock {
-                    language: "",
-                    infostring: "js title=something.txt",
-                    code: "const t = {};\n",
+                    language: "".into(),
+                    infostring: "js title=something.txt".into(),
+                    code: "const t = {};\n".into(),
                 }
             )
         );
@@ -125,11 +191,21 @@ const t = {};
             (
                 "",
                 FencedCodeblock {
-                    language: "",
-                    infostring: "js title=something.txt",
-                    code: "const t = {};\n",
+                    language: "".into(),
+                    infostring: "js title=something.txt".into(),
+                    code: "const t = {};\n".into(),
                 }
             )
         );
     }
+
+    #[test]
+    fn parse_fenced_codeblock_crlf() {
+        let (rest, codeblock) =
+            fenced_codeblock("```js\r\nconst t = {};\r\n```\r\nafter").unwrap();
+        assert_eq!(rest, "\r\nafter");
+        assert_eq!(codeblock.infostring, "js");
+        assert_eq!(codeblock.code, "const t = {};\r\n");
+        assert_eq!(codeblock.to_string(), "```js\nconst t = {};\r\n```");
+    }
 }
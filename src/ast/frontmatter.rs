@@ -0,0 +1,158 @@
+use super::{
+    lines::{split_lines, trim_eol},
+    owned,
+};
+use nom::{
+    error::{ErrorKind, ParseError},
+    IResult,
+};
+use nom_supreme::error::ErrorTree;
+use std::{borrow::Cow, fmt};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FrontmatterKind {
+    /// Fenced by `---`
+    Yaml,
+    /// Fenced by `+++`
+    Toml,
+}
+impl FrontmatterKind {
+    fn fence(self) -> &'static str {
+        match self {
+            FrontmatterKind::Yaml => "---",
+            FrontmatterKind::Toml => "+++",
+        }
+    }
+}
+
+/// Metadata at the start of a document, as in remark-frontmatter.
+/// It's only ever the first block.
+///
+/// ```md
+/// ---
+/// title: Getting started
+/// ---
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Frontmatter<'a> {
+    pub kind: FrontmatterKind,
+    pub value: Cow<'a, str>,
+}
+impl<'a> Frontmatter<'a> {
+    pub fn into_owned(self) -> Frontmatter<'static> {
+        Frontmatter {
+            kind: self.kind,
+            value: owned(self.value),
+        }
+    }
+}
+impl<'a> fmt::Display for Frontmatter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fence = self.kind.fence();
+        if self.value.is_empty() {
+            write!(f, "{}\n{}", fence, fence)
+        } else {
+            write!(f, "{}\n{}\n{}", fence, self.value, fence)
+        }
+    }
+}
+
+/// The kind of frontmatter `line` opens, if it's a fence. Fences
+/// aren't indented, and have nothing but whitespace after them.
+fn fence(line: &str) -> Option<FrontmatterKind> {
+    [FrontmatterKind::Yaml, FrontmatterKind::Toml]
+        .iter()
+        .copied()
+        .find(|kind| {
+            line.strip_prefix(kind.fence())
+                .is_some_and(|rest| rest.trim().is_empty())
+        })
+}
+
+/// Whether `input` starts with a line that opens frontmatter. Until
+/// the closing fence is there it's something else, like a thematic
+/// break, but typing it changes the whole block.
+pub(crate) fn opens_frontmatter(input: &str) -> bool {
+    split_lines(input).next().map(trim_eol).and_then(fence).is_some()
+}
+
+/// Frontmatter runs from an opening fence to the next line with the
+/// same fence. If there isn't one, it isn't frontmatter.
+pub fn frontmatter(input: &str) -> IResult<&str, Frontmatter<'_>, ErrorTree<&str>> {
+    let mut lines = split_lines(input);
+    let kind = lines
+        .next()
+        .and_then(|line| fence(trim_eol(line)).map(|kind| (kind, line.len())));
+    let (kind, mut offset) = match kind {
+        Some(opening) => opening,
+        None => {
+            return Err(nom::Err::Error(ErrorTree::from_error_kind(
+                input,
+                ErrorKind::Tag,
+            )))
+        }
+    };
+    let start = offset;
+    for line in lines {
+        let content = trim_eol(line);
+        if fence(content) == Some(kind) {
+            let value = trim_eol(&input[start..offset]);
+            return Ok((
+                &input[offset + content.len()..],
+                Frontmatter {
+                    kind,
+                    value: value.into(),
+                },
+            ));
+        }
+        offset += line.len();
+    }
+    Err(nom::Err::Error(ErrorTree::from_error_kind(
+        input,
+        ErrorKind::TakeUntil,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_frontmatter() {
+        assert_eq!(
+            frontmatter("---\ntitle: Intro\ntags: [a]\n---\n\n# Intro").unwrap(),
+            (
+                "\n\n# Intro",
+                Frontmatter {
+                    kind: FrontmatterKind::Yaml,
+                    value: "title: Intro\ntags: [a]".into()
+                }
+            )
+        );
+        assert_eq!(
+            frontmatter("+++\r\n+++").unwrap(),
+            (
+                "",
+                Frontmatter {
+                    kind: FrontmatterKind::Toml,
+                    value: "".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_frontmatter_fences() {
+        // unclosed, closed by the other kind, or not a fence at all
+        assert!(frontmatter("---\ntitle: Intro\n").is_err());
+        assert!(frontmatter("---\ntitle: Intro\n+++").is_err());
+        assert!(frontmatter(" ---\na\n---").is_err());
+        assert!(frontmatter("----\na\n----").is_err());
+    }
+
+    #[test]
+    fn stringify_frontmatter() {
+        let input = "---\ntitle: Intro\n---";
+        assert_eq!(frontmatter(input).unwrap().1.to_string(), input);
+    }
+}
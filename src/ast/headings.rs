@@ -1,24 +1,90 @@
-use nom::{multi::fold_many_m_n, IResult};
+use super::{
+    attributes::{attributes, fmt_attributes, Attribute},
+    inlines::{inlines_with_options, Inline},
+    lines::{eol, indentation, split_lines, till_eol, trim_eol},
+    owned,
+    paragraphs::interrupts_paragraph,
+    ParseOptions,
+};
+use nom::{
+    error::{ErrorKind, ParseError},
+    multi::fold_many_m_n,
+    IResult,
+};
 use nom_supreme::{error::ErrorTree, tag::complete::tag};
-use std::fmt;
+use std::{borrow::Cow, fmt};
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ATXHeading<'a> {
     pub level: u8,
-    pub value: &'a str,
+    pub value: Cow<'a, str>,
+    /// The anchor for the heading. This is the `#id` from the
+    /// heading's attributes, or a generated slug when
+    /// `ParseOptions::heading_ids` is on.
+    pub id: Option<Cow<'a, str>>,
+    /// Trailing `{#id .class}` attributes, when
+    /// `ParseOptions::heading_attributes` is on
+    pub attributes: Vec<Attribute<'a>>,
+}
+impl<'a> ATXHeading<'a> {
+    /// The inline content of the heading, such as code spans
+    pub fn inlines(&self) -> Vec<Inline<'_>> {
+        self.inlines_with_options(ParseOptions::default())
+    }
+
+    pub fn inlines_with_options(&self, options: ParseOptions) -> Vec<Inline<'_>> {
+        inlines_with_options(&self.value, options)
+    }
+
+    /// The attributes the heading is written out with. A generated
+    /// id is written as an `#id` in front of them, so the anchor
+    /// stays the same when the output is parsed again.
+    pub(crate) fn written_attributes(&self) -> Cow<'_, [Attribute<'a>]> {
+        match &self.id {
+            Some(id) if !self.attributes.iter().any(|attribute| attribute.key == "id") => {
+                let id = Attribute {
+                    key: "id".into(),
+                    value: id.clone(),
+                };
+                let mut attributes = vec![id];
+                attributes.extend(self.attributes.iter().cloned());
+                Cow::Owned(attributes)
+            }
+            _ => Cow::Borrowed(&self.attributes),
+        }
+    }
+
+    pub fn into_owned(self) -> ATXHeading<'static> {
+        ATXHeading {
+            level: self.level,
+            value: owned(self.value),
+            id: self.id.map(owned),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(Attribute::into_owned)
+                .collect(),
+        }
+    }
 }
 impl<'a> fmt::Display for ATXHeading<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // Use `self.number` to refer to each positional data point.
-        write!(f, "{} {}", "#".repeat(self.level.into()), self.value)
+        write!(f, "{} {}", "#".repeat(self.level.into()), self.value)?;
+        let attributes = self.written_attributes();
+        if !attributes.is_empty() {
+            write!(f, " ")?;
+            fmt_attributes(f, &attributes)?;
+        }
+        Ok(())
     }
 }
 
-fn inner_heading<'a, F: 'a, O, E: nom::error::ParseError<&'a str>>(
+fn inner_heading<'a, F, O, E: nom::error::ParseError<&'a str>>(
     inner: F,
 ) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
 where
-    F: Fn(&'a str) -> IResult<&'a str, O, E>,
+    F: 'a + Fn(&'a str) -> IResult<&'a str, O, E>,
 {
     nom::sequence::delimited(
         nom::character::complete::space0,
@@ -26,28 +92,150 @@ where
         nom::character::complete::space0,
     )
 }
-pub fn atx_heading(input: &str) -> IResult<&str, ATXHeading, ErrorTree<&str>> {
+pub fn atx_heading(input: &str) -> IResult<&str, ATXHeading<'_>, ErrorTree<&str>> {
     let (input, _) = fold_many_m_n(0, 3, tag(" "), 0, |acc: u8, _| acc + 1)(input)?;
+    // the hashes have to be followed by a space, unless the
+    // heading is empty
     let (input, num_hashes) = nom::sequence::terminated(
-        fold_many_m_n(0, 6, tag("#"), 0, |acc: u8, _| acc + 1),
-        nom::character::complete::multispace1,
+        fold_many_m_n(1, 6, tag("#"), 0, |acc: u8, _| acc + 1),
+        nom::branch::alt((
+            nom::character::complete::space1,
+            nom::combinator::peek(eol),
+            nom::combinator::eof,
+        )),
     )(input)?;
 
     // empty headings are a thing, so any parsing below this is optional
-    let (input, val) = inner_heading(nom::character::complete::not_line_ending)(input)?;
+    let (input, val) = inner_heading(till_eol)(input)?;
     Ok((
         input,
         ATXHeading {
             level: num_hashes,
-            value: val.trim(),
+            value: val.trim().into(),
+            id: None,
+            attributes: vec![],
         },
     ))
 }
 
+/// Splits a trailing attribute list off of a heading's content.
+/// The attributes have to be the last thing in the heading and
+/// be separated from any text before them by a space.
+fn split_attributes(value: &str) -> Option<(&str, Vec<Attribute<'_>>)> {
+    let start = value.rfind('{')?;
+    let (text, rest) = value.split_at(start);
+    if !(text.is_empty() || text.ends_with([' ', '\t'])) {
+        return None;
+    }
+    match attributes(rest) {
+        Ok(("", attributes)) => Some((text.trim_end(), attributes)),
+        _ => None,
+    }
+}
+
+pub fn atx_heading_with_options<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, ATXHeading<'a>, ErrorTree<&'a str>> {
+    move |input| {
+        let (input, mut heading) = atx_heading(input)?;
+        if options.heading_attributes {
+            add_attributes(&mut heading);
+        }
+        Ok((input, heading))
+    }
+}
+
+fn add_attributes(heading: &mut ATXHeading) {
+    // a heading that was just parsed still points into the input
+    if let Cow::Borrowed(value) = heading.value {
+        if let Some((value, attributes)) = split_attributes(value) {
+            heading.id = attributes
+                .iter()
+                .rev()
+                .find(|attribute| attribute.key == "id")
+                .map(|attribute| attribute.value.clone());
+            heading.value = value.into();
+            heading.attributes = attributes;
+        }
+    }
+}
+
+/// The level of the heading a line of `=` or `-` underlines
+fn setext_underline(line: &str) -> Option<u8> {
+    let (columns, indent) = indentation(line);
+    let underline = line[indent..].trim_end();
+    if columns > 3 || underline.is_empty() {
+        None
+    } else if underline.chars().all(|c| c == '=') {
+        Some(1)
+    } else if underline.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// ```md
+/// Heading
+/// =======
+/// ```
+///
+/// The text is whatever a paragraph would have held, with its
+/// lines joined by spaces. `=` makes a level 1 heading and `-` a
+/// level 2 one.
+pub fn setext_heading(input: &str) -> IResult<&str, ATXHeading<'_>, ErrorTree<&str>> {
+    let mut lines = vec![];
+    let mut offset = 0;
+    for line in split_lines(input) {
+        let content = trim_eol(line);
+        if content.trim().is_empty() {
+            break;
+        }
+        if !lines.is_empty() {
+            if let Some(level) = setext_underline(content) {
+                let value = if lines.len() == 1 {
+                    Cow::Borrowed(lines[0])
+                } else {
+                    Cow::Owned(lines.join(" "))
+                };
+                return Ok((
+                    &input[offset + content.len()..],
+                    ATXHeading {
+                        level,
+                        value,
+                        id: None,
+                        attributes: vec![],
+                    },
+                ));
+            }
+            if interrupts_paragraph(content) {
+                break;
+            }
+        }
+        lines.push(content.trim());
+        offset += line.len();
+    }
+    Err(nom::Err::Error(ErrorTree::from_error_kind(
+        input,
+        ErrorKind::Verify,
+    )))
+}
+
+pub fn setext_heading_with_options<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, ATXHeading<'a>, ErrorTree<&'a str>> {
+    move |input| {
+        let (input, mut heading) = setext_heading(input)?;
+        if options.heading_attributes {
+            add_attributes(&mut heading);
+        }
+        Ok((input, heading))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use nom::{error::ErrorKind, Err::Error};
 
     #[test]
     fn parse_atx_heading_level_1() {
@@ -57,7 +245,9 @@ mod tests {
                 "",
                 ATXHeading {
                     level: 1,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }
             )
         );
@@ -70,7 +260,9 @@ mod tests {
                 "",
                 ATXHeading {
                     level: 2,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }
             )
         );
@@ -83,7 +275,9 @@ mod tests {
                 "",
                 ATXHeading {
                     level: 3,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }
             )
         );
@@ -96,7 +290,9 @@ mod tests {
                 "",
                 ATXHeading {
                     level: 4,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }
             )
         );
@@ -109,7 +305,9 @@ mod tests {
                 "",
                 ATXHeading {
                     level: 5,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }
             )
         );
@@ -122,7 +320,9 @@ mod tests {
                 "",
                 ATXHeading {
                     level: 6,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }
             )
         );
@@ -136,7 +336,9 @@ mod tests {
                 "",
                 ATXHeading {
                     level: 6,
-                    value: ""
+                    value: "".into(),
+                    id: None,
+                    attributes: vec![]
                 }
             )
         );
@@ -152,7 +354,9 @@ mod tests {
                 "",
                 ATXHeading {
                     level: 1,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }
             )
         );
@@ -168,11 +372,90 @@ mod tests {
                 "",
                 ATXHeading {
                     level: 1,
-                    value: "a bunch-of valid (symbols), like:+"
+                    value: "a bunch-of valid (symbols), like:+".into(),
+                    id: None,
+                    attributes: vec![]
+                }
+            )
+        );
+    }
+    #[test]
+    fn parse_atx_heading_attributes() {
+        let options = ParseOptions {
+            heading_attributes: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            atx_heading_with_options(options)("## Install {#setup .wide}").unwrap(),
+            (
+                "",
+                ATXHeading {
+                    level: 2,
+                    value: "Install".into(),
+                    id: Some("setup".into()),
+                    attributes: vec![
+                        Attribute {
+                            key: "id".into(),
+                            value: "setup".into()
+                        },
+                        Attribute {
+                            key: "class".into(),
+                            value: "wide".into()
+                        }
+                    ]
+                }
+            )
+        );
+    }
+
+    #[test]
+    // braces that aren't a trailing attribute list are
+    // part of the heading
+    fn parse_atx_heading_not_attributes() {
+        let options = ParseOptions {
+            heading_attributes: true,
+            ..ParseOptions::default()
+        };
+        for input in &["# a{#b}", "# {#a} b", "# a {#b"] {
+            let (_, heading) = atx_heading_with_options(options)(input).unwrap();
+            assert_eq!(heading.value, &input[2..]);
+            assert_eq!(heading.id, None);
+        }
+    }
+
+    #[test]
+    fn parse_setext_heading() {
+        assert_eq!(
+            setext_heading("Release\r\nnotes\n-----\nafter").unwrap(),
+            (
+                "\nafter",
+                ATXHeading {
+                    level: 2,
+                    value: "Release notes".into(),
+                    id: None,
+                    attributes: vec![]
                 }
             )
         );
+        assert_eq!(setext_heading("Title\n===").unwrap().1.level, 1);
+        assert!(setext_heading("Title\n\n===").is_err());
+        assert!(setext_heading("Title\n- - -").is_err());
     }
+
+    #[test]
+    fn stringify_atx_heading_attributes() {
+        let heading = ATXHeading {
+            level: 1,
+            value: "Intro".into(),
+            id: Some("intro".into()),
+            attributes: vec![Attribute {
+                key: "id".into(),
+                value: "intro".into(),
+            }],
+        };
+        assert_eq!(heading.to_string(), "# Intro {#intro}");
+    }
+
     // #[test]
     // // #hashtags are not valid headings, and
     // // instad parse as paragraphs.
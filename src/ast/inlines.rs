@@ -0,0 +1,276 @@
+use super::{
+    code_spans::{inline_code, InlineCode},
+    directives::{text_directive, TextDirective},
+    math::{inline_math, InlineMath},
+    owned,
+    strikethrough::{strikethrough, Strikethrough},
+    ParseOptions,
+};
+use std::{borrow::Cow, fmt};
+
+/// Phrasing content inside of a block, such as a paragraph.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Inline<'a> {
+    Text(Cow<'a, str>),
+    InlineCode(InlineCode<'a>),
+    InlineMath(InlineMath<'a>),
+    TextDirective(TextDirective<'a>),
+    Strikethrough(Strikethrough<'a>),
+}
+impl<'a> Inline<'a> {
+    pub fn into_owned(self) -> Inline<'static> {
+        match self {
+            Inline::Text(text) => Inline::Text(owned(text)),
+            Inline::InlineCode(code) => Inline::InlineCode(code.into_owned()),
+            Inline::InlineMath(math) => Inline::InlineMath(math.into_owned()),
+            Inline::TextDirective(directive) => Inline::TextDirective(directive.into_owned()),
+            Inline::Strikethrough(strikethrough) => {
+                Inline::Strikethrough(strikethrough.into_owned())
+            }
+        }
+    }
+}
+impl<'a> fmt::Display for Inline<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Inline::Text(text) => write!(f, "{}", text),
+            Inline::InlineCode(code) => write!(f, "{}", code),
+            Inline::InlineMath(math) => write!(f, "{}", math),
+            Inline::TextDirective(directive) => write!(f, "{}", directive),
+            Inline::Strikethrough(strikethrough) => write!(f, "{}", strikethrough),
+        }
+    }
+}
+
+/// Splits the content of a block into inline nodes.
+///
+/// Code spans bind tighter than anything else, so they're
+/// found first and whatever is inside of them is never
+/// looked at again. Everything that isn't part of a more
+/// specific inline node ends up in `Inline::Text`.
+pub fn inlines(input: &str) -> Vec<Inline<'_>> {
+    inlines_with_options(input, ParseOptions::default())
+}
+
+pub fn inlines_with_options(input: &str, options: ParseOptions) -> Vec<Inline<'_>> {
+    let mut nodes = vec![];
+    let mut text_start = 0;
+    let mut offset = 0;
+    let is_special = |c| {
+        c == '`'
+            || c == '\\'
+            || (options.math && c == '$')
+            || (options.directives && c == ':')
+            || (options.gfm && c == '~')
+    };
+    while let Some(found) = input[offset..].find(is_special) {
+        offset += found;
+        let rest = &input[offset..];
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            // an escaped backtick or dollar can't open anything
+            offset += 1 + escaped
+                .chars()
+                .next()
+                .filter(char::is_ascii_punctuation)
+                .map_or(0, char::len_utf8);
+            continue;
+        }
+        let fence_char = rest.chars().next().unwrap_or_default();
+        let parsed = match fence_char {
+            '`' => inline_code(rest).map(|(remaining, code)| (remaining, Inline::InlineCode(code))),
+            '$' => inline_math(rest).map(|(remaining, math)| (remaining, Inline::InlineMath(math))),
+            '~' => strikethrough(rest)
+                .map(|(remaining, strikethrough)| (remaining, Inline::Strikethrough(strikethrough))),
+            // `a::b` isn't a directive
+            _ if input[..offset].ends_with(':') => {
+                offset += 1;
+                continue;
+            }
+            _ => text_directive(rest)
+                .map(|(remaining, directive)| (remaining, Inline::TextDirective(directive))),
+        };
+        match parsed {
+            Ok((remaining, node)) => {
+                if text_start < offset {
+                    nodes.push(Inline::Text(input[text_start..offset].into()));
+                }
+                nodes.push(node);
+                offset = input.len() - remaining.len();
+                text_start = offset;
+            }
+            // an unmatched backtick (or dollar, or colon) string is
+            // literal text, all of it, not just the first character
+            Err(_) => {
+                offset += rest.find(|c| c != fence_char).unwrap_or(rest.len());
+            }
+        }
+    }
+    if text_start < input.len() {
+        nodes.push(Inline::Text(input[text_start..].into()));
+    }
+    nodes
+}
+
+/// The plain text of some inline nodes, without any of
+/// the markup around it
+pub fn text_content(nodes: &[Inline]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match node {
+            Inline::Text(value) => {
+                let mut chars = value.chars().peekable();
+                while let Some(c) = chars.next() {
+                    match chars.peek() {
+                        Some(next) if c == '\\' && next.is_ascii_punctuation() => (),
+                        _ => text.push(c),
+                    }
+                }
+            }
+            Inline::InlineCode(code) => text.push_str(&code.value),
+            Inline::InlineMath(math) => text.push_str(&math.value),
+            Inline::TextDirective(directive) => {
+                text.push_str(directive.label.as_deref().unwrap_or(""))
+            }
+            Inline::Strikethrough(strikethrough) => {
+                text.push_str(&text_content(&inlines(&strikethrough.value)))
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_inlines_text() {
+        assert_eq!(
+            inlines("just words"),
+            vec![Inline::Text("just words".into())]
+        );
+    }
+
+    #[test]
+    fn parse_inlines_code() {
+        assert_eq!(
+            inlines("use `parse` to parse"),
+            vec![
+                Inline::Text("use ".into()),
+                Inline::InlineCode(InlineCode {
+                    value: "parse".into()
+                }),
+                Inline::Text(" to parse".into()),
+            ]
+        );
+    }
+
+    #[test]
+    // things that would otherwise be emphasis or jsx
+    // are part of the code
+    fn parse_inlines_code_precedence() {
+        assert_eq!(
+            inlines("`*a* <B />`"),
+            vec![Inline::InlineCode(InlineCode {
+                value: "*a* <B />".into()
+            })]
+        );
+    }
+
+    #[test]
+    fn parse_inlines_unmatched_backticks() {
+        assert_eq!(
+            inlines("```foo`` and `bar`"),
+            vec![
+                Inline::Text("```foo`` and ".into()),
+                Inline::InlineCode(InlineCode {
+                    value: "bar".into()
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_inlines_math() {
+        let math = ParseOptions {
+            math: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            inlines_with_options("area is $\\pi r^2$, `$code$`", math),
+            vec![
+                Inline::Text("area is ".into()),
+                Inline::InlineMath(InlineMath {
+                    value: "\\pi r^2".into()
+                }),
+                Inline::Text(", ".into()),
+                Inline::InlineCode(InlineCode {
+                    value: "$code$".into()
+                }),
+            ]
+        );
+        assert_eq!(
+            inlines_with_options("costs $5 and $10", math),
+            vec![Inline::Text("costs $5 and $10".into())]
+        );
+        assert_eq!(inlines("$x$"), vec![Inline::Text("$x$".into())]);
+    }
+
+    #[test]
+    fn parse_inlines_text_directive() {
+        let directives = ParseOptions {
+            directives: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            inlines_with_options("press :kbd[Ctrl] at 10:30, or a::b", directives),
+            vec![
+                Inline::Text("press ".into()),
+                Inline::TextDirective(TextDirective {
+                    name: "kbd".into(),
+                    label: Some("Ctrl".into()),
+                    attributes: vec![]
+                }),
+                Inline::Text(" at 10:30, or a::b".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn inlines_text_content() {
+        assert_eq!(
+            text_content(&inlines("use `parse` \\*now\\*")),
+            "use parse *now*"
+        );
+    }
+
+    #[test]
+    fn parse_inlines_escaped_backtick() {
+        assert_eq!(
+            inlines("\\`not code`"),
+            vec![Inline::Text("\\`not code`".into())]
+        );
+    }
+
+    #[test]
+    fn parse_inlines_strikethrough() {
+        let gfm = ParseOptions {
+            gfm: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            inlines_with_options("~~old `~~`~~ new, ~~ not ~~", gfm),
+            vec![
+                Inline::Strikethrough(Strikethrough {
+                    value: "old `~~`".into()
+                }),
+                Inline::Text(" new, ~~ not ~~".into()),
+            ]
+        );
+        assert_eq!(inlines("~~old~~"), vec![Inline::Text("~~old~~".into())]);
+        assert_eq!(
+            text_content(&inlines_with_options("~~`a`~~", gfm)),
+            "a"
+        );
+    }
+}
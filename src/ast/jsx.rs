@@ -0,0 +1,473 @@
+use super::{container_children, lines::eol, owned, Content, MdxAst, ParseOptions};
+use crate::stringify::{self, StringifyOptions};
+use nom::{
+    branch::alt,
+    bytes::complete::{take_till, take_while},
+    character::complete::{char, multispace0, multispace1, satisfy, space0},
+    combinator::{eof, opt, peek, recognize},
+    error::{ErrorKind, ParseError},
+    multi::{fold_many_m_n, many0},
+    sequence::{delimited, pair, preceded, tuple},
+    IResult,
+};
+use nom_supreme::{error::ErrorTree, tag::complete::tag};
+use std::{borrow::Cow, fmt};
+
+/// A JSX element on its own line. Anything between its tags is
+/// parsed as regular block content, so markdown works inside
+/// components:
+///
+/// ```md
+/// <Note type="info">
+/// Some **markdown**
+/// </Note>
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct JsxElement<'a> {
+    /// `None` for fragments, `<>...</>`
+    pub name: Option<Cow<'a, str>>,
+    pub attributes: Vec<JsxAttribute<'a>>,
+    pub children: Vec<MdxAst<'a>>,
+    /// Written as `<Name />` rather than with a closing tag
+    pub self_closing: bool,
+}
+impl<'a> JsxElement<'a> {
+    pub fn into_owned(self) -> JsxElement<'static> {
+        JsxElement {
+            name: self.name.map(owned),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(JsxAttribute::into_owned)
+                .collect(),
+            children: self.children.into_iter().map(MdxAst::into_owned).collect(),
+            self_closing: self.self_closing,
+        }
+    }
+}
+impl<'a> JsxElement<'a> {
+    /// The opening tag, or the whole element when it's self closing
+    pub(crate) fn opening(&self) -> String {
+        let mut tag = format!("<{}", self.name.as_deref().unwrap_or(""));
+        for attribute in &self.attributes {
+            tag.push_str(&format!(" {}", attribute));
+        }
+        tag.push_str(if self.self_closing { " />" } else { ">" });
+        tag
+    }
+}
+impl<'a> fmt::Display for JsxElement<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&stringify::jsx_element(self, StringifyOptions::default()))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum JsxAttribute<'a> {
+    /// `name`, `name="value"` or `name={expression}`
+    Named {
+        name: Cow<'a, str>,
+        value: Option<JsxAttributeValue<'a>>,
+    },
+    /// `{...props}`, holding the expression after the `...`
+    Spread(Cow<'a, str>),
+}
+impl<'a> JsxAttribute<'a> {
+    pub fn into_owned(self) -> JsxAttribute<'static> {
+        match self {
+            JsxAttribute::Named { name, value } => JsxAttribute::Named {
+                name: owned(name),
+                value: value.map(JsxAttributeValue::into_owned),
+            },
+            JsxAttribute::Spread(expression) => JsxAttribute::Spread(owned(expression)),
+        }
+    }
+}
+impl<'a> fmt::Display for JsxAttribute<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsxAttribute::Named { name, value: None } => write!(f, "{}", name),
+            JsxAttribute::Named {
+                name,
+                value: Some(JsxAttributeValue::String(value)),
+            } => {
+                let quote = if value.contains('"') { '\'' } else { '"' };
+                write!(f, "{}={}{}{}", name, quote, value, quote)
+            }
+            JsxAttribute::Named {
+                name,
+                value: Some(JsxAttributeValue::Expression(expression)),
+            } => write!(f, "{}={{{}}}", name, expression),
+            JsxAttribute::Spread(expression) => write!(f, "{{...{}}}", expression),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum JsxAttributeValue<'a> {
+    String(Cow<'a, str>),
+    /// The source of a `{...}` expression, without the braces
+    Expression(Cow<'a, str>),
+}
+impl<'a> JsxAttributeValue<'a> {
+    pub fn into_owned(self) -> JsxAttributeValue<'static> {
+        match self {
+            JsxAttributeValue::String(value) => JsxAttributeValue::String(owned(value)),
+            JsxAttributeValue::Expression(expression) => {
+                JsxAttributeValue::Expression(owned(expression))
+            }
+        }
+    }
+}
+
+/// A JavaScript expression on its own, as a block
+///
+/// ```md
+/// {props.items.map((item) => <Item {...item} />)}
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Expression<'a> {
+    /// The source between the braces, exactly as it was written
+    pub value: Cow<'a, str>,
+}
+impl<'a> Expression<'a> {
+    pub fn into_owned(self) -> Expression<'static> {
+        Expression {
+            value: owned(self.value),
+        }
+    }
+}
+impl<'a> fmt::Display for Expression<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{{}}}", self.value)
+    }
+}
+
+/// Parses a `{...}` expression and returns the source between the
+/// braces. Nested braces and braces in strings are balanced, but
+/// the JavaScript itself isn't checked.
+pub fn expression(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    let (inner, _) = char('{')(input)?;
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in inner.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' | '`' => quote = Some(c),
+            '{' => depth += 1,
+            '}' if depth == 0 => return Ok((&inner[i + 1..], &inner[..i])),
+            '}' => depth -= 1,
+            _ => (),
+        }
+    }
+    Err(nom::Err::Error(ErrorTree::from_error_kind(
+        input,
+        ErrorKind::Char,
+    )))
+}
+
+/// Element and attribute names, including member expressions like
+/// `Tabs.Item` and namespaces like `svg:rect`
+fn jsx_name(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    recognize(pair(
+        satisfy(|c| c.is_alphabetic() || c == '_' || c == '$'),
+        take_while(|c: char| c.is_alphanumeric() || ['_', '$', '-', '.', ':'].contains(&c)),
+    ))(input)
+}
+
+fn jsx_attribute_value(input: &str) -> IResult<&str, JsxAttributeValue<'_>, ErrorTree<&str>> {
+    if let Ok((input, value)) = expression(input) {
+        return Ok((input, JsxAttributeValue::Expression(value.into())));
+    }
+    let (input, value) = alt((
+        delimited(char('"'), take_till(|c| c == '"'), char('"')),
+        delimited(char('\''), take_till(|c| c == '\''), char('\'')),
+    ))(input)?;
+    Ok((input, JsxAttributeValue::String(value.into())))
+}
+
+fn jsx_attribute(input: &str) -> IResult<&str, JsxAttribute<'_>, ErrorTree<&str>> {
+    if let Ok((rest, value)) = expression(input) {
+        return match value.trim_start().strip_prefix("...") {
+            Some(spread) => Ok((rest, JsxAttribute::Spread(spread.trim().into()))),
+            None => Err(nom::Err::Error(ErrorTree::from_error_kind(
+                input,
+                ErrorKind::Tag,
+            ))),
+        };
+    }
+    let (input, name) = jsx_name(input)?;
+    let (input, value) = opt(preceded(
+        tuple((multispace0, char('='), multispace0)),
+        jsx_attribute_value,
+    ))(input)?;
+    Ok((
+        input,
+        JsxAttribute::Named {
+            name: name.into(),
+            value,
+        },
+    ))
+}
+
+struct OpeningTag<'a> {
+    name: Option<&'a str>,
+    attributes: Vec<JsxAttribute<'a>>,
+    self_closing: bool,
+}
+
+fn opening_tag(input: &str) -> IResult<&str, OpeningTag<'_>, ErrorTree<&str>> {
+    let start = input;
+    let (input, _) = char('<')(input)?;
+    let (input, name) = opt(jsx_name)(input)?;
+    let (input, attributes) = many0(preceded(multispace1, jsx_attribute))(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, end) = alt((tag("/>"), tag(">")))(input)?;
+    let self_closing = end == "/>";
+    // fragments can't have attributes, and `</>` closes one
+    if name.is_none() && (self_closing || !attributes.is_empty()) {
+        return Err(nom::Err::Error(ErrorTree::from_error_kind(
+            start,
+            ErrorKind::Verify,
+        )));
+    }
+    Ok((
+        input,
+        OpeningTag {
+            name,
+            attributes,
+            self_closing,
+        },
+    ))
+}
+
+fn closing_tag(input: &str) -> IResult<&str, Option<&str>, ErrorTree<&str>> {
+    delimited(
+        tag("</"),
+        delimited(multispace0, opt(jsx_name), multispace0),
+        char('>'),
+    )(input)
+}
+
+/// Finds the closing tag for an element called `name`, skipping
+/// over any elements with the same name nested inside it.
+///
+/// Returns the element's content and whatever follows the
+/// closing tag.
+fn split_jsx_children<'a>(input: &'a str, name: Option<&str>) -> Option<(&'a str, &'a str)> {
+    let mut depth = 0;
+    let mut offset = 0;
+    while let Some(i) = input[offset..].find('<') {
+        let at = offset + i;
+        if let Ok((rest, closing)) = closing_tag(&input[at..]) {
+            if closing == name {
+                if depth == 0 {
+                    return Some((&input[..at], rest));
+                }
+                depth -= 1;
+            }
+        } else if let Ok((_, nested)) = opening_tag(&input[at..]) {
+            if nested.name == name && !nested.self_closing {
+                depth += 1;
+            }
+        }
+        offset = at + 1;
+    }
+    None
+}
+
+/// Expressions are blocks when they start a line and nothing but
+/// whitespace follows them on the line they end on.
+pub fn flow_expression(input: &str) -> IResult<&str, Expression<'_>, ErrorTree<&str>> {
+    // basically anything can start with 0-3 spaces. We don't really care.
+    let (input, _) = fold_many_m_n(0, 3, tag(" "), 0, |acc: u8, _| acc + 1)(input)?;
+    let (rest, value) = expression(input)?;
+    let (rest, _) = space0(rest)?;
+    let (rest, _) = peek(alt((eol, eof)))(rest)?;
+    Ok((
+        rest,
+        Expression {
+            value: value.into(),
+        },
+    ))
+}
+
+/// An element's opening tag, and the content between its tags
+/// unless it's self closing
+fn element_parts(
+    start: &str,
+) -> IResult<&str, (OpeningTag<'_>, Option<Content<'_>>), ErrorTree<&str>> {
+    // basically anything can start with 0-3 spaces. We don't really care.
+    let (input, _) = fold_many_m_n(0, 3, tag(" "), 0, |acc: u8, _| acc + 1)(start)?;
+    let (rest, opening) = opening_tag(input)?;
+    if opening.self_closing {
+        return Ok((rest, (opening, None)));
+    }
+    let (content, after) = split_jsx_children(rest, opening.name).ok_or_else(|| {
+        nom::Err::Error(ErrorTree::from_error_kind(input, ErrorKind::TakeUntil))
+    })?;
+    let content = Content::slice(content, start.len() - rest.len());
+    Ok((after, (opening, Some(content))))
+}
+
+/// The content of the JSX element at the start of `input`
+pub(crate) fn jsx_content(input: &str) -> Option<Content<'_>> {
+    element_parts(input).ok().and_then(|(_, (_, content))| content)
+}
+
+/// JSX elements are blocks when they start a line and nothing but
+/// whitespace follows them on the line they end on.
+pub fn jsx_element<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, JsxElement<'a>, ErrorTree<&'a str>> {
+    move |input| {
+        let (rest, (opening, content)) = element_parts(input)?;
+        let children = match content {
+            Some(content) => container_children(input, content.text, options)?,
+            None => vec![],
+        };
+        let (rest, _) = space0(rest)?;
+        let (rest, _) = peek(alt((eol, eof)))(rest)?;
+        Ok((
+            rest,
+            JsxElement {
+                name: opening.name.map(Into::into),
+                attributes: opening.attributes,
+                children,
+                self_closing: opening.self_closing,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{ATXHeading, Paragraph};
+
+    #[test]
+    fn parse_expression() {
+        assert_eq!(
+            expression("{{ a: '}' }} rest").unwrap(),
+            (" rest", "{ a: '}' }")
+        );
+        assert!(expression("{ unclosed").is_err());
+    }
+
+    #[test]
+    fn parse_self_closing_element() {
+        assert_eq!(
+            jsx_element(ParseOptions::default())(
+                "<Chart data={[1, 2]} title=\"Sales\" {...props} wide />\nafter"
+            )
+            .unwrap(),
+            (
+                "\nafter",
+                JsxElement {
+                    name: Some("Chart".into()),
+                    attributes: vec![
+                        JsxAttribute::Named {
+                            name: "data".into(),
+                            value: Some(JsxAttributeValue::Expression("[1, 2]".into()))
+                        },
+                        JsxAttribute::Named {
+                            name: "title".into(),
+                            value: Some(JsxAttributeValue::String("Sales".into()))
+                        },
+                        JsxAttribute::Spread("props".into()),
+                        JsxAttribute::Named {
+                            name: "wide".into(),
+                            value: None
+                        },
+                    ],
+                    children: vec![],
+                    self_closing: true,
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_element_children() {
+        let (rest, element) = jsx_element(ParseOptions::default())(
+            "<Note>\n# Title\n\n<Note>\ninner\n</Note>\n</Note>",
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            element.children,
+            vec![
+                MdxAst::ATXHeading(ATXHeading {
+                    level: 1,
+                    value: "Title".into(),
+                    id: None,
+                    attributes: vec![]
+                }),
+                MdxAst::JsxElement(JsxElement {
+                    name: Some("Note".into()),
+                    attributes: vec![],
+                    children: vec![MdxAst::Paragraph(Paragraph {
+                        words: "inner".into()
+                    })],
+                    self_closing: false,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_fragment() {
+        let (_, element) = jsx_element(ParseOptions::default())("<>hi</>").unwrap();
+        assert_eq!(element.name, None);
+        assert_eq!(element.children.len(), 1);
+    }
+
+    #[test]
+    // text after an element on the same line makes it inline
+    // content of a paragraph instead
+    fn parse_inline_element_not_block() {
+        assert!(jsx_element(ParseOptions::default())("<b>bold</b> text").is_err());
+        assert!(jsx_element(ParseOptions::default())("<https://example.com>").is_err());
+    }
+
+    #[test]
+    fn stringify_element() {
+        let element = JsxElement {
+            name: Some("Note".into()),
+            attributes: vec![JsxAttribute::Named {
+                name: "title".into(),
+                value: Some(JsxAttributeValue::String("Say \"hi\"".into())),
+            }],
+            children: vec![MdxAst::Paragraph(Paragraph { words: "hi".into() })],
+            self_closing: false,
+        };
+        assert_eq!(
+            element.to_string(),
+            "<Note title='Say \"hi\"'>\nhi\n</Note>"
+        );
+    }
+
+    #[test]
+    fn parse_flow_expression() {
+        assert_eq!(
+            flow_expression("{items.map((item) => {\n  return item\n})}  \nafter").unwrap(),
+            (
+                "\nafter",
+                Expression {
+                    value: "items.map((item) => {\n  return item\n})".into()
+                }
+            )
+        );
+        assert!(flow_expression("{a} and more").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Line endings and indentation, shared by every block parser.
+//!
+//! Input is never normalized. Lines can end in `\n`, `\r\n` or a
+//! lone `\r`, and tabs count to the next multiple of 4 columns when
+//! deciding how far a line is indented, as in CommonMark.
+use nom::IResult;
+use nom_supreme::error::{BaseErrorKind, ErrorTree, Expectation};
+use std::borrow::Cow;
+
+/// A line ending
+pub(crate) fn eol(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    // this runs between every two blocks, so it doesn't go through
+    // `alt`, which would put together an error for every kind of
+    // line ending each time there isn't one
+    let len = if input.starts_with("\r\n") {
+        2
+    } else if input.starts_with(['\n', '\r']) {
+        1
+    } else {
+        return Err(nom::Err::Error(ErrorTree::Base {
+            location: input,
+            kind: BaseErrorKind::Expected(Expectation::Tag("\n")),
+        }));
+    };
+    Ok((&input[len..], &input[..len]))
+}
+
+/// The line ending after a block, and any blank lines before the
+/// next one
+pub(crate) fn separator(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    let mut rest = input;
+    loop {
+        match eol(rest.trim_start_matches([' ', '\t'])) {
+            Ok((next, _)) => rest = next,
+            Err(error) if rest.len() == input.len() => return Err(error),
+            Err(_) => return Ok((rest, &input[..input.len() - rest.len()])),
+        }
+    }
+}
+
+/// Everything up to the next line ending
+pub(crate) fn till_eol(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    nom::bytes::complete::take_till(|c| c == '\n' || c == '\r')(input)
+}
+
+/// Splits `input` into lines, each with its line ending
+pub(crate) fn split_lines(input: &str) -> impl Iterator<Item = &str> {
+    let mut rest = input;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = match rest.find(['\n', '\r']) {
+            Some(i) if rest[i..].starts_with("\r\n") => i + 2,
+            Some(i) => i + 1,
+            None => rest.len(),
+        };
+        let (line, next) = rest.split_at(end);
+        rest = next;
+        Some(line)
+    })
+}
+
+/// Removes one line ending from the end of `input`, if there is one
+pub(crate) fn trim_eol(input: &str) -> &str {
+    if let Some(input) = input.strip_suffix("\r\n") {
+        input
+    } else if let Some(input) = input.strip_suffix(['\n', '\r']) {
+        input
+    } else {
+        input
+    }
+}
+
+/// The line ending `input` uses, going by its first line
+pub(crate) fn line_ending(input: &str) -> &'static str {
+    match input.find(['\n', '\r']) {
+        Some(i) if input[i..].starts_with("\r\n") => "\r\n",
+        Some(i) if input[i..].starts_with('\r') => "\r",
+        _ => "\n",
+    }
+}
+
+/// Replaces the `\n` line endings in `output` with `line_ending`
+pub(crate) fn with_line_ending(output: String, line_ending: &str) -> String {
+    if line_ending == "\n" {
+        output
+    } else {
+        output.replace("\r\n", "\n").replace('\n', line_ending)
+    }
+}
+
+/// How many columns the indentation at the start of `line` takes
+/// up, and how many bytes
+pub(crate) fn indentation(line: &str) -> (usize, usize) {
+    indentation_from(line, 0)
+}
+
+/// Like `indentation`, for a line that starts at `column`, which
+/// matters for where the tab stops are
+pub(crate) fn indentation_from(line: &str, column: usize) -> (usize, usize) {
+    let mut columns = column;
+    for (i, c) in line.char_indices() {
+        match c {
+            ' ' => columns += 1,
+            '\t' => columns += 4 - columns % 4,
+            _ => return (columns - column, i),
+        }
+    }
+    (columns - column, line.len())
+}
+
+/// Removes `columns` columns of indentation from the start of
+/// `line`. A tab that's only partly removed is replaced by the
+/// spaces that are left of it.
+pub(crate) fn strip_indentation(line: &str, columns: usize) -> Cow<'_, str> {
+    let mut column = 0;
+    for (i, c) in line.char_indices() {
+        if column >= columns {
+            return Cow::Borrowed(&line[i..]);
+        }
+        match c {
+            ' ' => column += 1,
+            '\t' => {
+                let next = column + 4 - column % 4;
+                if next > columns {
+                    return Cow::Owned(format!("{}{}", " ".repeat(next - columns), &line[i + 1..]));
+                }
+                column = next;
+            }
+            _ => return Cow::Borrowed(&line[i..]),
+        }
+    }
+    Cow::Borrowed("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_mixed_line_endings() {
+        assert_eq!(
+            split_lines("a\r\nb\nc\rd").collect::<Vec<_>>(),
+            vec!["a\r\n", "b\n", "c\r", "d"]
+        );
+        assert_eq!(
+            split_lines("a\r\n").map(trim_eol).collect::<Vec<_>>(),
+            vec!["a"]
+        );
+    }
+
+    #[test]
+    fn tab_stops() {
+        assert_eq!(indentation("\tx"), (4, 1));
+        assert_eq!(indentation("  \tx"), (4, 3));
+        assert_eq!(indentation(" \t\tx"), (8, 3));
+        assert_eq!(indentation_from("\tx", 2), (2, 1));
+    }
+
+    #[test]
+    fn strip_partial_tab() {
+        assert_eq!(strip_indentation("\tfoo", 2), "  foo");
+        assert_eq!(strip_indentation("    foo", 2), "  foo");
+        assert_eq!(strip_indentation("\t\tfoo", 4), "\tfoo");
+        assert_eq!(strip_indentation(" foo", 2), "foo");
+    }
+}
@@ -0,0 +1,473 @@
+use super::{
+    container_children,
+    lines::{indentation, indentation_from, split_lines, strip_indentation, trim_eol},
+    paragraphs::interrupts_paragraph,
+    thematic_breaks::is_thematic_break,
+    Content, MdxAst, ParseOptions,
+};
+use crate::{
+    mdx_error::Span,
+    stringify::{self, StringifyOptions},
+};
+use nom::{
+    error::{ErrorKind, ParseError},
+    IResult,
+};
+use nom_supreme::error::ErrorTree;
+use std::{borrow::Cow, fmt};
+
+/// ```md
+/// - a bullet list
+/// - with two items
+///
+/// 1. an ordered list
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct List<'a> {
+    /// `1.` style markers rather than `-` style ones
+    pub ordered: bool,
+    /// The number of the first item, for ordered lists
+    pub start: Option<u32>,
+    /// Whether there are blank lines between the items, or between
+    /// the children of any item
+    pub spread: bool,
+    pub children: Vec<ListItem<'a>>,
+}
+impl<'a> List<'a> {
+    pub fn into_owned(self) -> List<'static> {
+        List {
+            ordered: self.ordered,
+            start: self.start,
+            spread: self.spread,
+            children: self
+                .children
+                .into_iter()
+                .map(ListItem::into_owned)
+                .collect(),
+        }
+    }
+}
+impl<'a> fmt::Display for List<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&stringify::list(self, false, StringifyOptions::default()))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ListItem<'a> {
+    /// Whether the item's children are separated by blank lines
+    pub spread: bool,
+    /// Whether a GFM task list item, starting with `[x]` or `[ ]`,
+    /// is checked. `None` for items that aren't tasks.
+    pub checked: Option<bool>,
+    pub children: Vec<MdxAst<'a>>,
+}
+impl<'a> ListItem<'a> {
+    pub fn into_owned(self) -> ListItem<'static> {
+        ListItem {
+            spread: self.spread,
+            checked: self.checked,
+            children: self.children.into_iter().map(MdxAst::into_owned).collect(),
+        }
+    }
+}
+impl<'a> fmt::Display for ListItem<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let separator = if self.spread { "\n\n" } else { "\n" };
+        f.write_str(&stringify::blocks(
+            &self.children,
+            separator,
+            StringifyOptions::default(),
+        ))
+    }
+}
+
+/// The marker at the start of a list item, like `- ` or `1. `
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Marker<'a> {
+    /// `-`, `+` or `*` for bullets, `.` or `)` for ordered items
+    pub delimiter: char,
+    pub number: Option<u32>,
+    /// The column the item's content starts at
+    pub width: usize,
+    /// The content on the marker's line
+    pub rest: &'a str,
+}
+
+pub(crate) fn list_marker(line: &str) -> Option<Marker<'_>> {
+    let (columns, indent) = indentation(line);
+    if columns > 3 {
+        return None;
+    }
+    let trimmed = &line[indent..];
+    let digits = trimmed.len()
+        - trimmed
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .len();
+    let (delimiter, number) = match digits {
+        0 => (
+            trimmed
+                .chars()
+                .next()
+                .filter(|c| ['-', '+', '*'].contains(c))?,
+            None,
+        ),
+        1..=9 => (
+            trimmed[digits..]
+                .chars()
+                .next()
+                .filter(|c| ['.', ')'].contains(c))?,
+            Some(trimmed[..digits].parse().ok()?),
+        ),
+        _ => return None,
+    };
+    let marker = columns + digits + 1;
+    let after = &trimmed[digits + 1..];
+    let (spaces, whitespace) = indentation_from(after, marker);
+    if after.trim().is_empty() {
+        return Some(Marker {
+            delimiter,
+            number,
+            width: marker + 1,
+            rest: "",
+        });
+    }
+    // more than four spaces would make the content indented code,
+    // which belongs to the item after a single space
+    let (width, rest) = match spaces {
+        0 => return None,
+        1..=4 => (marker + spaces, &after[whitespace..]),
+        _ => (marker + 1, &after[1..]),
+    };
+    Some(Marker {
+        delimiter,
+        number,
+        width,
+        rest,
+    })
+}
+
+/// A task list item's checkbox, and the content after it
+fn task(line: &str) -> Option<(bool, &str)> {
+    let checked = match line.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let rest = &line[3..];
+    let content = rest.trim_start_matches([' ', '\t']);
+    if content.len() == rest.len() || content.is_empty() {
+        return None;
+    }
+    Some((checked, content))
+}
+
+/// The content of a list item, with its marker and indentation
+/// stripped
+pub(crate) struct ItemContent<'a> {
+    /// Where the marker is in the list's input
+    pub marker: usize,
+    /// The column the content starts at
+    pub width: usize,
+    pub checked: Option<bool>,
+    pub content: Content<'a>,
+    has_blank_lines: bool,
+}
+
+/// The items of a list, before their content is parsed
+pub(crate) struct ListContent<'a> {
+    first: Marker<'a>,
+    /// Whether there are blank lines between the items
+    spread: bool,
+    pub items: Vec<ItemContent<'a>>,
+    /// Where the list ends in its input
+    end: usize,
+}
+
+/// An item's lines while the list is read
+struct Item<'a> {
+    marker: usize,
+    width: usize,
+    lines: Vec<(Cow<'a, str>, Span)>,
+    /// The indexes of the lazy continuation lines
+    lazy: Vec<usize>,
+}
+
+/// The items of the list at the start of `input`, and their lines
+pub(crate) fn list_content(input: &str, options: ParseOptions) -> Option<ListContent<'_>> {
+    let first = split_lines(input)
+        .next()
+        .map(trim_eol)
+        .filter(|line| !is_thematic_break(line))
+        .and_then(list_marker)?;
+
+    let mut items: Vec<Item> = vec![];
+    let mut width = 0;
+    let mut spread = false;
+    let mut blank = false;
+    let mut offset = 0;
+    let mut end = 0;
+    for line in split_lines(input) {
+        let content = trim_eol(line);
+        let source = Span::new(offset, offset + content.len());
+        if content.trim().is_empty() {
+            if let Some(item) = items.last_mut() {
+                item.lines.push((Cow::Borrowed(""), source));
+            }
+            blank = true;
+        } else if !items.is_empty() && indentation(content).0 >= width {
+            let item = items.last_mut().unwrap();
+            item.lines.push((strip_indentation(content, width), source));
+            blank = false;
+        } else if let Some(marker) = Some(content)
+            .filter(|line| !is_thematic_break(line))
+            .and_then(list_marker)
+            .filter(|marker| {
+                marker.delimiter == first.delimiter
+                    && marker.number.is_some() == first.number.is_some()
+            })
+        {
+            spread |= blank && !items.is_empty();
+            width = marker.width;
+            let at = offset + indentation(content).1;
+            items.push(Item {
+                marker: at,
+                width,
+                lines: vec![(Cow::Borrowed(marker.rest), source)],
+                lazy: vec![],
+            });
+            blank = false;
+        } else if !blank && !items.is_empty() && !interrupts_paragraph(content) {
+            let item = items.last_mut().unwrap();
+            item.lazy.push(item.lines.len());
+            item.lines.push((Cow::Borrowed(content.trim_start()), source));
+        } else {
+            break;
+        }
+        if !blank {
+            end = offset + content.len();
+        }
+        offset += line.len();
+    }
+
+    let items = items
+        .into_iter()
+        .map(|item| {
+            let Item {
+                marker,
+                width,
+                mut lines,
+                lazy,
+            } = item;
+            let checked = match lines.first_mut() {
+                Some((Cow::Borrowed(first), _)) if options.gfm => {
+                    task(first).map(|(checked, rest)| {
+                        *first = rest;
+                        checked
+                    })
+                }
+                _ => None,
+            };
+            while lines.last().is_some_and(|(line, _)| line.is_empty()) {
+                lines.pop();
+            }
+            ItemContent {
+                marker,
+                width,
+                checked,
+                has_blank_lines: lines.iter().any(|(line, _)| line.is_empty()),
+                content: Content::lines(lines, &lazy),
+            }
+        })
+        .collect();
+    Some(ListContent {
+        first,
+        spread,
+        items,
+        end,
+    })
+}
+
+/// Lists are a run of items with the same kind of marker. An item
+/// holds every following line that's indented past its marker,
+/// along with lazy continuation lines of a paragraph. Everything
+/// in an item is parsed as regular block content.
+pub fn list<'a>(
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, List<'a>, ErrorTree<&'a str>> {
+    move |input| {
+        let ListContent {
+            first,
+            mut spread,
+            items,
+            end,
+        } = list_content(input, options)
+            .ok_or_else(|| nom::Err::Error(ErrorTree::from_error_kind(input, ErrorKind::Char)))?;
+
+        let mut children = Vec::with_capacity(items.len());
+        for item in items {
+            let item_children = container_children(input, item.content.text, options)?;
+            let item_spread = item.has_blank_lines && item_children.len() > 1;
+            spread |= item_spread;
+            children.push(ListItem {
+                spread: item_spread,
+                checked: item.checked,
+                children: item_children,
+            });
+        }
+        Ok((
+            &input[end..],
+            List {
+                ordered: first.number.is_some(),
+                start: first.number,
+                spread,
+                children,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Paragraph, ThematicBreak};
+
+    fn paragraph(words: &str) -> MdxAst<'_> {
+        MdxAst::Paragraph(Paragraph {
+            words: words.into(),
+        })
+    }
+
+    #[test]
+    fn parse_list_marker() {
+        assert_eq!(
+            list_marker("  12) twelve"),
+            Some(Marker {
+                delimiter: ')',
+                number: Some(12),
+                width: 6,
+                rest: "twelve"
+            })
+        );
+        assert_eq!(list_marker("-not a list"), None);
+        assert_eq!(list_marker("1234567890. too long"), None);
+        assert_eq!(list_marker("-").map(|marker| marker.width), Some(2));
+    }
+
+    #[test]
+    fn parse_tight_list() {
+        assert_eq!(
+            list(ParseOptions::default())("- one\n- two\n  more\n\nafter").unwrap(),
+            (
+                "\n\nafter",
+                List {
+                    ordered: false,
+                    start: None,
+                    spread: false,
+                    children: vec![
+                        ListItem {
+                            spread: false,
+                            checked: None,
+                            children: vec![paragraph("one")]
+                        },
+                        ListItem {
+                            spread: false,
+                            checked: None,
+                            children: vec![paragraph("two\nmore")]
+                        },
+                    ]
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_nested_list() {
+        let (rest, list) =
+            list(ParseOptions::default())("1. one\n   - two\n\n   ---\n2. three").unwrap();
+        assert_eq!(rest, "");
+        assert!(list.ordered);
+        assert_eq!(list.start, Some(1));
+        assert!(list.spread);
+        assert_eq!(
+            list.children[0].children,
+            vec![
+                paragraph("one"),
+                MdxAst::List(List {
+                    ordered: false,
+                    start: None,
+                    spread: false,
+                    children: vec![ListItem {
+                        spread: false,
+                        checked: None,
+                        children: vec![paragraph("two")]
+                    }]
+                }),
+                MdxAst::ThematicBreak(ThematicBreak {
+                    char_count: 3,
+                    break_char: '-'
+                }),
+            ]
+        );
+        assert_eq!(list.children[1].children, vec![paragraph("three")]);
+    }
+
+    #[test]
+    // a different marker starts a new list
+    fn parse_list_marker_change() {
+        let (rest, list) = list(ParseOptions::default())("- a\n* b").unwrap();
+        assert_eq!(rest, "\n* b");
+        assert_eq!(list.children.len(), 1);
+    }
+
+    #[test]
+    fn parse_thematic_break_not_list() {
+        assert!(list(ParseOptions::default())("- - -").is_err());
+    }
+
+    #[test]
+    fn stringify_list() {
+        let list = List {
+            ordered: true,
+            start: Some(3),
+            spread: false,
+            children: vec![
+                ListItem {
+                    spread: true,
+                    checked: None,
+                    children: vec![paragraph("a"), paragraph("b")],
+                },
+                ListItem {
+                    spread: false,
+                    checked: None,
+                    children: vec![paragraph("c")],
+                },
+            ],
+        };
+        assert_eq!(list.to_string(), "3. a\n\n   b\n4. c");
+    }
+
+    #[test]
+    fn parse_task_list() {
+        let gfm = ParseOptions {
+            gfm: true,
+            ..ParseOptions::default()
+        };
+        let (_, tasks) = list(gfm)("- [x] done
+- [ ] todo
+- [ ]
+- [y] no").unwrap();
+        assert_eq!(
+            tasks
+                .children
+                .iter()
+                .map(|item| item.checked)
+                .collect::<Vec<_>>(),
+            vec![Some(true), Some(false), None, None]
+        );
+        assert_eq!(tasks.children[0].children, vec![paragraph("done")]);
+        let (_, plain) = list(ParseOptions::default())("- [x] done").unwrap();
+        assert_eq!(plain.children[0].checked, None);
+        assert_eq!(plain.children[0].children, vec![paragraph("[x] done")]);
+    }
+}
@@ -0,0 +1,309 @@
+use super::{
+    lines::{eol, indentation, split_lines, till_eol, trim_eol},
+    owned,
+};
+use nom::{
+    bytes::complete::take_while1,
+    character::complete::space0,
+    combinator::{eof, verify},
+    error::{ErrorKind, ParseError},
+    multi::fold_many_m_n,
+    IResult,
+};
+use nom_supreme::{error::ErrorTree, tag::complete::tag};
+use std::{borrow::Cow, fmt};
+
+/// Display math, fenced by two or more dollar signs.
+///
+/// ```md
+/// $$
+/// L = \frac{1}{2} \rho v^2 S C_L
+/// $$
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Math<'a> {
+    pub meta: Cow<'a, str>,
+    pub value: Cow<'a, str>,
+}
+impl<'a> Math<'a> {
+    pub fn into_owned(self) -> Math<'static> {
+        Math {
+            meta: owned(self.meta),
+            value: owned(self.value),
+        }
+    }
+}
+impl<'a> fmt::Display for Math<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // a line in the value that starts with as many dollars
+        // as the fence would close it early
+        let longest_run = self
+            .value
+            .lines()
+            .map(|line| line.trim_start().len() - line.trim_start().trim_start_matches('$').len())
+            .max()
+            .unwrap_or(0);
+        let fence = "$".repeat(std::cmp::max(2, longest_run + 1));
+        write!(f, "{}{}", fence, self.meta)?;
+        if !self.value.is_empty() {
+            write!(f, "\n{}", self.value)?;
+        }
+        write!(f, "\n{}", fence)
+    }
+}
+
+/// Inline math, such as `$x^2$` or `$$x^2$$`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InlineMath<'a> {
+    pub value: Cow<'a, str>,
+}
+impl<'a> InlineMath<'a> {
+    pub fn into_owned(self) -> InlineMath<'static> {
+        InlineMath {
+            value: owned(self.value),
+        }
+    }
+}
+impl<'a> fmt::Display for InlineMath<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let edges_need_padding =
+            self.value.starts_with([' ', '$']) || self.value.ends_with([' ', '$']);
+        let mut size = if edges_need_padding { 2 } else { 1 };
+        while dollar_runs(&self.value).any(|run| run == size) {
+            size += 1;
+        }
+        let fence = "$".repeat(size);
+        if edges_need_padding {
+            write!(f, "{} {} {}", fence, self.value, fence)
+        } else {
+            write!(f, "{}{}{}", fence, self.value, fence)
+        }
+    }
+}
+
+/// lengths of every maximal run of dollar signs in `input`
+fn dollar_runs(input: &str) -> impl Iterator<Item = usize> + '_ {
+    input
+        .split(|c| c != '$')
+        .map(str::len)
+        .filter(|len| *len > 0)
+}
+
+/// A closing fence is up to three spaces of indentation, at least
+/// as many dollars as the opening fence and nothing else.
+fn is_closing_fence(line: &str, size: usize) -> bool {
+    let (indent, bytes) = indentation(line);
+    let line = &line[bytes..];
+    let dollars = line.len() - line.trim_start_matches('$').len();
+    indent <= 3 && dollars >= size && line[dollars..].trim().is_empty()
+}
+
+/// Math blocks are fenced like code blocks are. The closing fence
+/// has to be at least as long as the opening fence, and if there
+/// isn't one the block runs to the end of the document.
+pub fn math(input: &str) -> IResult<&str, Math<'_>, ErrorTree<&str>> {
+    // basically anything can start with 0-3 spaces. We don't really care.
+    let (input, _) = fold_many_m_n(0, 3, tag(" "), 0, |acc: u8, _| acc + 1)(input)?;
+    let (input, fence) = verify(take_while1(|c| c == '$'), |fence: &str| fence.len() >= 2)(input)?;
+    let (input, _) = space0(input)?;
+    // `$$x$$` on a single line is inline math, not a block
+    let (input, meta) = verify(till_eol, |meta: &str| !meta.contains('$'))(input)?;
+    let (input, _) = nom::branch::alt((eol, eof))(input)?;
+
+    let mut offset = 0;
+    for line in split_lines(input) {
+        let content = trim_eol(line);
+        if is_closing_fence(content, fence.len()) {
+            let value = trim_eol(&input[..offset]);
+            return Ok((
+                &input[offset + content.len()..],
+                Math {
+                    meta: meta.trim_end().into(),
+                    value: value.into(),
+                },
+            ));
+        }
+        offset += line.len();
+    }
+    Ok((
+        "",
+        Math {
+            meta: meta.trim_end().into(),
+            value: input.trim_end_matches(['\n', '\r']).into(),
+        },
+    ))
+}
+
+/// Inline math opens with a string of dollar signs and closes with
+/// the next string of dollar signs of the same length, like code
+/// spans do.
+///
+/// Single dollars are also used for money, so they come with the
+/// extra rules pandoc uses: the opening dollar has to be followed by
+/// a non-space character, and the closing dollar has to follow a
+/// non-space character and can't be followed by a digit. That way
+/// `$5 and $10` stays text.
+pub fn inline_math(input: &str) -> IResult<&str, InlineMath<'_>, ErrorTree<&str>> {
+    let (rest, fence) = take_while1(|c| c == '$')(input)?;
+    let single = fence.len() == 1;
+    if single && rest.starts_with(char::is_whitespace) {
+        return Err(nom::Err::Error(ErrorTree::from_error_kind(
+            rest,
+            ErrorKind::Verify,
+        )));
+    }
+
+    let mut offset = 0;
+    while let Some(start) = rest[offset..].find('$') {
+        let start = offset + start;
+        let run = rest[start..]
+            .find(|c| c != '$')
+            .unwrap_or(rest.len() - start);
+        let after = &rest[start + run..];
+        let closes = run == fence.len()
+            && start > 0
+            && (!single
+                || (!rest[..start].ends_with(char::is_whitespace)
+                    && !after.starts_with(|c: char| c.is_ascii_digit())));
+        if closes {
+            let mut value = &rest[..start];
+            if value.len() >= 2
+                && value.starts_with([' ', '\n'])
+                && value.ends_with([' ', '\n'])
+                && !value.trim().is_empty()
+            {
+                value = &value[1..value.len() - 1];
+            }
+            return Ok((
+                after,
+                InlineMath {
+                    value: value.into(),
+                },
+            ));
+        }
+        offset = start + run;
+    }
+    Err(nom::Err::Error(ErrorTree::from_error_kind(
+        rest,
+        ErrorKind::TakeUntil,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_math() {
+        assert_eq!(
+            math("$$\nx^2\n$$").unwrap(),
+            (
+                "",
+                Math {
+                    meta: "".into(),
+                    value: "x^2".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_math_meta() {
+        assert_eq!(
+            math("$$ label\na\nb\n$$\n\nafter").unwrap(),
+            (
+                "\n\nafter",
+                Math {
+                    meta: "label".into(),
+                    value: "a\nb".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    // a shorter fence doesn't close the block
+    fn parse_math_fence_length() {
+        assert_eq!(
+            math("$$$\n$$\n$$$$").unwrap(),
+            (
+                "",
+                Math {
+                    meta: "".into(),
+                    value: "$$".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_math_unclosed() {
+        assert_eq!(
+            math("$$\nx^2\n").unwrap(),
+            (
+                "",
+                Math {
+                    meta: "".into(),
+                    value: "x^2".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_math_single_line_fails() {
+        assert!(math("$$x$$").is_err());
+        assert!(math("$\nx\n$").is_err());
+    }
+
+    #[test]
+    fn parse_inline_math() {
+        assert_eq!(
+            inline_math("$x^2$ rest").unwrap(),
+            (
+                " rest",
+                InlineMath {
+                    value: "x^2".into()
+                }
+            )
+        );
+        assert_eq!(
+            inline_math("$$ a $ b $$").unwrap(),
+            (
+                "",
+                InlineMath {
+                    value: "a $ b".into()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn parse_inline_math_currency() {
+        assert!(inline_math("$5 and $10").is_err());
+        assert!(inline_math("$ 5$").is_err());
+        assert!(inline_math("$5$0").is_err());
+    }
+
+    #[test]
+    fn stringify_math() {
+        assert_eq!(
+            Math {
+                meta: "".into(),
+                value: "x^2".into()
+            }
+            .to_string(),
+            "$$\nx^2\n$$"
+        );
+        assert_eq!(
+            Math {
+                meta: "".into(),
+                value: "$$".into()
+            }
+            .to_string(),
+            "$$$\n$$\n$$$"
+        );
+        assert_eq!(InlineMath { value: "x".into() }.to_string(), "$x$");
+        assert_eq!(InlineMath { value: " x".into() }.to_string(), "$$  x $$");
+    }
+}
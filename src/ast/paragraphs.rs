@@ -1,52 +1,127 @@
-use nom::{bytes::complete::*, IResult};
+use super::{
+    inlines::{inlines_with_options, Inline},
+    lines::{indentation, split_lines, trim_eol},
+    lists::list_marker,
+    owned,
+    thematic_breaks::is_thematic_break,
+    ParseOptions,
+};
+use nom::{
+    error::{ErrorKind, ParseError},
+    IResult,
+};
 use nom_supreme::error::ErrorTree;
-use std::fmt;
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+use std::{borrow::Cow, fmt};
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Paragraph<'a> {
-    pub words: &'a str,
+    pub words: Cow<'a, str>,
+}
+impl<'a> Paragraph<'a> {
+    /// The inline content of the paragraph, such as code spans
+    pub fn inlines(&self) -> Vec<Inline<'_>> {
+        self.inlines_with_options(ParseOptions::default())
+    }
+
+    pub fn inlines_with_options(&self, options: ParseOptions) -> Vec<Inline<'_>> {
+        inlines_with_options(&self.words, options)
+    }
+
+    pub fn into_owned(self) -> Paragraph<'static> {
+        Paragraph {
+            words: owned(self.words),
+        }
+    }
 }
 impl<'a> fmt::Display for Paragraph<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for line in self.words.lines() {
+        for (i, line) in split_lines(&self.words).map(trim_eol).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
             write!(f, "{}", line)?
         }
         Ok(())
     }
 }
 
-/// Paragraphs can end at \n\n or eof
-///
-/// TODO: they can also be interrupted by lists without
-/// a second newline
+/// Whether `line` starts a block that can interrupt a paragraph,
+/// without a blank line in between
+pub(crate) fn interrupts_paragraph(line: &str) -> bool {
+    let (columns, indent) = indentation(line);
+    if columns > 3 {
+        return false;
+    }
+    let trimmed = &line[indent..];
+    // most lines are just more text, and can't start anything
+    if !trimmed.starts_with(['#', '`', '~', '>', '-', '*', '_', '+', '0', '1']) {
+        return false;
+    }
+    let hashes = trimmed.len() - trimmed.trim_start_matches('#').len();
+    let heading = (1..=6).contains(&hashes)
+        && trimmed[hashes..]
+            .chars()
+            .next()
+            .is_none_or(|c| c == ' ' || c == '\t');
+    // only lists that start at 1 can interrupt a paragraph, so
+    // numbers in the middle of a sentence don't start one
+    let list = list_marker(line).is_some_and(|marker| {
+        !marker.rest.trim().is_empty() && marker.number.is_none_or(|number| number == 1)
+    });
+    heading
+        || list
+        || trimmed.starts_with("```")
+        || trimmed.starts_with("~~~")
+        || trimmed.starts_with('>')
+        || is_thematic_break(line)
+}
+
+/// Paragraphs end at a blank line, at eof, or at a line that
+/// starts another block
 ///
 /// ```md
 /// things
 /// - list item
 /// ```
-pub fn paragraph(input: &str) -> IResult<&str, Paragraph, ErrorTree<&str>> {
-    let result: IResult<&str, &str, ErrorTree<&str>> = take_until("\n\n")(input);
-    match result {
-        Ok((input, para)) => Ok((input, Paragraph { words: para })),
-        Err(e) => {
-            if input == "" {
-                Err(e)
-            } else {
-                Ok(("", Paragraph { words: input }))
-            }
+pub fn paragraph(input: &str) -> IResult<&str, Paragraph<'_>, ErrorTree<&str>> {
+    if input.is_empty() {
+        return Err(nom::Err::Error(ErrorTree::from_error_kind(
+            input,
+            ErrorKind::TakeUntil,
+        )));
+    }
+    let mut end = 0;
+    for (i, line) in split_lines(input).enumerate() {
+        let content = trim_eol(line);
+        if i > 0 && (content.trim().is_empty() || interrupts_paragraph(content)) {
+            break;
         }
+        end += line.len();
     }
+    // the line ending is left for whatever separates the blocks
+    let words = &input[..end];
+    let words = trim_eol(words);
+    Ok((
+        &input[words.len()..],
+        Paragraph {
+            words: words.into(),
+        },
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use nom::{error::ErrorKind, Err::Error};
 
     #[test]
     fn parse_paragraph_dash() {
         assert_eq!(
             paragraph("---\n\n").unwrap(),
-            ("\n\n", Paragraph { words: "---" })
+            (
+                "\n\n",
+                Paragraph {
+                    words: "---".into()
+                }
+            )
         );
     }
 
@@ -57,7 +132,7 @@ mod tests {
             (
                 "\n\n",
                 Paragraph {
-                    words: "a line\nanotherline\nyetanotherline"
+                    words: "a line\nanotherline\nyetanotherline".into()
                 }
             )
         );
@@ -0,0 +1,143 @@
+use super::{inlines::text_content, ATXHeading, MdxAst, ParseOptions};
+use crate::visit::{visit, visit_mut, Visit, VisitControl, VisitMut};
+use std::{borrow::Cow, collections::HashMap};
+
+/// Turns some text into an anchor the same way GitHub does.
+///
+/// The text is lowercased, everything that isn't a letter, a
+/// number, a space, `-` or `_` is dropped and every space
+/// becomes a `-`.
+pub fn slug(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Keeps track of the slugs in a document so they're unique.
+/// Repeats get a `-1`, `-2`, etc suffix, like they do on GitHub.
+#[derive(Debug, Default, Clone)]
+pub struct Slugger {
+    occurrences: HashMap<String, usize>,
+}
+impl Slugger {
+    pub fn slug(&mut self, value: &str) -> String {
+        let original = slug(value);
+        let mut result = original.clone();
+        while self.occurrences.contains_key(&result) {
+            let count = self.occurrences.entry(original.clone()).or_insert(0);
+            *count += 1;
+            result = format!("{}-{}", original, count);
+        }
+        self.occurrences.insert(result.clone(), 0);
+        result
+    }
+
+    /// Marks an id as taken without changing it, for ids that
+    /// were written out by hand
+    pub fn reserve(&mut self, id: &str) {
+        self.occurrences.entry(id.to_string()).or_insert(0);
+    }
+}
+
+struct ExplicitIds<'s> {
+    slugger: &'s mut Slugger,
+}
+impl<'a, 's> Visit<'a> for ExplicitIds<'s> {
+    fn enter_atx_heading(&mut self, heading: &ATXHeading<'a>) -> VisitControl {
+        if let Some(id) = &heading.id {
+            self.slugger.reserve(id);
+        }
+        VisitControl::Continue
+    }
+}
+
+/// Reserves every id that was written out by hand in the tree, so
+/// no generated slug takes one, wherever in the document it is
+pub fn reserve_ids(ast: &[MdxAst], slugger: &mut Slugger) {
+    visit(ast, &mut ExplicitIds { slugger });
+}
+
+struct HeadingIds<'s> {
+    options: ParseOptions,
+    slugger: &'s mut Slugger,
+}
+impl<'a, 's> VisitMut<'a> for HeadingIds<'s> {
+    fn enter_atx_heading(&mut self, heading: &mut ATXHeading<'a>) -> VisitControl {
+        if heading.id.is_none() {
+            let text = text_content(&heading.inlines_with_options(self.options));
+            heading.id = Some(Cow::Owned(self.slugger.slug(&text)));
+        }
+        VisitControl::Continue
+    }
+}
+
+/// Gives every heading in the tree an `id`, in document order.
+/// Headings that already have one keep it, and their ids are
+/// reserved before any slug is generated.
+pub fn add_heading_ids(ast: &mut [MdxAst], options: ParseOptions, slugger: &mut Slugger) {
+    reserve_ids(ast, slugger);
+    visit_mut(ast, &mut HeadingIds { options, slugger });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slug_github() {
+        assert_eq!(slug("Hello, World!"), "hello-world");
+        assert_eq!(slug("  spaces  "), "--spaces--");
+        assert_eq!(slug("snake_case and-dashes"), "snake_case-and-dashes");
+        assert_eq!(slug("Ünïcödé Straße 日本語"), "ünïcödé-straße-日本語");
+        assert_eq!(slug("emoji 🎉 party"), "emoji--party");
+    }
+
+    #[test]
+    fn slugger_dedupes() {
+        let mut slugger = Slugger::default();
+        assert_eq!(slugger.slug("Intro"), "intro");
+        assert_eq!(slugger.slug("Intro"), "intro-1");
+        assert_eq!(slugger.slug("Intro"), "intro-2");
+        assert_eq!(slugger.slug("Intro 1"), "intro-1-1");
+    }
+
+    #[test]
+    fn slugger_reserved() {
+        let mut slugger = Slugger::default();
+        slugger.reserve("intro");
+        assert_eq!(slugger.slug("Intro"), "intro-1");
+    }
+
+    #[test]
+    fn explicit_ids_win_over_earlier_slugs() {
+        let mut ast = crate::parse_with_options(
+            "# Intro\n\n# Intro\n\n> # Later {#intro-1}",
+            ParseOptions {
+                heading_attributes: true,
+                ..ParseOptions::default()
+            },
+        )
+        .unwrap()
+        .ast;
+        add_heading_ids(&mut ast, ParseOptions::default(), &mut Slugger::default());
+        let ids: Vec<_> = ast
+            .iter()
+            .map(|node| match node {
+                MdxAst::ATXHeading(heading) => heading.id.as_deref(),
+                MdxAst::BlockQuote(quote) => match &quote.children[0] {
+                    MdxAst::ATXHeading(heading) => heading.id.as_deref(),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ids, vec![Some("intro"), Some("intro-2"), Some("intro-1")]);
+    }
+}
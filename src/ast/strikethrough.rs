@@ -0,0 +1,106 @@
+use super::{code_spans::inline_code, owned};
+use nom::{
+    bytes::complete::take_while1,
+    error::{ErrorKind, ParseError},
+    IResult,
+};
+use nom_supreme::error::ErrorTree;
+use std::{borrow::Cow, fmt};
+
+/// GFM strikethrough, like `~~gone~~` or `~gone~`.
+///
+/// `value` is the inline markdown between the tildes, which can
+/// have code spans and other inline nodes of its own.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Strikethrough<'a> {
+    pub value: Cow<'a, str>,
+}
+impl<'a> Strikethrough<'a> {
+    pub fn into_owned(self) -> Strikethrough<'static> {
+        Strikethrough {
+            value: owned(self.value),
+        }
+    }
+}
+impl<'a> fmt::Display for Strikethrough<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "~~{}~~", self.value)
+    }
+}
+
+/// Strikethrough opens with one or two tildes and closes with the
+/// next run of as many tildes that isn't in a code span. The text
+/// between them can't start or end with whitespace, so `a ~ b ~ c`
+/// stays text.
+pub fn strikethrough(input: &str) -> IResult<&str, Strikethrough<'_>, ErrorTree<&str>> {
+    let (rest, fence) = take_while1(|c| c == '~')(input)?;
+    if fence.len() > 2 || rest.starts_with(char::is_whitespace) {
+        return Err(nom::Err::Error(ErrorTree::from_error_kind(
+            rest,
+            ErrorKind::Verify,
+        )));
+    }
+    let mut offset = 0;
+    while let Some(found) = rest[offset..].find(['~', '`', '\\']) {
+        let start = offset + found;
+        let after = &rest[start..];
+        if let Some(escaped) = after.strip_prefix('\\') {
+            offset = start + 1 + escaped.chars().next().map_or(0, char::len_utf8);
+            continue;
+        }
+        if after.starts_with('`') {
+            offset = match inline_code(after) {
+                Ok((remaining, _)) => rest.len() - remaining.len(),
+                Err(_) => start + after.find(|c| c != '`').unwrap_or(after.len()),
+            };
+            continue;
+        }
+        let run = after.find(|c| c != '~').unwrap_or(after.len());
+        let value = &rest[..start];
+        if run == fence.len() && start > 0 && !value.ends_with(char::is_whitespace) {
+            return Ok((
+                &after[run..],
+                Strikethrough {
+                    value: value.into(),
+                },
+            ));
+        }
+        offset = start + run;
+    }
+    Err(nom::Err::Error(ErrorTree::from_error_kind(
+        rest,
+        ErrorKind::TakeUntil,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_strikethrough() {
+        assert_eq!(
+            strikethrough("~~gone `~~` too~~ rest").unwrap(),
+            (
+                " rest",
+                Strikethrough {
+                    value: "gone `~~` too".into()
+                }
+            )
+        );
+        assert_eq!(
+            strikethrough("~one~").unwrap().1,
+            Strikethrough {
+                value: "one".into()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_strikethrough_needs_matching_tildes() {
+        assert!(strikethrough("~~a~").is_err());
+        assert!(strikethrough("~~~a~~~").is_err());
+        assert!(strikethrough("~ a ~").is_err());
+        assert!(strikethrough("~~a ~~").is_err());
+    }
+}
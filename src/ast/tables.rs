@@ -0,0 +1,216 @@
+use super::{
+    lines::{indentation, split_lines, trim_eol},
+    owned,
+    paragraphs::interrupts_paragraph,
+};
+use nom::{
+    error::{ErrorKind, ParseError},
+    IResult,
+};
+use nom_supreme::error::ErrorTree;
+use std::{borrow::Cow, fmt};
+
+/// How a column is aligned, from the colons in the delimiter row
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Align {
+    /// `:--`
+    Left,
+    /// `:-:`
+    Center,
+    /// `--:`
+    Right,
+}
+
+/// A GFM table. Every row has a cell for every column, and the
+/// cells keep their inline markdown, pipes escaped.
+///
+/// ```md
+/// | Option | Default |
+/// | ------ | ------: |
+/// | `math` | `false` |
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Table<'a> {
+    /// How each column is aligned, or `None` for the default
+    pub align: Vec<Option<Align>>,
+    /// The header row, then the body rows
+    pub rows: Vec<Vec<Cow<'a, str>>>,
+}
+impl<'a> Table<'a> {
+    pub fn into_owned(self) -> Table<'static> {
+        Table {
+            align: self.align,
+            rows: self
+                .rows
+                .into_iter()
+                .map(|row| row.into_iter().map(owned).collect())
+                .collect(),
+        }
+    }
+}
+impl<'a> fmt::Display for Table<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let row = |cells: &[Cow<str>]| {
+            let cells: Vec<&str> = cells.iter().map(|cell| cell.as_ref()).collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let delimiters: Vec<&str> = self
+            .align
+            .iter()
+            .map(|align| match align {
+                None => "---",
+                Some(Align::Left) => ":--",
+                Some(Align::Center) => ":-:",
+                Some(Align::Right) => "--:",
+            })
+            .collect();
+        for (i, cells) in self.rows.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", row(cells))?;
+            if i == 0 {
+                write!(f, "\n| {} |", delimiters.join(" | "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The cells of a row, split on pipes that aren't escaped. The
+/// pipes at either end are optional.
+fn cells(line: &str) -> Vec<&str> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+    let mut cells = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '|' if !escaped => {
+                cells.push(line[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.push(line[start..].trim());
+    cells
+}
+
+/// The alignment of every column, if `line` is a delimiter row. It
+/// needs a pipe, so a setext heading's underline isn't one.
+fn delimiter_row(line: &str) -> Option<Vec<Option<Align>>> {
+    if !line.contains('|') || indentation(line).0 > 3 {
+        return None;
+    }
+    cells(line)
+        .into_iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.bytes().all(|b| b == b'-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Some(Align::Center),
+                (true, false) => Some(Align::Left),
+                (false, true) => Some(Align::Right),
+                (false, false) => None,
+            })
+        })
+        .collect()
+}
+
+/// A header row, a delimiter row with as many cells, and then body
+/// rows up to a blank line or a line that starts another block. Body
+/// rows with too few cells are filled up with empty ones, and cells
+/// past the last column are dropped.
+pub fn table(input: &str) -> IResult<&str, Table<'_>, ErrorTree<&str>> {
+    let not_a_table = || nom::Err::Error(ErrorTree::from_error_kind(input, ErrorKind::Verify));
+    let mut lines = split_lines(input).map(trim_eol);
+    let header = lines.next().ok_or_else(not_a_table)?;
+    let align = lines
+        .next()
+        .and_then(delimiter_row)
+        .ok_or_else(not_a_table)?;
+    let header_cells = cells(header);
+    if indentation(header).0 > 3 || header_cells.len() != align.len() {
+        return Err(not_a_table());
+    }
+
+    let mut rows = vec![header_cells];
+    let mut end = 0;
+    for (i, line) in split_lines(input).enumerate() {
+        let content = trim_eol(line);
+        if i > 1 {
+            if content.trim().is_empty() || interrupts_paragraph(content) {
+                break;
+            }
+            let mut row = cells(content);
+            row.resize(align.len(), "");
+            rows.push(row);
+        }
+        end += line.len();
+    }
+    let rest = &input[trim_eol(&input[..end]).len()..];
+    Ok((
+        rest,
+        Table {
+            align,
+            rows: rows
+                .into_iter()
+                .map(|row| row.into_iter().map(Cow::Borrowed).collect())
+                .collect(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_table() {
+        let (rest, table) =
+            table("| a | b \\| c | d |\n|:--|:-:|--:|\n| 1 | 2\n\nafter").unwrap();
+        assert_eq!(rest, "\n\nafter");
+        assert_eq!(
+            table.align,
+            vec![Some(Align::Left), Some(Align::Center), Some(Align::Right)]
+        );
+        assert_eq!(
+            table.rows,
+            vec![vec!["a", "b \\| c", "d"], vec!["1", "2", ""]]
+        );
+    }
+
+    #[test]
+    fn parse_table_without_outer_pipes() {
+        let (rest, table) = table("a | b\n--- | ---\n# Heading").unwrap();
+        assert_eq!(rest, "\n# Heading");
+        assert_eq!(table.rows, vec![vec!["a", "b"]]);
+    }
+
+    #[test]
+    fn parse_table_needs_a_delimiter_row() {
+        assert!(table("a | b\n---").is_err());
+        assert!(table("a | b\n| - |").is_err());
+        assert!(table("a\n-").is_err());
+        assert!(table("| a |").is_err());
+    }
+
+    #[test]
+    fn stringify_table() {
+        let input = "| a | b |\n| :-: | --- |\n| 1 | 2 |";
+        assert_eq!(table(input).unwrap().1.to_string(), input);
+        assert_eq!(
+            table("a|b\n-|:-\n1|2").unwrap().1.to_string(),
+            "| a | b |\n| --- | :-- |\n| 1 | 2 |"
+        );
+    }
+}
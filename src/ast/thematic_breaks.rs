@@ -1,4 +1,11 @@
-use nom::{branch::alt, character::complete::space0, multi::fold_many_m_n, IResult};
+use super::lines::eol;
+use nom::{
+    branch::alt,
+    character::complete::space0,
+    combinator::{eof, peek},
+    multi::fold_many_m_n,
+    IResult,
+};
 use nom_supreme::{error::ErrorTree, tag::complete::tag};
 use std::fmt;
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -17,11 +24,11 @@ impl fmt::Display for ThematicBreak {
     }
 }
 
-fn optionally_surrounded_by_spaces<'a, F: 'a, O, E: nom::error::ParseError<&'a str>>(
+fn optionally_surrounded_by_spaces<'a, F, O, E: nom::error::ParseError<&'a str>>(
     inner: F,
 ) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
 where
-    F: Fn(&'a str) -> IResult<&'a str, O, E>,
+    F: 'a + Fn(&'a str) -> IResult<&'a str, O, E>,
 {
     nom::sequence::delimited(space0, inner, space0)
 }
@@ -47,6 +54,8 @@ pub fn thematic_break(input: &str) -> IResult<&str, ThematicBreak, ErrorTree<&st
         0,
         |acc: u8, _| acc + 1,
     )(input)?;
+    // `* **strong**` is a list item, not a break with text after it
+    let (input, _) = peek(alt((eol, eof)))(input)?;
 
     Ok((
         input,
@@ -57,10 +66,21 @@ pub fn thematic_break(input: &str) -> IResult<&str, ThematicBreak, ErrorTree<&st
     ))
 }
 
+/// Whether all of `line` is a thematic break, which takes
+/// precedence over a list item or paragraph starting with it
+pub(crate) fn is_thematic_break(line: &str) -> bool {
+    matches!(thematic_break(line), Ok((rest, _)) if rest.trim().is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use nom::{error::ErrorKind, Err::Error};
+
+    #[test]
+    fn parse_thematic_break_then_text() {
+        assert!(thematic_break("* **strong**").is_err());
+        assert!(thematic_break("- - -  \r\nnext").is_ok());
+    }
 
     #[test]
     fn parse_thematic_break_dash() {
@@ -0,0 +1,374 @@
+//! The `mdx` command line tool.
+//!
+//! Every subcommand that works on one document reads it from a path,
+//! or from stdin when the path is `-` or left out, and writes to
+//! stdout unless it's given `-o`. Diagnostics go to stderr, so
+//! commands can be put together in a pipeline.
+//!
+//! Settings come from the nearest `mdx.toml` to each file, or to the
+//! current directory for stdin, and flags override them.
+use color_eyre::eyre::{Result, WrapErr};
+use mdx::{
+    config::ConfigError, parse_with_options, stringify_with_options, to_html_with_options, Config,
+    Mdx, MdxError, ParseOptions, Settings,
+};
+use lsp_server::Connection;
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+};
+use structopt::{clap::ErrorKind, StructOpt};
+
+mod check;
+mod compile;
+mod fmt;
+
+/// Everything went fine
+pub const SUCCESS: i32 = 0;
+/// A document has errors, or isn't formatted
+pub const FAILURE: i32 = 1;
+/// The command line or a config file doesn't make sense
+pub const USAGE: i32 = 2;
+/// A file couldn't be read or written
+pub const IO_ERROR: i32 = 3;
+
+const EXIT_CODES: &str = "\
+EXIT CODES:
+    0    Success
+    1    A document has errors, or isn't formatted
+    2    The command line or a config file doesn't make sense
+    3    A file couldn't be read or written";
+
+#[derive(StructOpt)]
+#[structopt(name = "mdx", about = "Parse, format and lint MDX", after_help = EXIT_CODES)]
+struct Opt {
+    /// Don't print anything but the output, and report problems only
+    /// through the exit code
+    #[structopt(short, long, global = true)]
+    quiet: bool,
+    /// Print what's happening to stderr
+    #[structopt(short, long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+    /// Use this config file instead of the nearest mdx.toml
+    #[structopt(long, global = true, parse(from_os_str))]
+    config: Option<PathBuf>,
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt)]
+enum Command {
+    /// Parses a document and reports any errors
+    Parse {
+        #[structopt(flatten)]
+        io: Io,
+        #[structopt(flatten)]
+        parse: ParseArgs,
+    },
+    /// Prints the syntax tree of a document
+    Ast {
+        #[structopt(flatten)]
+        io: Io,
+        #[structopt(flatten)]
+        parse: ParseArgs,
+    },
+    /// Writes a document back out as markdown
+    Stringify {
+        #[structopt(flatten)]
+        io: Io,
+        #[structopt(flatten)]
+        parse: ParseArgs,
+        #[structopt(flatten)]
+        style: fmt::StyleArgs,
+    },
+    Compile(compile::CompileArgs),
+    /// Renders a document as HTML
+    ///
+    /// Emphasis, strong text and links aren't rendered yet: they come out
+    /// as the text they were written as, escaped, like `*this*`.
+    Html {
+        #[structopt(flatten)]
+        io: Io,
+        #[structopt(flatten)]
+        parse: ParseArgs,
+    },
+    Fmt(fmt::FmtArgs),
+    Check(check::CheckArgs),
+    /// Runs a language server on stdin and stdout
+    Lsp,
+}
+
+// Where a document comes from and where the output goes. These are
+// flattened into subcommands, so they can't have doc comments, which
+// structopt would use as the subcommand's description.
+#[derive(StructOpt)]
+pub struct Io {
+    /// The file to read, or `-` for stdin
+    #[structopt(default_value = "-")]
+    input: String,
+    /// Writes the output to a file instead of stdout
+    #[structopt(short, long)]
+    output: Option<PathBuf>,
+}
+impl Io {
+    fn name(&self) -> &str {
+        if self.input == "-" {
+            "<stdin>"
+        } else {
+            &self.input
+        }
+    }
+
+    /// The file to read, or `None` for stdin
+    fn path(&self) -> Option<&Path> {
+        if self.input == "-" {
+            None
+        } else {
+            Some(Path::new(&self.input))
+        }
+    }
+
+    fn read(&self) -> Result<String> {
+        if self.input == "-" {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .wrap_err("couldn't read stdin")?;
+            Ok(input)
+        } else {
+            fs::read_to_string(&self.input)
+                .wrap_err_with(|| format!("couldn't read {}", self.input))
+        }
+    }
+
+    fn write(&self, output: &str) -> Result<()> {
+        match &self.output {
+            Some(path) if path.as_os_str() != "-" => fs::write(path, output)
+                .wrap_err_with(|| format!("couldn't write {}", path.display())),
+            _ => io::stdout()
+                .write_all(output.as_bytes())
+                .wrap_err("couldn't write to stdout"),
+        }
+    }
+}
+
+// Syntax extensions to turn on, on top of the ones in the config
+#[derive(StructOpt)]
+pub struct ParseArgs {
+    /// `$$` math blocks and `$` inline math
+    #[structopt(long)]
+    math: bool,
+    /// `:::container`, `::leaf` and `:text` directives
+    #[structopt(long)]
+    directives: bool,
+    /// Trailing `{#id .class}` attributes on headings
+    #[structopt(long)]
+    heading_attributes: bool,
+    /// Slugs for every heading without an `#id`
+    #[structopt(long)]
+    heading_ids: bool,
+    /// GitHub flavored markdown: tables, task lists and strikethrough
+    #[structopt(long)]
+    gfm: bool,
+    /// YAML or TOML frontmatter at the start of the document
+    #[structopt(long)]
+    frontmatter: bool,
+}
+impl ParseArgs {
+    pub fn apply(&self, options: &mut ParseOptions) {
+        options.math |= self.math;
+        options.directives |= self.directives;
+        options.heading_attributes |= self.heading_attributes;
+        options.heading_ids |= self.heading_ids;
+        options.gfm |= self.gfm;
+        options.frontmatter |= self.frontmatter;
+    }
+}
+
+/// How much to print, shared by every subcommand
+pub struct Reporter {
+    quiet: bool,
+    verbose: bool,
+}
+impl Reporter {
+    /// Progress, only shown with `--verbose`
+    pub fn info(&self, message: impl std::fmt::Display) {
+        if self.verbose {
+            eprintln!("{}", message);
+        }
+    }
+
+    /// The results of a command, on stdout
+    pub fn print(&self, message: impl std::fmt::Display) {
+        if !self.quiet {
+            println!("{}", message);
+        }
+    }
+
+    /// Something worth knowing even without `--verbose`
+    pub fn status(&self, message: impl std::fmt::Display) {
+        if !self.quiet {
+            eprintln!("{}", message);
+        }
+    }
+
+    pub fn error(&self, error: &MdxError, source: &str, path: &str) {
+        if !self.quiet {
+            let color = io::stderr().is_terminal();
+            eprintln!(
+                "{}",
+                error.snippet(source).with_path(path).with_color(color)
+            );
+        }
+    }
+}
+
+/// Finds and loads the config for every file, loading each config
+/// file only once
+pub struct Configs {
+    /// The `--config` file, which is used for every file
+    explicit: Option<Config>,
+    loaded: HashMap<PathBuf, Config>,
+}
+impl Configs {
+    fn new(path: Option<&Path>) -> Result<Self, ConfigError> {
+        Ok(Configs {
+            explicit: path.map(Config::load).transpose()?,
+            loaded: HashMap::new(),
+        })
+    }
+
+    /// Forgets every config file that was loaded, so they're loaded
+    /// again when they're needed
+    pub fn reload(&mut self) {
+        self.loaded.clear();
+    }
+
+    /// The settings for `file`, or for stdin if it's `None`
+    pub fn settings(&mut self, file: Option<&Path>) -> Result<Settings, ConfigError> {
+        let config = match &self.explicit {
+            Some(config) => config,
+            None => match Config::find(file.unwrap_or_else(|| Path::new("."))) {
+                Some(path) => {
+                    if !self.loaded.contains_key(&path) {
+                        let config = Config::load(&path)?;
+                        self.loaded.insert(path.clone(), config);
+                    }
+                    &self.loaded[&path]
+                }
+                None => return Ok(Settings::default()),
+            },
+        };
+        Ok(match file {
+            Some(file) => config.settings(file),
+            None => config.defaults(),
+        })
+    }
+}
+
+/// Runs the command line, and returns the exit code
+pub fn run() -> i32 {
+    let opt = match Opt::from_args_safe() {
+        Ok(opt) => opt,
+        Err(error) => match error.kind {
+            ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => {
+                println!("{}", error.message);
+                return SUCCESS;
+            }
+            _ => {
+                eprintln!("{}", error.message);
+                return USAGE;
+            }
+        },
+    };
+    let reporter = Reporter {
+        quiet: opt.quiet,
+        verbose: opt.verbose,
+    };
+    let result = Configs::new(opt.config.as_deref())
+        .map_err(Into::into)
+        .and_then(|mut configs| run_command(opt.command, &mut configs, &reporter));
+    match result {
+        Ok(code) => code,
+        Err(error) if error.is::<ConfigError>() => {
+            eprintln!("{}", error);
+            USAGE
+        }
+        Err(error) => {
+            eprintln!("{:?}", error);
+            IO_ERROR
+        }
+    }
+}
+
+fn run_command(command: Command, configs: &mut Configs, reporter: &Reporter) -> Result<i32> {
+    match command {
+        Command::Parse { io, parse } => {
+            let mut options = configs.settings(io.path())?.parse;
+            parse.apply(&mut options);
+            let input = io.read()?;
+            match parse_with_options(&input, options) {
+                Ok(mdx) => {
+                    reporter.info(format!("{}: {} blocks", io.name(), mdx.ast.len()));
+                    Ok(SUCCESS)
+                }
+                Err(error) => {
+                    reporter.error(&error, &input, io.name());
+                    Ok(FAILURE)
+                }
+            }
+        }
+        Command::Ast { io, parse } => {
+            let mut options = configs.settings(io.path())?.parse;
+            parse.apply(&mut options);
+            single(&io, options, reporter, |mdx| format!("{:#?}\n", mdx.ast))
+        }
+        Command::Stringify { io, parse, style } => {
+            let mut settings = configs.settings(io.path())?;
+            parse.apply(&mut settings.parse);
+            style.apply(&mut settings.stringify);
+            single(&io, settings.parse, reporter, |mdx| {
+                format!("{}\n", stringify_with_options(&mdx, settings.stringify))
+            })
+        }
+        Command::Html { io, parse } => {
+            let mut options = configs.settings(io.path())?.parse;
+            parse.apply(&mut options);
+            single(&io, options, reporter, |mdx| {
+                to_html_with_options(&mdx, options)
+            })
+        }
+        Command::Compile(args) => compile::run(args, configs, reporter),
+        Command::Fmt(args) => fmt::run(args, configs, reporter),
+        Command::Check(args) => check::run(args, configs, reporter),
+        Command::Lsp => {
+            let (connection, io_threads) = Connection::stdio();
+            mdx::lsp::run(&connection)?;
+            // the writer thread only finishes once the connection is gone
+            drop(connection);
+            io_threads.join()?;
+            Ok(SUCCESS)
+        }
+    }
+}
+
+/// Parses one document and writes out whatever `f` makes of it
+fn single<F>(io: &Io, options: ParseOptions, reporter: &Reporter, f: F) -> Result<i32>
+where
+    F: FnOnce(Mdx) -> String,
+{
+    let input = io.read()?;
+    reporter.info(format!("parsing {}", io.name()));
+    match parse_with_options(&input, options) {
+        Ok(mdx) => {
+            io.write(&f(mdx))?;
+            Ok(SUCCESS)
+        }
+        Err(error) => {
+            reporter.error(&error, &input, io.name());
+            Ok(FAILURE)
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use super::{Configs, ParseArgs, Reporter, FAILURE, SUCCESS};
+use color_eyre::eyre::{bail, Result, WrapErr};
+use mdx::processor::Severity;
+use serde_json::json;
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+/// Parses and lints MDX files, and exits with 1 if there are any
+/// errors
+#[derive(StructOpt)]
+pub struct CheckArgs {
+    /// How to print diagnostics
+    #[structopt(long, default_value = "human", possible_values = &["human", "json"])]
+    format: String,
+    #[structopt(flatten)]
+    parse: ParseArgs,
+    /// Files, directories to search for .md and .mdx files, or globs
+    /// like `docs/**/*.mdx`
+    #[structopt(required = true)]
+    paths: Vec<String>,
+}
+
+pub fn run(args: CheckArgs, configs: &mut Configs, reporter: &Reporter) -> Result<i32> {
+    let json = args.format == "json";
+    let color = io::stdout().is_terminal();
+    let mut files = vec![];
+    let (mut errors, mut warnings) = (0, 0);
+    for path in find_files(&args.paths)? {
+        let name = path.display().to_string();
+        reporter.info(format!("checking {}", name));
+        let source =
+            fs::read_to_string(&path).wrap_err_with(|| format!("couldn't read {}", name))?;
+        let mut settings = configs.settings(Some(&path))?;
+        args.parse.apply(&mut settings.parse);
+        let diagnostics = settings
+            .linter()
+            .check_with_options(&source, settings.parse);
+        for diagnostic in &diagnostics {
+            match diagnostic.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+                Severity::Info => (),
+            }
+        }
+        if json {
+            files.push(json!({
+                "path": name,
+                "diagnostics": diagnostics
+                    .iter()
+                    .map(|diagnostic| diagnostic.to_json(&source))
+                    .collect::<Vec<_>>(),
+            }));
+        } else {
+            for diagnostic in &diagnostics {
+                reporter.print(
+                    diagnostic
+                        .snippet(&source)
+                        .with_path(&name)
+                        .with_color(color),
+                );
+            }
+        }
+    }
+    if json {
+        reporter.print(serde_json::to_string_pretty(&files)?);
+    } else {
+        reporter.print(format!(
+            "{} error{}, {} warning{}",
+            errors,
+            if errors == 1 { "" } else { "s" },
+            warnings,
+            if warnings == 1 { "" } else { "s" },
+        ));
+    }
+    Ok(if errors > 0 { FAILURE } else { SUCCESS })
+}
+
+/// Every file `paths` points to, sorted, with directories searched
+/// for `.md` and `.mdx` files
+pub fn find_files(paths: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in paths {
+        if path.contains(['*', '?', '[']) {
+            let matches = glob::glob(path).wrap_err_with(|| format!("`{}` isn't a glob", path))?;
+            for entry in matches {
+                let entry = entry?;
+                if entry.is_dir() {
+                    find_in_dir(&entry, &mut files)?;
+                } else if is_markdown(&entry) {
+                    files.push(entry);
+                }
+            }
+        } else {
+            let path = Path::new(path);
+            if path.is_dir() {
+                find_in_dir(path, &mut files)?;
+            } else if path.exists() {
+                // files that were asked for by name are checked whatever
+                // their extension is
+                files.push(path.to_path_buf());
+            } else {
+                bail!("{} doesn't exist", path.display());
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Skips hidden directories and `node_modules`
+fn find_in_dir(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir).wrap_err_with(|| format!("couldn't read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "node_modules" {
+                find_in_dir(&path, files)?;
+            }
+        } else if is_markdown(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "md" || extension == "mdx")
+}
@@ -0,0 +1,320 @@
+use super::{
+    check::{find_files, is_markdown},
+    Configs, Io, ParseArgs, Reporter, FAILURE, SUCCESS, USAGE,
+};
+use color_eyre::eyre::{Result, WrapErr};
+use mdx::{
+    config::{CompileTarget, CONFIG_FILE},
+    processor::{ProcessError, Processor, Severity, VFile},
+    Settings,
+};
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+use structopt::StructOpt;
+
+/// Runs documents through the processor and its plugins
+#[derive(StructOpt)]
+pub struct CompileArgs {
+    #[structopt(flatten)]
+    io: Io,
+    #[structopt(flatten)]
+    parse: ParseArgs,
+    /// Puts a table of contents after a "Table of contents" heading
+    #[structopt(long)]
+    toc: bool,
+    /// What to compile to [default: markdown]
+    #[structopt(long, possible_values = &["markdown", "html"])]
+    to: Option<CompileTarget>,
+    /// Compiles every .md and .mdx file in the input directory into
+    /// this directory, with the same structure
+    #[structopt(long, parse(from_os_str), conflicts_with = "output")]
+    out_dir: Option<PathBuf>,
+    /// Keeps running, and compiles files again when they change
+    #[structopt(long, requires = "out-dir")]
+    watch: bool,
+}
+
+impl CompileArgs {
+    /// The settings for `file`, with the flags on top
+    fn settings(&self, configs: &mut Configs, file: Option<&Path>) -> Result<Settings> {
+        let mut settings = configs.settings(file)?;
+        self.parse.apply(&mut settings.parse);
+        settings.compile.toc |= self.toc;
+        settings.compile.to = self.to.unwrap_or(settings.compile.to);
+        Ok(settings)
+    }
+}
+
+pub fn run(args: CompileArgs, configs: &mut Configs, reporter: &Reporter) -> Result<i32> {
+    let out_dir = match &args.out_dir {
+        Some(out_dir) => out_dir,
+        None => {
+            let mut processor = args.settings(configs, args.io.path())?.processor();
+            let input = args.io.read()?;
+            return match compile(&mut processor, &input, args.io.name(), reporter) {
+                Some(output) => {
+                    args.io.write(&output)?;
+                    Ok(SUCCESS)
+                }
+                None => Ok(FAILURE),
+            };
+        }
+    };
+
+    let src_dir = Path::new(&args.io.input);
+    if !src_dir.is_dir() {
+        reporter.status(format!(
+            "--out-dir needs a directory to compile, and {} isn't one",
+            args.io.name()
+        ));
+        return Ok(USAGE);
+    }
+    let tree = Tree {
+        src_dir: src_dir
+            .canonicalize()
+            .wrap_err_with(|| format!("couldn't read {}", src_dir.display()))?,
+        out_dir: out_dir.clone(),
+        args: &args,
+    };
+    // compiling into the source directory would trigger another
+    // compile for every file that's written
+    if canonicalize_missing(&tree.out_dir)?.starts_with(&tree.src_dir) {
+        reporter.status("--out-dir can't be inside the directory that's compiled");
+        return Ok(USAGE);
+    }
+    fs::create_dir_all(&tree.out_dir)
+        .wrap_err_with(|| format!("couldn't create {}", tree.out_dir.display()))?;
+
+    // watching starts before the first compile, so that nothing that
+    // changes while it runs is missed
+    let watching = match args.watch {
+        true => Some(start_watching(&tree, reporter)?),
+        false => None,
+    };
+    let mut ok = true;
+    for path in find_files(&[tree.src_dir.display().to_string()])? {
+        let compiled = match tree.compile(configs, &path, reporter) {
+            Ok(compiled) => compiled,
+            // like while watching, a file that can't be read or
+            // written doesn't stop the rest
+            Err(error) if watching.is_some() => {
+                reporter.status(format!("{:?}", error));
+                false
+            }
+            Err(error) => return Err(error),
+        };
+        if compiled {
+            reporter.info(format!("compiled {}", path.display()));
+        }
+        ok &= compiled;
+    }
+    match watching {
+        Some(watching) => watch(&tree, watching, configs, reporter),
+        None => Ok(if ok { SUCCESS } else { FAILURE }),
+    }
+}
+
+/// The canonical form of `path`, which might not exist yet: its
+/// closest ancestor that does is canonicalized, and the rest is added
+fn canonicalize_missing(path: &Path) -> Result<PathBuf> {
+    let mut existing = path;
+    let mut missing = vec![];
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    // a relative path with no existing ancestor is in the current
+    // directory
+    let existing = match existing.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => existing,
+    };
+    let mut canonical = existing
+        .canonicalize()
+        .wrap_err_with(|| format!("couldn't read {}", existing.display()))?;
+    canonical.extend(missing.into_iter().rev());
+    Ok(canonical)
+}
+
+/// The directory that's compiled, and where it's compiled to
+struct Tree<'a> {
+    src_dir: PathBuf,
+    out_dir: PathBuf,
+    args: &'a CompileArgs,
+}
+impl Tree<'_> {
+    /// Where `path` in the source directory is compiled to
+    fn output_path(&self, path: &Path, settings: &Settings) -> Option<PathBuf> {
+        let relative = path.strip_prefix(&self.src_dir).ok()?;
+        let output = self.out_dir.join(relative);
+        Some(match settings.compile.to {
+            CompileTarget::Html => output.with_extension("html"),
+            CompileTarget::Markdown => output,
+        })
+    }
+
+    /// Compiles one file, and returns whether it compiled. Errors in
+    /// the document are reported, rather than returned.
+    fn compile(&self, configs: &mut Configs, path: &Path, reporter: &Reporter) -> Result<bool> {
+        let settings = self.args.settings(configs, Some(path))?;
+        let output_path = match self.output_path(path, &settings) {
+            Some(output_path) => output_path,
+            None => return Ok(true),
+        };
+        let name = path.display().to_string();
+        let input = fs::read_to_string(path).wrap_err_with(|| format!("couldn't read {}", name))?;
+        let output = match compile(&mut settings.processor(), &input, &name, reporter) {
+            Some(output) => output,
+            None => return Ok(false),
+        };
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("couldn't create {}", parent.display()))?;
+        }
+        fs::write(&output_path, output)
+            .wrap_err_with(|| format!("couldn't write {}", output_path.display()))?;
+        Ok(true)
+    }
+
+    /// Removes whatever `path` was compiled to, for a file or a
+    /// directory that's gone
+    fn remove(&self, configs: &mut Configs, path: &Path, reporter: &Reporter) -> Result<()> {
+        let mirror = match path.strip_prefix(&self.src_dir) {
+            Ok(relative) => self.out_dir.join(relative),
+            Err(_) => return Ok(()),
+        };
+        let settings = self.args.settings(configs, Some(path))?;
+        let (output, removed) = match self.output_path(path, &settings) {
+            Some(output) if is_markdown(path) && output.is_file() => {
+                let removed = fs::remove_file(&output);
+                (output, removed)
+            }
+            _ if mirror.is_dir() => {
+                let removed = fs::remove_dir_all(&mirror);
+                (mirror, removed)
+            }
+            _ => return Ok(()),
+        };
+        match removed {
+            Ok(()) => reporter.status(format!("removed {}", output.display())),
+            Err(error) => {
+                reporter.status(format!("couldn't remove {}: {}", output.display(), error))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A watcher on `tree`, and the changes it sees
+type Watching = (RecommendedWatcher, Receiver<DebouncedEvent>);
+
+fn start_watching(tree: &Tree, reporter: &Reporter) -> Result<Watching> {
+    let (sender, events) = channel();
+    let mut watcher = watcher(sender, Duration::from_millis(100))?;
+    watcher
+        .watch(&tree.src_dir, RecursiveMode::Recursive)
+        .wrap_err_with(|| format!("couldn't watch {}", tree.src_dir.display()))?;
+    reporter.status(format!("watching {}", tree.src_dir.display()));
+    Ok((watcher, events))
+}
+
+/// Compiles `tree` again whenever something in it changes, and all
+/// of it when a config file in it changes. Only returns if the
+/// directory can't be watched anymore.
+fn watch(
+    tree: &Tree,
+    (_watcher, events): Watching,
+    configs: &mut Configs,
+    reporter: &Reporter,
+) -> Result<i32> {
+    let compile_all = |path: &Path, configs: &mut Configs| -> Result<()> {
+        let paths = if path.is_dir() {
+            find_files(&[path.display().to_string()])?
+        } else if is_markdown(path) {
+            vec![path.to_path_buf()]
+        } else {
+            vec![]
+        };
+        for path in paths {
+            if tree.compile(configs, &path, reporter)? {
+                reporter.status(format!("compiled {}", path.display()));
+            }
+        }
+        Ok(())
+    };
+    // a config can change the settings of every file below it
+    let changed = |path: &Path, configs: &mut Configs| -> Result<()> {
+        if is_config(path) {
+            reporter.status(format!("{} changed", path.display()));
+            configs.reload();
+            compile_all(&tree.src_dir, configs)
+        } else {
+            compile_all(path, configs)
+        }
+    };
+    for event in events {
+        // a file that can't be read or written is reported, and
+        // watching goes on
+        let result = match event {
+            DebouncedEvent::Create(path) | DebouncedEvent::Write(path) => changed(&path, configs),
+            DebouncedEvent::Remove(path) if is_config(&path) => changed(&path, configs),
+            DebouncedEvent::Remove(path) => tree.remove(configs, &path, reporter),
+            DebouncedEvent::Rename(from, to) if is_config(&from) => {
+                changed(&from, configs).and_then(|()| changed(&to, configs))
+            }
+            DebouncedEvent::Rename(from, to) => tree
+                .remove(configs, &from, reporter)
+                .and_then(|()| changed(&to, configs)),
+            DebouncedEvent::Rescan => changed(&tree.src_dir, configs),
+            DebouncedEvent::Error(error, path) => {
+                let path = path.map(|path| format!("{}: ", path.display()));
+                reporter.status(format!("{}{}", path.unwrap_or_default(), error));
+                Ok(())
+            }
+            DebouncedEvent::NoticeWrite(_)
+            | DebouncedEvent::NoticeRemove(_)
+            | DebouncedEvent::Chmod(_) => Ok(()),
+        };
+        if let Err(error) = result {
+            reporter.status(format!("{:?}", error));
+        }
+    }
+    Ok(FAILURE)
+}
+
+fn is_config(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == CONFIG_FILE)
+}
+
+/// Runs one document through `processor`, reporting anything that
+/// went wrong. Returns `None` if it didn't compile.
+fn compile(
+    processor: &mut Processor,
+    input: &str,
+    name: &str,
+    reporter: &Reporter,
+) -> Option<String> {
+    let mut file = VFile::new(input);
+    let result = processor.process(&mut file);
+    if let Err(ProcessError::Parse(error)) = result {
+        reporter.error(&error, input, name);
+        return None;
+    }
+    for message in &file.messages {
+        match message.severity {
+            Severity::Info => reporter.info(format!("{}: {}", name, message)),
+            _ => reporter.status(format!("{}: {}", name, message)),
+        }
+    }
+    result.ok().map(|()| file.value)
+}
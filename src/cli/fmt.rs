@@ -0,0 +1,120 @@
+use super::{Configs, ParseArgs, Reporter, FAILURE, SUCCESS};
+use color_eyre::eyre::{Result, WrapErr};
+use mdx::{ast::ThematicBreak, format, HeadingStyle, ListItemIndent, StringifyOptions};
+use std::{fs, path::PathBuf};
+use structopt::StructOpt;
+
+// How markdown is written out, for `fmt` and `stringify`. Anything
+// that's left out comes from the config.
+#[derive(StructOpt)]
+pub struct StyleArgs {
+    /// The list bullet [default: -]
+    #[structopt(long, possible_values = &["-", "*", "+"])]
+    bullet: Option<char>,
+    /// The emphasis marker, or keep each one as it was
+    #[structopt(long, possible_values = &["*", "_"])]
+    emphasis: Option<char>,
+    /// The strong marker, or keep each one as it was
+    #[structopt(long, possible_values = &["*", "_"])]
+    strong: Option<char>,
+    /// The code fence character [default: `]
+    #[structopt(long, possible_values = &["`", "~"])]
+    fence: Option<char>,
+    /// How long code fences are, at least 3 [default: 3]
+    #[structopt(long, parse(try_from_str = fence_length))]
+    fence_length: Option<usize>,
+    /// Write level 1 and 2 headings as setext headings
+    #[structopt(long)]
+    setext: bool,
+    /// The thematic break, like `***` or `- - -`, or keep each one as
+    /// it was
+    #[structopt(long, parse(try_from_str = thematic_break))]
+    thematic_break: Option<ThematicBreak>,
+    /// Where list item content starts [default: one]
+    #[structopt(long, possible_values = &["one", "tab", "mixed"])]
+    list_item_indent: Option<String>,
+    /// Refill paragraphs to fit in this many columns
+    #[structopt(long)]
+    line_width: Option<usize>,
+}
+impl StyleArgs {
+    pub fn apply(&self, options: &mut StringifyOptions) {
+        options.bullet = self.bullet.unwrap_or(options.bullet);
+        options.emphasis = self.emphasis.or(options.emphasis);
+        options.strong = self.strong.or(options.strong);
+        options.fence = self.fence.unwrap_or(options.fence);
+        options.fence_length = self.fence_length.unwrap_or(options.fence_length);
+        if self.setext {
+            options.heading_style = HeadingStyle::Setext;
+        }
+        options.thematic_break = self.thematic_break.or(options.thematic_break);
+        options.list_item_indent = match self.list_item_indent.as_deref() {
+            Some("tab") => ListItemIndent::Tab,
+            Some("mixed") => ListItemIndent::Mixed,
+            Some(_) => ListItemIndent::One,
+            None => options.list_item_indent,
+        };
+        options.line_width = self.line_width.or(options.line_width);
+    }
+}
+
+fn fence_length(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(length) if length >= 3 => Ok(length),
+        _ => Err(format!("`{}` isn't a number that's at least 3", value)),
+    }
+}
+
+/// A thematic break read the way the parser reads one
+fn thematic_break(value: &str) -> Result<ThematicBreak, String> {
+    match mdx::ast::thematic_break(value) {
+        Ok(("", thematic_break)) => Ok(thematic_break),
+        _ => Err(format!("`{}` isn't a thematic break", value)),
+    }
+}
+
+/// Formats MDX files in place
+#[derive(StructOpt)]
+pub struct FmtArgs {
+    /// Don't write anything, list the files that aren't formatted and
+    /// exit with 1 if there are any
+    #[structopt(long)]
+    check: bool,
+    #[structopt(flatten)]
+    parse: ParseArgs,
+    #[structopt(flatten)]
+    style: StyleArgs,
+    #[structopt(required = true)]
+    files: Vec<PathBuf>,
+}
+
+pub fn run(args: FmtArgs, configs: &mut Configs, reporter: &Reporter) -> Result<i32> {
+    let mut code = SUCCESS;
+    for path in &args.files {
+        let mut settings = configs.settings(Some(path))?;
+        args.parse.apply(&mut settings.parse);
+        args.style.apply(&mut settings.stringify);
+        let name = path.display().to_string();
+        let file = fs::read_to_string(path).wrap_err_with(|| format!("couldn't read {}", name))?;
+        let formatted = match format(&file, settings.parse, settings.stringify) {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                reporter.error(&error, &file, &name);
+                code = FAILURE;
+                continue;
+            }
+        };
+        if formatted == file {
+            reporter.info(format!("{} is formatted", name));
+            continue;
+        }
+        if args.check {
+            reporter.print(&name);
+            code = FAILURE;
+        } else {
+            fs::write(path, formatted).wrap_err_with(|| format!("couldn't write {}", name))?;
+            reporter.status(format!("formatted {}", name));
+        }
+    }
+    Ok(code)
+}
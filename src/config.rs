@@ -0,0 +1,606 @@
+//! Settings shared by the library and every `mdx` subcommand, read
+//! from an `mdx.toml` file.
+//!
+//! The config file that applies to a document is the nearest
+//! `mdx.toml` in the document's directory or one of its parents.
+//! Every table and key is optional:
+//!
+//! ```toml
+//! [parse]
+//! math = true
+//! directives = true
+//!
+//! [lint]
+//! heading-increment = "error"
+//! first-heading-level = "off"
+//!
+//! [stringify]
+//! bullet = "*"
+//! heading-style = "setext"
+//! line-width = 80
+//!
+//! [compile]
+//! toc = true
+//! to = "html"
+//!
+//! # Files matching any of the globs, relative to the config file,
+//! # get these settings on top of the ones above. Later overrides win.
+//! [[overrides]]
+//! files = ["api/**/*.mdx"]
+//! lint = { fenced-code-language-required = "off" }
+//! ```
+//!
+//! [`Config::settings`] works out the [`Settings`] for one file,
+//! which can build the [`Linter`] and [`Processor`] for it.
+use crate::{
+    ast::thematic_break,
+    lint::{Linter, RuleLevel},
+    stringify_with_options, to_html_with_options, HeadingStyle, InjectToc, ListItemIndent,
+    ParseOptions, Processor, StringifyOptions, TocOptions,
+};
+use glob::{MatchOptions, Pattern};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// The name of the config file that `Config::discover` looks for
+pub const CONFIG_FILE: &str = "mdx.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file couldn't be read
+    Io(PathBuf, io::Error),
+    /// The config file isn't valid TOML, or has keys or values that
+    /// aren't settings
+    Toml(PathBuf, toml::de::Error),
+    /// A setting has a value that can't be used, like a bullet that
+    /// isn't one or a lint rule that doesn't exist
+    Invalid(PathBuf, String),
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => {
+                write!(f, "couldn't read {}: {}", path.display(), error)
+            }
+            ConfigError::Toml(path, error) => write!(f, "{}: {}", path.display(), error),
+            ConfigError::Invalid(path, message) => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(_, error) => Some(error),
+            ConfigError::Toml(_, error) => Some(error),
+            ConfigError::Invalid(..) => None,
+        }
+    }
+}
+
+/// What `compile` turns documents into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompileTarget {
+    Markdown,
+    Html,
+}
+impl FromStr for CompileTarget {
+    type Err = String;
+
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        match target {
+            "markdown" => Ok(CompileTarget::Markdown),
+            "html" => Ok(CompileTarget::Html),
+            _ => Err(format!(
+                "`{}` isn't something to compile to, use markdown or html",
+                target
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompileOptions {
+    /// Put a table of contents after a "Table of contents" heading
+    pub toc: bool,
+    pub to: CompileTarget,
+}
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            toc: false,
+            to: CompileTarget::Markdown,
+        }
+    }
+}
+
+/// Everything that decides how one file is handled
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Settings {
+    pub parse: ParseOptions,
+    pub stringify: StringifyOptions,
+    /// Levels for the lint rules that are configured. Rules that
+    /// aren't in here keep their default level.
+    pub lint: BTreeMap<String, RuleLevel>,
+    pub compile: CompileOptions,
+}
+impl Settings {
+    /// Every built in rule, at the configured levels
+    pub fn linter(&self) -> Linter {
+        let mut linter = Linter::default();
+        for (rule, level) in &self.lint {
+            linter.set_level(rule, *level);
+        }
+        linter
+    }
+
+    /// A processor that parses with these settings, injects a table
+    /// of contents if `compile.toc` is set, and compiles to
+    /// `compile.to`. Markdown output ends in a newline.
+    pub fn processor(&self) -> Processor {
+        let Settings {
+            parse,
+            stringify,
+            compile,
+            ..
+        } = *self;
+        let mut processor = Processor::new().parse_options(parse);
+        if compile.toc {
+            processor = processor.use_plugin(InjectToc(TocOptions {
+                parse_options: parse,
+                ..TocOptions::default()
+            }));
+        }
+        match compile.to {
+            CompileTarget::Html => {
+                processor.compiler(move |mdx, _| to_html_with_options(&mdx, parse))
+            }
+            CompileTarget::Markdown => processor
+                .compiler(move |mdx, _| format!("{}\n", stringify_with_options(&mdx, stringify))),
+        }
+    }
+}
+
+// Every key in these tables is optional, and only the ones that are
+// set change anything, so overrides can be laid on top of each other.
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ParseConfig {
+    pub math: Option<bool>,
+    pub directives: Option<bool>,
+    pub heading_attributes: Option<bool>,
+    pub heading_ids: Option<bool>,
+    pub gfm: Option<bool>,
+    pub frontmatter: Option<bool>,
+}
+impl ParseConfig {
+    fn apply(&self, options: &mut ParseOptions) {
+        set(&mut options.math, self.math);
+        set(&mut options.directives, self.directives);
+        set(&mut options.heading_attributes, self.heading_attributes);
+        set(&mut options.heading_ids, self.heading_ids);
+        set(&mut options.gfm, self.gfm);
+        set(&mut options.frontmatter, self.frontmatter);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct StringifyConfig {
+    pub bullet: Option<char>,
+    pub emphasis: Option<char>,
+    pub strong: Option<char>,
+    pub fence: Option<char>,
+    pub fence_length: Option<usize>,
+    pub heading_style: Option<HeadingStyle>,
+    /// Like `***` or `- - -`
+    pub thematic_break: Option<String>,
+    pub list_item_indent: Option<ListItemIndent>,
+    pub line_width: Option<usize>,
+}
+impl StringifyConfig {
+    fn apply(&self, options: &mut StringifyOptions) -> Result<(), String> {
+        if let Some(bullet) = self.bullet {
+            options.bullet = one_of("bullet", bullet, &['-', '*', '+'])?;
+        }
+        if let Some(emphasis) = self.emphasis {
+            options.emphasis = Some(one_of("emphasis", emphasis, &['*', '_'])?);
+        }
+        if let Some(strong) = self.strong {
+            options.strong = Some(one_of("strong", strong, &['*', '_'])?);
+        }
+        if let Some(fence) = self.fence {
+            options.fence = one_of("fence", fence, &['`', '~'])?;
+        }
+        match self.fence_length {
+            Some(length) if length < 3 => {
+                return Err(format!(
+                    "fence-length is {}, but has to be at least 3",
+                    length
+                ))
+            }
+            length => set(&mut options.fence_length, length),
+        }
+        set(&mut options.heading_style, self.heading_style);
+        if let Some(value) = &self.thematic_break {
+            options.thematic_break = match thematic_break(value) {
+                Ok(("", thematic_break)) => Some(thematic_break),
+                _ => return Err(format!("`{}` isn't a thematic break", value)),
+            };
+        }
+        set(&mut options.list_item_indent, self.list_item_indent);
+        if self.line_width.is_some() {
+            options.line_width = self.line_width;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CompileConfig {
+    pub toc: Option<bool>,
+    pub to: Option<CompileTarget>,
+}
+impl CompileConfig {
+    fn apply(&self, options: &mut CompileOptions) {
+        set(&mut options.toc, self.toc);
+        set(&mut options.to, self.to);
+    }
+}
+
+/// Settings for the files that match any of `files`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Override {
+    /// Globs, relative to the directory the config file is in
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub parse: ParseConfig,
+    #[serde(default)]
+    pub lint: BTreeMap<String, RuleLevel>,
+    #[serde(default)]
+    pub stringify: StringifyConfig,
+    #[serde(default)]
+    pub compile: CompileConfig,
+}
+
+/// The contents of an `mdx.toml` file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// The file the config was loaded from, if it was loaded from one.
+    /// Override globs are relative to its directory.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    pub parse: ParseConfig,
+    pub lint: BTreeMap<String, RuleLevel>,
+    pub stringify: StringifyConfig,
+    pub compile: CompileConfig,
+    pub overrides: Vec<Override>,
+}
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    /// Reads a config that isn't in a file, so its override globs are
+    /// relative to the current directory
+    fn from_str(config: &str) -> Result<Self, Self::Err> {
+        Config::parse(config, None)
+    }
+}
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let config =
+            fs::read_to_string(path).map_err(|error| ConfigError::Io(path.to_path_buf(), error))?;
+        Config::parse(&config, Some(path))
+    }
+
+    /// The nearest config file to `start`, looking in `start` itself
+    /// if it's a directory, and then in every directory above it
+    pub fn find(start: impl AsRef<Path>) -> Option<PathBuf> {
+        let start = absolute(start.as_ref());
+        let dir = if start.is_dir() {
+            &start
+        } else {
+            start.parent()?
+        };
+        dir.ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Loads the nearest config file to `start`, or returns `None` if
+    /// there isn't one
+    pub fn discover(start: impl AsRef<Path>) -> Result<Option<Config>, ConfigError> {
+        Config::find(start).map(Config::load).transpose()
+    }
+
+    /// The settings for `file`: the defaults, with the top level
+    /// settings on top, and then every override that matches
+    pub fn settings(&self, file: impl AsRef<Path>) -> Settings {
+        let mut settings = self.defaults();
+        let relative = self.relative(file.as_ref());
+        for rule in &self.overrides {
+            if rule.matches(&relative) {
+                self.layer(
+                    &mut settings,
+                    &rule.parse,
+                    &rule.lint,
+                    &rule.stringify,
+                    &rule.compile,
+                );
+            }
+        }
+        settings
+    }
+
+    /// The settings for a document that isn't a file, like stdin,
+    /// which no override applies to
+    pub fn defaults(&self) -> Settings {
+        let mut settings = Settings::default();
+        self.layer(
+            &mut settings,
+            &self.parse,
+            &self.lint,
+            &self.stringify,
+            &self.compile,
+        );
+        settings
+    }
+
+    fn parse(config: &str, path: Option<&Path>) -> Result<Config, ConfigError> {
+        let name = path.unwrap_or_else(|| Path::new(CONFIG_FILE));
+        let mut config: Config =
+            toml::from_str(config).map_err(|error| ConfigError::Toml(name.to_path_buf(), error))?;
+        config.path = path.map(absolute);
+        config
+            .validate()
+            .map_err(|message| ConfigError::Invalid(name.to_path_buf(), message))?;
+        Ok(config)
+    }
+
+    /// Checks everything serde can't, so `settings` can't fail
+    fn validate(&self) -> Result<(), String> {
+        let rules: Vec<&str> = Linter::default().rules().map(|(name, _)| name).collect();
+        let lints = std::iter::once(&self.lint).chain(self.overrides.iter().map(|rule| &rule.lint));
+        for rule in lints.flat_map(BTreeMap::keys) {
+            if !rules.contains(&rule.as_str()) {
+                return Err(format!("there's no lint rule called `{}`", rule));
+            }
+        }
+        let styles = std::iter::once(&self.stringify)
+            .chain(self.overrides.iter().map(|rule| &rule.stringify));
+        for style in styles {
+            style.apply(&mut StringifyOptions::default())?;
+        }
+        for glob in self.overrides.iter().flat_map(|rule| &rule.files) {
+            Pattern::new(glob).map_err(|error| format!("`{}` isn't a glob: {}", glob, error))?;
+        }
+        Ok(())
+    }
+
+    fn layer(
+        &self,
+        settings: &mut Settings,
+        parse: &ParseConfig,
+        lint: &BTreeMap<String, RuleLevel>,
+        stringify: &StringifyConfig,
+        compile: &CompileConfig,
+    ) {
+        parse.apply(&mut settings.parse);
+        settings
+            .lint
+            .extend(lint.iter().map(|(rule, level)| (rule.clone(), *level)));
+        // checked by `validate`
+        let _ = stringify.apply(&mut settings.stringify);
+        compile.apply(&mut settings.compile);
+    }
+
+    /// `file` relative to the config's directory
+    fn relative(&self, file: &Path) -> PathBuf {
+        let file = absolute(file);
+        let root = match self.path.as_ref().and_then(|path| path.parent()) {
+            Some(root) => root.to_path_buf(),
+            None => absolute(Path::new(".")),
+        };
+        match file.strip_prefix(&root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => file,
+        }
+    }
+}
+impl Override {
+    fn matches(&self, relative: &Path) -> bool {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::default()
+        };
+        self.files.iter().any(|glob| {
+            Pattern::new(glob)
+                .map(|pattern| pattern.matches_path_with(relative, options))
+                .unwrap_or(false)
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for RuleLevel {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn set<T>(option: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *option = value;
+    }
+}
+
+fn one_of(key: &str, value: char, allowed: &[char]) -> Result<char, String> {
+    if allowed.contains(&value) {
+        Ok(value)
+    } else {
+        let allowed: Vec<String> = allowed.iter().map(|c| format!("`{}`", c)).collect();
+        Err(format!(
+            "{} can't be `{}`, use {}",
+            key,
+            value,
+            allowed.join(", ")
+        ))
+    }
+}
+
+/// `path` joined onto the current directory if it's relative. Unlike
+/// `canonicalize`, this works for paths that don't exist.
+fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("mdx-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn settings_from_config() {
+        let config: Config = "
+[parse]
+math = true
+gfm = true
+frontmatter = true
+
+[lint]
+heading-increment = \"error\"
+
+[stringify]
+bullet = \"*\"
+heading-style = \"setext\"
+thematic-break = \"***\"
+
+[compile]
+to = \"html\"
+"
+        .parse()
+        .unwrap();
+        let settings = config.settings("doc.mdx");
+        assert!(settings.parse.math);
+        assert!(settings.parse.gfm);
+        assert!(settings.parse.frontmatter);
+        assert!(!settings.parse.directives);
+        assert_eq!(settings.stringify.bullet, '*');
+        assert_eq!(settings.stringify.heading_style, HeadingStyle::Setext);
+        assert!(settings.stringify.thematic_break.is_some());
+        assert_eq!(settings.stringify.fence, '`');
+        assert_eq!(settings.compile.to, CompileTarget::Html);
+        assert!(settings
+            .linter()
+            .rules()
+            .any(|rule| rule == ("heading-increment", RuleLevel::Error)));
+        assert!(settings
+            .linter()
+            .rules()
+            .any(|rule| rule == ("no-empty-headings", RuleLevel::Warn)));
+    }
+
+    #[test]
+    fn overrides() {
+        let config: Config = "
+[lint]
+first-heading-level = \"error\"
+
+[[overrides]]
+files = [\"api/**/*.mdx\"]
+parse = { directives = true }
+lint = { first-heading-level = \"off\" }
+
+[[overrides]]
+files = [\"api/internal/*\"]
+lint = { first-heading-level = \"warn\" }
+"
+        .parse()
+        .unwrap();
+        let level = |file: &str| config.settings(file).lint["first-heading-level"];
+        assert_eq!(level("readme.mdx"), RuleLevel::Error);
+        assert_eq!(level("api/readme.mdx"), RuleLevel::Off);
+        assert_eq!(level("api/v1/readme.mdx"), RuleLevel::Off);
+        assert_eq!(level("api/internal/readme.mdx"), RuleLevel::Warn);
+        // `*` doesn't cross directories
+        assert_eq!(level("docs/api/readme.mdx"), RuleLevel::Error);
+        assert!(config.settings("api/readme.mdx").parse.directives);
+        assert!(!config.settings("readme.mdx").parse.directives);
+    }
+
+    #[test]
+    fn invalid_configs() {
+        let errors = [
+            "[parse]\nsmart-quotes = true",
+            "[parse]\ngfm = \"yes\"",
+            "[lint]\nno-such-rule = \"warn\"",
+            "[lint]\nheading-increment = \"loud\"",
+            "[stringify]\nbullet = \"x\"",
+            "[stringify]\nfence-length = 2",
+            "[stringify]\nthematic-break = \"--\"",
+            "[compile]\nto = \"pdf\"",
+            "[[overrides]]\nfiles = [\"[\"]",
+            "[[overrides]]\nlint = {}",
+        ];
+        for config in &errors {
+            assert!(config.parse::<Config>().is_err(), "{}", config);
+        }
+        let error = "[lint]\nno-such-rule = \"warn\""
+            .parse::<Config>()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "mdx.toml: there's no lint rule called `no-such-rule`"
+        );
+    }
+
+    #[test]
+    fn discover_walks_up() {
+        let dir = scratch("discover");
+        fs::create_dir_all(dir.join("docs/guide")).unwrap();
+        fs::write(dir.join("docs/guide/intro.mdx"), "# Intro").unwrap();
+        assert_eq!(Config::find(dir.join("docs/guide/intro.mdx")), None);
+
+        fs::write(
+            dir.join(CONFIG_FILE),
+            "[[overrides]]\nfiles = [\"docs/**\"]\nparse = { math = true }",
+        )
+        .unwrap();
+        let config = Config::discover(dir.join("docs/guide/intro.mdx"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.path, Some(dir.join(CONFIG_FILE)));
+        // globs are relative to the config, not the current directory
+        assert!(config.settings(dir.join("docs/guide/intro.mdx")).parse.math);
+        assert!(!config.settings(dir.join("intro.mdx")).parse.math);
+
+        fs::write(dir.join("docs").join(CONFIG_FILE), "").unwrap();
+        assert_eq!(
+            Config::find(dir.join("docs/guide")),
+            Some(dir.join("docs").join(CONFIG_FILE))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,808 @@
+//! A lossless view of a document, for tools that change part of a
+//! file and have to leave everything else exactly as it was.
+//!
+//! The document is split into its top level blocks. Each block keeps
+//! the source it was parsed from, indentation and markers included,
+//! along with the trivia in front of it: the line endings and blank
+//! lines between blocks, or a BOM and whitespace at the start of the
+//! file. `Cst::to_source` puts all of that back together, so a
+//! document comes back out byte for byte.
+//!
+//! The blocks inside block quotes, lists, JSX elements and container
+//! directives are [`CstNode`]s of their own, with their spans in the
+//! source. When an edit only changes blocks inside a container, only
+//! those are stringified again, and the markers, indentation and
+//! blank lines around them stay as they were. Anything else that
+//! was edited is stringified again as a whole block.
+use crate::{
+    ast::{
+        block_quotes::quote_content,
+        blocks_recovering,
+        directives::directive_content,
+        fenced_codeblocks, first_ast, frontmatter,
+        jsx::jsx_content,
+        lines::{self, separator},
+        lists::list_content,
+        mdx_ast, slugs, Content,
+    },
+    mdx_error::{MdxError, Span},
+    stringify::{self, StringifyOptions},
+    Mdx, MdxAst, ParseOptions,
+};
+use nom::{
+    character::complete::multispace0,
+    combinator::{eof, recognize},
+    IResult,
+};
+use nom_supreme::error::ErrorTree;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cst<'a> {
+    source: &'a str,
+    options: ParseOptions,
+    blocks: Vec<CstBlock<'a>>,
+    /// Whitespace after the last block
+    trailing: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CstBlock<'a> {
+    /// Everything between the previous block and this one, like
+    /// line endings and blank lines
+    pub leading: Cow<'a, str>,
+    /// Where the block is in the source, or `None` for blocks that
+    /// were inserted
+    pub span: Option<Span>,
+    node: MdxAst<'a>,
+    children: Vec<CstNode>,
+    edited: bool,
+}
+impl<'a> CstBlock<'a> {
+    pub fn node(&self) -> &MdxAst<'a> {
+        &self.node
+    }
+
+    /// The blocks inside the block, if it's a container
+    pub fn children(&self) -> &[CstNode] {
+        &self.children
+    }
+
+    /// Whether the whole block has to be stringified again, rather
+    /// than written out as it was with only the nodes in it that
+    /// were edited stringified again
+    pub fn is_edited(&self) -> bool {
+        self.edited || self.span.is_none()
+    }
+
+    /// Calls `f` on the block and every block inside it, containers
+    /// before what's in them, each with where it is in the source.
+    /// Blocks that were inserted have nowhere to be, and neither do
+    /// blocks inside one that was edited as a whole.
+    pub fn for_each_node<'n, F>(&'n self, mut f: F)
+    where
+        F: FnMut(&'n MdxAst<'a>, Span),
+    {
+        if let (Some(span), false) = (self.span, self.is_edited()) {
+            f(&self.node, span);
+            for_each_node(&self.node, &self.children, &mut f);
+        }
+    }
+}
+
+/// A block inside a container, and where it is in the source. The
+/// items of a list are nodes too, with the blocks in them as their
+/// children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CstNode {
+    pub span: Span,
+    pub children: Vec<CstNode>,
+    /// What the source has in front of every line of the node after
+    /// its first, like `> ` in a block quote, so the node can be put
+    /// back in its place after it's stringified again
+    prefix: String,
+    /// How many spaces the node's first line starts with that stand
+    /// for part of a tab, which is still in the source in front of it
+    tab_spaces: usize,
+    /// Whether the node has lines that carry on a paragraph without
+    /// the prefix, so it can't be stringified again by itself
+    lazy: bool,
+    edited: bool,
+}
+impl CstNode {
+    /// Whether the node has to be stringified again
+    pub fn is_edited(&self) -> bool {
+        self.edited
+    }
+}
+
+/// Where the blocks inside a container parsed from `input` are.
+/// `to_source` turns offsets in `input` into offsets in the source,
+/// and `prefix` is what's in front of the container's lines there.
+/// A container with `lazy` lines has them in its JSX element or
+/// directive too, which don't have a prefix of their own.
+fn layout(
+    input: &str,
+    node: &MdxAst,
+    options: ParseOptions,
+    to_source: &dyn Fn(usize) -> usize,
+    prefix: &str,
+    lazy: bool,
+) -> Vec<CstNode> {
+    match node {
+        MdxAst::BlockQuote(_) => match quote_content(input) {
+            Some((content, _)) => {
+                let marker = &input[..input.find('>').map_or(0, |i| i + 1)];
+                let prefix = format!("{}{} ", prefix, marker);
+                nodes(&content, input, options, to_source, &prefix, false)
+            }
+            None => vec![],
+        },
+        MdxAst::List(_) => match list_content(input, options) {
+            Some(list) => list
+                .items
+                .iter()
+                .map(|item| {
+                    let content = &item.content;
+                    let end = content.source_offset(input, content.text.len());
+                    let prefix = format!("{}{}", prefix, " ".repeat(item.width));
+                    CstNode {
+                        span: Span::new(to_source(item.marker), to_source(end.max(item.marker))),
+                        children: nodes(content, input, options, to_source, &prefix, false),
+                        prefix: prefix.clone(),
+                        tab_spaces: 0,
+                        lazy: false,
+                        edited: false,
+                    }
+                })
+                .collect(),
+            None => vec![],
+        },
+        MdxAst::JsxElement(_) => match jsx_content(input) {
+            Some(content) => nodes(&content, input, options, to_source, prefix, lazy),
+            None => vec![],
+        },
+        MdxAst::ContainerDirective(_) => match directive_content(input, options) {
+            Some(content) => nodes(&content, input, options, to_source, prefix, lazy),
+            None => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+/// The blocks parsed from a container's content, which came from
+/// `input`
+fn nodes(
+    content: &Content,
+    input: &str,
+    options: ParseOptions,
+    to_source: &dyn Fn(usize) -> usize,
+    prefix: &str,
+    lazy: bool,
+) -> Vec<CstNode> {
+    let text: &str = &content.text;
+    let start = text.trim_start_matches([' ', '\t', '\r', '\n']);
+    let leading = &text[..text.len() - start.len()];
+    let parsed = match blocks_from(text, start, leading, false, options, |_| false) {
+        Ok((_, (parsed, _))) => parsed,
+        Err(_) => return vec![],
+    };
+    let source = |offset: usize| to_source(content.source_offset(input, offset));
+    parsed
+        .into_iter()
+        .map(|(_, span, node)| {
+            let to_source = |offset: usize| source(span.start + offset);
+            let lazy = lazy || content.has_lazy_lines(span);
+            let rest = &text[span.start..];
+            CstNode {
+                span: Span::new(source(span.start), source(span.end)),
+                children: layout(rest, &node, options, &to_source, prefix, lazy),
+                prefix: prefix.to_string(),
+                tab_spaces: content.tab_spaces(input, span.start),
+                lazy,
+                edited: false,
+            }
+        })
+        .collect()
+}
+
+/// The blocks right inside a container, each with its node. A list's
+/// are the blocks in its items.
+fn pairs<'n, 'a>(
+    node: &'n MdxAst<'a>,
+    layout: &'n [CstNode],
+) -> Vec<(&'n MdxAst<'a>, &'n CstNode)> {
+    let children = match node {
+        MdxAst::List(list) => {
+            return list
+                .children
+                .iter()
+                .zip(layout)
+                .flat_map(|(item, node)| item.children.iter().zip(&node.children))
+                .collect()
+        }
+        MdxAst::BlockQuote(quote) => &quote.children,
+        MdxAst::JsxElement(element) => &element.children,
+        MdxAst::ContainerDirective(container) => &container.children,
+        _ => return vec![],
+    };
+    children.iter().zip(layout).collect()
+}
+
+fn for_each_node<'n, 'a, F>(node: &'n MdxAst<'a>, layout: &'n [CstNode], f: &mut F)
+where
+    F: FnMut(&'n MdxAst<'a>, Span),
+{
+    for (child, cst) in pairs(node, layout) {
+        if !cst.edited {
+            f(child, cst.span);
+            for_each_node(child, &cst.children, f);
+        }
+    }
+}
+
+/// Marks the nodes in `layout` that changed from `before` to `after`.
+/// Returns false if more than the blocks inside the container
+/// changed, so it has to be stringified again as a whole.
+fn mark(before: &MdxAst, after: &MdxAst, layout: &mut [CstNode]) -> bool {
+    match (before, after) {
+        (MdxAst::List(before), MdxAst::List(after)) => {
+            (before.ordered, before.start, before.spread)
+                == (after.ordered, after.start, after.spread)
+                && before.children.len() == after.children.len()
+                && layout.len() == after.children.len()
+                && before
+                    .children
+                    .iter()
+                    .zip(&after.children)
+                    .zip(layout)
+                    .all(|((before, after), item)| {
+                        (before.spread, before.checked) == (after.spread, after.checked)
+                            && mark_blocks(&before.children, &after.children, &mut item.children)
+                    })
+        }
+        (MdxAst::BlockQuote(before), MdxAst::BlockQuote(after)) => {
+            mark_blocks(&before.children, &after.children, layout)
+        }
+        (MdxAst::JsxElement(before), MdxAst::JsxElement(after)) => {
+            (&before.name, &before.attributes, before.self_closing)
+                == (&after.name, &after.attributes, after.self_closing)
+                && mark_blocks(&before.children, &after.children, layout)
+        }
+        (MdxAst::ContainerDirective(before), MdxAst::ContainerDirective(after)) => {
+            (&before.name, &before.label, &before.attributes)
+                == (&after.name, &after.label, &after.attributes)
+                && mark_blocks(&before.children, &after.children, layout)
+        }
+        _ => false,
+    }
+}
+
+fn mark_blocks(before: &[MdxAst], after: &[MdxAst], layout: &mut [CstNode]) -> bool {
+    if before.len() != after.len() || layout.len() != after.len() {
+        return false;
+    }
+    for ((before, after), node) in before.iter().zip(after).zip(layout) {
+        if before != after && !mark(before, after, &mut node.children) {
+            if node.lazy {
+                return false;
+            }
+            node.edited = true;
+        }
+    }
+    true
+}
+
+/// Puts `prefix` in front of every line of `block` after the first
+fn indent(block: &str, prefix: &str) -> String {
+    let mut out = String::with_capacity(block.len());
+    for (i, line) in block.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+            out.push_str(if line.is_empty() { prefix.trim_end() } else { prefix });
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Blocks, each with the trivia in front of it and where it is
+pub(crate) type Parsed<'a> = Vec<(&'a str, Span, MdxAst<'a>)>;
+
+/// Where `blocks_from` stopped
+pub(crate) enum End<'a> {
+    /// At the end of the document, with the whitespace after the
+    /// last block
+    Trailing(&'a str),
+    /// Before the block at `at`, which `resume` asked for, with the
+    /// trivia in front of that block
+    Resumed { leading: &'a str, at: usize },
+}
+
+/// The same grammar as `mdx_elements`, but holding on to where
+/// everything is
+pub(crate) fn blocks(
+    source: &str,
+    options: ParseOptions,
+) -> IResult<&str, (Parsed<'_>, &str), ErrorTree<&str>> {
+    let (leading, input) = first_block(source);
+    let (rest, (parsed, end)) = blocks_from(source, input, leading, true, options, |_| false)?;
+    match end {
+        End::Trailing(trailing) => Ok((rest, (parsed, trailing))),
+        End::Resumed { .. } => unreachable!("nothing is resumed"),
+    }
+}
+
+/// Splits `source` where its first block starts, after a byte order
+/// mark and any blank lines
+pub(crate) fn first_block(source: &str) -> (&str, &str) {
+    let body = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let input = body.trim_start_matches([' ', '\t', '\r', '\n']);
+    source.split_at(source.len() - input.len())
+}
+
+/// Parses the blocks of `source` from `input` on, which is where a
+/// block starts, with `leading` in front of it. `at_start` is whether
+/// that's the first block of the document. Every block only
+/// depends on the source after it, so this is exactly what a parse of
+/// the whole document finds there. It stops before a block at an
+/// offset that `resume` returns true for, which lets a reparse pick
+/// up the blocks it already had from there.
+pub(crate) fn blocks_from<'a>(
+    source: &'a str,
+    input: &'a str,
+    leading: &'a str,
+    at_start: bool,
+    options: ParseOptions,
+    resume: impl Fn(usize) -> bool,
+) -> IResult<&'a str, (Parsed<'a>, End<'a>), ErrorTree<&'a str>> {
+    let offset = |rest: &str| source.len() - rest.len();
+    let (mut input, mut leading) = (input, leading);
+    let mut parsed = vec![];
+    let mut before = &source[offset(input) - leading.len()..];
+    // an empty document, or one that's only blank lines
+    if at_start && input.is_empty() {
+        return Ok((input, (parsed, End::Trailing(leading))));
+    }
+    loop {
+        let start = offset(input);
+        if resume(start) {
+            return Ok((input, (parsed, End::Resumed { leading, at: start })));
+        }
+        let node = if at_start && parsed.is_empty() {
+            first_ast(options)(input)
+        } else {
+            mdx_ast(options)(input)
+        };
+        match node {
+            Ok((rest, node)) => {
+                parsed.push((leading, Span::new(start, offset(rest)), node));
+                before = rest;
+            }
+            // blank lines at the end, which are trailing whitespace
+            Err(nom::Err::Error(_)) if !(at_start && parsed.is_empty()) => break,
+            Err(error) => return Err(error),
+        }
+        match separator(before) {
+            Ok((rest, separator)) => {
+                leading = separator;
+                input = rest;
+            }
+            Err(nom::Err::Error(_)) => break,
+            Err(error) => return Err(error),
+        }
+    }
+    let (rest, trailing) = recognize(multispace0)(before)?;
+    let (rest, _) = eof(rest)?;
+    Ok((rest, (parsed, End::Trailing(trailing))))
+}
+
+/// Whether a block parsed from `input` might have looked past its
+/// own end and the first line of the next block. JSX, expressions and
+/// directives that aren't closed are searched for all the way to the
+/// end, before the parser settles on a paragraph.
+pub(crate) fn looks_ahead(input: &str, node: &MdxAst) -> bool {
+    matches!(node, MdxAst::Paragraph(_) | MdxAst::ATXHeading(_))
+        && input.trim_start_matches(' ').starts_with(['<', '{', ':'])
+}
+
+/// Whether the first block of a document, parsed from `input`, is
+/// an opening frontmatter fence that isn't closed yet, so a closing
+/// fence anywhere further down would turn it into frontmatter
+pub(crate) fn opens_frontmatter(input: &str, node: &MdxAst, options: ParseOptions) -> bool {
+    options.frontmatter
+        && !matches!(node, MdxAst::Frontmatter(_))
+        && frontmatter::opens_frontmatter(input)
+}
+
+impl<'a> Cst<'a> {
+    pub fn parse(source: &'a str) -> Result<Self, MdxError> {
+        Self::parse_with_options(source, ParseOptions::default())
+    }
+
+    pub fn parse_with_options(source: &'a str, options: ParseOptions) -> Result<Self, MdxError> {
+        let (parsed, trailing) = match blocks(source, options) {
+            Ok((_, blocks)) => blocks,
+            Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => {
+                return Err(MdxError::from_error_tree(source, error))
+            }
+            Err(nom::Err::Incomplete(_)) => {
+                unreachable!("only complete parsers are used")
+            }
+        };
+        Ok(Cst::from_parsed(source, parsed, trailing, options))
+    }
+
+    /// A document from blocks that were already parsed out of
+    /// `source`
+    pub(crate) fn from_parsed(
+        source: &'a str,
+        parsed: Parsed<'a>,
+        trailing: &'a str,
+        options: ParseOptions,
+    ) -> Self {
+        let (trivia, mut ast): (Vec<_>, Vec<_>) = parsed
+            .into_iter()
+            .map(|(leading, span, node)| ((leading, span), node))
+            .unzip();
+        if options.heading_ids {
+            slugs::add_heading_ids(&mut ast, options, &mut slugs::Slugger::default());
+        }
+        let blocks = trivia
+            .into_iter()
+            .zip(ast)
+            .map(|((leading, span), node)| CstBlock {
+                leading: leading.into(),
+                span: Some(span),
+                children: layout(
+                    &source[span.start..],
+                    &node,
+                    options,
+                    &|offset| span.start + offset,
+                    "",
+                    false,
+                ),
+                node,
+                edited: false,
+            })
+            .collect();
+        Cst {
+            source,
+            options,
+            blocks,
+            trailing,
+        }
+    }
+
+    /// A document that doesn't have to parse. What doesn't parse is
+    /// left in the trivia in front of the next block, or after the
+    /// last one, so it still comes back out byte for byte.
+    pub(crate) fn parse_recovering(source: &'a str, options: ParseOptions) -> Self {
+        let (blocks, _) = blocks_recovering(source, options);
+        let mut end = 0;
+        let parsed = blocks
+            .into_iter()
+            .map(|(span, node)| {
+                let leading = &source[end..span.start];
+                end = span.end;
+                (leading, span, node)
+            })
+            .collect();
+        Cst::from_parsed(source, parsed, &source[end..], options)
+    }
+
+    /// The source the document was parsed from, before any edits
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// The options the document was parsed with
+    pub fn options(&self) -> ParseOptions {
+        self.options
+    }
+
+    pub fn blocks(&self) -> &[CstBlock<'a>] {
+        &self.blocks
+    }
+
+    /// What parses, but is probably a mistake, like a code fence
+    /// that's never closed. These are the warnings
+    /// `parse_recovering_with_options` reports for the same source.
+    pub fn warnings(&self) -> Vec<MdxError> {
+        self.blocks
+            .iter()
+            .filter(|block| matches!(block.node, MdxAst::Codeblock(_)))
+            .filter_map(|block| {
+                let span = block.span?;
+                fenced_codeblocks::unclosed_fence(&self.source[span.start..])
+                    .map(|warning| warning.shift(span.start))
+            })
+            .collect()
+    }
+
+    /// Changes the block at `index`. It's only stringified again if
+    /// `f` actually changed it.
+    pub fn edit<F>(&mut self, index: usize, f: F)
+    where
+        F: FnOnce(&mut MdxAst<'a>),
+    {
+        let block = &mut self.blocks[index];
+        let before = block.node.clone();
+        f(&mut block.node);
+        if block.node != before && !mark(&before, &block.node, &mut block.children) {
+            block.edited = true;
+        }
+    }
+
+    /// Calls `f` on every block, like `edit` does
+    pub fn edit_each<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut MdxAst<'a>),
+    {
+        for index in 0..self.blocks.len() {
+            self.edit(index, &mut f);
+        }
+    }
+
+    pub fn replace(&mut self, index: usize, node: MdxAst<'a>) {
+        self.edit(index, |old| *old = node);
+    }
+
+    /// Inserts a block before the block at `index`, separated from
+    /// its neighbours by a blank line
+    pub fn insert(&mut self, index: usize, node: MdxAst<'a>) {
+        let separator = Cow::Owned(self.line_ending().repeat(2));
+        let leading = match self.blocks.first_mut() {
+            // whatever starts the file stays at the start of it
+            Some(first) if index == 0 => std::mem::replace(&mut first.leading, separator),
+            None => Cow::Borrowed(""),
+            Some(_) => separator,
+        };
+        self.blocks.insert(
+            index,
+            CstBlock {
+                leading,
+                span: None,
+                node,
+                children: vec![],
+                edited: true,
+            },
+        );
+    }
+
+    /// Removes the block at `index`, along with the blank lines
+    /// before it
+    pub fn remove(&mut self, index: usize) -> MdxAst<'a> {
+        let removed = self.blocks.remove(index);
+        if index == 0 {
+            if let Some(first) = self.blocks.first_mut() {
+                first.leading = removed.leading;
+            }
+        }
+        removed.node
+    }
+
+    pub fn to_source(&self) -> String {
+        self.to_source_with_options(StringifyOptions::default())
+    }
+
+    /// Writes the document back out. Edited blocks are stringified
+    /// with `options`, using the same line endings as the rest of the
+    /// file.
+    pub fn to_source_with_options(&self, options: StringifyOptions) -> String {
+        let line_ending = self.line_ending();
+        let mut out = String::with_capacity(self.source.len());
+        for block in &self.blocks {
+            out.push_str(&block.leading);
+            match block.span {
+                Some(span) if !block.edited => {
+                    self.write(&mut out, span, &block.node, &block.children, options)
+                }
+                _ => {
+                    let rendered = stringify::block(&block.node, options);
+                    out.push_str(&lines::with_line_ending(rendered, line_ending));
+                }
+            }
+        }
+        out.push_str(self.trailing);
+        out
+    }
+
+    /// Writes out the source of `span`, with the nodes in it that
+    /// were edited stringified again
+    fn write(
+        &self,
+        out: &mut String,
+        span: Span,
+        node: &MdxAst,
+        layout: &[CstNode],
+        options: StringifyOptions,
+    ) {
+        let mut at = span.start;
+        for (child, cst) in pairs(node, layout) {
+            out.push_str(&self.source[at..cst.span.start]);
+            if cst.edited {
+                let rendered = stringify::block(child, options);
+                let spaces = rendered.len() - rendered.trim_start_matches(' ').len();
+                let rendered = indent(&rendered[spaces.min(cst.tab_spaces)..], &cst.prefix);
+                out.push_str(&lines::with_line_ending(rendered, self.line_ending()));
+            } else {
+                self.write(out, cst.span, child, &cst.children, options);
+            }
+            at = cst.span.end;
+        }
+        out.push_str(&self.source[at..span.end]);
+    }
+
+    pub fn into_mdx(self) -> Mdx<'a> {
+        Mdx {
+            ast: self.blocks.into_iter().map(|block| block.node).collect(),
+        }
+    }
+
+    fn line_ending(&self) -> &'static str {
+        lines::line_ending(self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{FencedCodeblock, Paragraph},
+        parse,
+    };
+
+    #[test]
+    fn round_trip_exactly() {
+        let input =
+            "\u{feff}\n\n   # Title   \n  \n\n*  *  *\n\n- a\n-   b\n\n```js\ncode\n```\n\n\n";
+        let cst = Cst::parse(input).unwrap();
+        assert_eq!(cst.to_source(), input);
+        // the BOM is three bytes
+        assert_eq!(cst.blocks()[0].leading, "\u{feff}\n\n   ");
+        assert_eq!(cst.blocks()[0].span, Some(Span::new(8, 18)));
+        assert_eq!(cst.into_mdx(), parse(input).unwrap());
+    }
+
+    #[test]
+    fn round_trip_empty_documents() {
+        for input in ["", "\n\n", "\u{feff} \n"] {
+            let cst = Cst::parse(input).unwrap();
+            assert!(cst.blocks().is_empty());
+            assert_eq!(cst.to_source(), input);
+        }
+    }
+
+    #[test]
+    fn rerender_only_edited_blocks() {
+        let input = "#  Intro\n\n```toml\nversion = \"1.0\"\n```\n\nSee   the  docs.\n";
+        let mut cst = Cst::parse(input).unwrap();
+        cst.edit_each(|node| {
+            if let MdxAst::Codeblock(FencedCodeblock { code, .. }) = node {
+                *code = code.replace("1.0", "2.0").into();
+            }
+        });
+        cst.edit(2, |_| ());
+        assert_eq!(
+            cst.blocks()
+                .iter()
+                .map(CstBlock::is_edited)
+                .collect::<Vec<_>>(),
+            vec![false, true, false]
+        );
+        assert_eq!(cst.to_source(), input.replace("1.0", "2.0"));
+    }
+
+    #[test]
+    fn rerender_only_edited_children() {
+        let input = "> # Notes\n>\n> *  first   item\n> *  second  item\n>    carried on\n";
+        let mut cst = Cst::parse(input).unwrap();
+        cst.edit(0, |node| {
+            if let MdxAst::BlockQuote(quote) = node {
+                if let MdxAst::List(list) = &mut quote.children[1] {
+                    list.children[1].children[0] = MdxAst::Paragraph(Paragraph {
+                        words: "2nd\nitem".into(),
+                    });
+                }
+            }
+        });
+        assert!(!cst.blocks()[0].is_edited());
+        assert_eq!(
+            cst.to_source(),
+            "> # Notes\n>\n> *  first   item\n> *  2nd\n>    item\n"
+        );
+
+        let input = "<Note>\n\n  Some   text\n\n  :::tip\n  More   text\n  :::\n</Note>\n";
+        let options = ParseOptions {
+            directives: true,
+            ..ParseOptions::default()
+        };
+        let mut cst = Cst::parse_with_options(input, options).unwrap();
+        cst.edit(0, |node| {
+            if let MdxAst::JsxElement(element) = node {
+                if let MdxAst::ContainerDirective(tip) = &mut element.children[1] {
+                    tip.children[0] = MdxAst::Paragraph(Paragraph {
+                        words: "Less".into(),
+                    });
+                }
+            }
+        });
+        assert_eq!(
+            cst.to_source(),
+            "<Note>\n\n  Some   text\n\n  :::tip\n  Less\n  :::\n</Note>\n"
+        );
+    }
+
+    #[test]
+    fn rerender_containers_that_changed() {
+        let input = "- one\n-   two\n";
+        let mut cst = Cst::parse(input).unwrap();
+        cst.edit(0, |node| {
+            if let MdxAst::List(list) = node {
+                list.ordered = true;
+            }
+        });
+        assert!(cst.blocks()[0].is_edited());
+        assert_eq!(cst.to_source(), "1. one\n2. two\n");
+
+        // the code carries on in a lazy line, which doesn't have the
+        // item's indentation to put back
+        let input = "- ```\n  one\ntwo\n";
+        let mut cst = Cst::parse(input).unwrap();
+        cst.edit(0, |node| {
+            if let MdxAst::List(list) = node {
+                if let MdxAst::Codeblock(code) = &mut list.children[0].children[0] {
+                    code.code = "three".into();
+                }
+            }
+        });
+        assert!(cst.blocks()[0].is_edited());
+        assert_eq!(cst.to_source(), "- ```\n  three\n  ```\n");
+    }
+
+    #[test]
+    fn nested_spans() {
+        let input = "- a\r\n\r\n  > ### x\r\n  > y\r\n- b\r\n";
+        let cst = Cst::parse(input).unwrap();
+        let mut nodes = vec![];
+        cst.blocks()[0].for_each_node(|node, span| {
+            nodes.push((node.clone(), &input[span.start..span.end]));
+        });
+        let sources: Vec<_> = nodes.iter().map(|(_, source)| *source).collect();
+        assert_eq!(
+            sources,
+            vec![input.trim_end(), "a", "> ### x\r\n  > y", "### x", "y", "b"]
+        );
+        assert!(matches!(nodes[3].0, MdxAst::ATXHeading(_)));
+        // list items are nodes, but not blocks of their own
+        let items = cst.blocks()[0].children();
+        assert_eq!(&input[items[1].span.start..items[1].span.end], "- b");
+    }
+
+    #[test]
+    fn insert_and_remove_blocks() {
+        let input = "\r\n# Old\r\n\r\nText\r\n";
+        let mut cst = Cst::parse(input).unwrap();
+        cst.remove(0);
+        assert_eq!(cst.to_source(), "\r\nText\r\n");
+        cst.insert(
+            0,
+            MdxAst::Paragraph(Paragraph {
+                words: "New\nlines".into(),
+            }),
+        );
+        assert_eq!(cst.to_source(), "\r\nNew\r\nlines\r\n\r\nText\r\n");
+    }
+
+    #[test]
+    fn warnings_match_recovering_parse() {
+        let input = "# Title\n\n```js\nunclosed";
+        let cst = Cst::parse(input).unwrap();
+        assert_eq!(cst.to_source(), input);
+        assert_eq!(
+            cst.warnings(),
+            crate::parse_recovering(input).diagnostics
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! Rendering documents as HTML.
+//!
+//! Every markdown block becomes the element it stands for. JSX
+//! elements are written as tags with their string attributes, so
+//! plain HTML written as JSX comes out the way it went in. Anything
+//! that needs JavaScript to evaluate, like ESM, expressions and
+//! attributes with expression values, is left out.
+//!
+//! Emphasis, strong text and links aren't nodes of the AST, so they're
+//! written as the text they are in the source.
+use crate::{
+    ast::{
+        inlines_with_options, Align, Attribute, ContainerDirective, Inline, JsxAttribute,
+        JsxAttributeValue, JsxElement, LeafDirective, List, Table, TextDirective,
+    },
+    toc::{Toc, TocEntry},
+    Mdx, MdxAst, ParseOptions,
+};
+
+pub fn to_html(mdx: &Mdx) -> String {
+    to_html_with_options(mdx, ParseOptions::default())
+}
+
+/// Renders `mdx`, with `options` deciding which inline syntax is
+/// recognized in headings and paragraphs
+pub fn to_html_with_options(mdx: &Mdx, options: ParseOptions) -> String {
+    let mut out = String::new();
+    blocks(&mut out, &mdx.ast, true, options);
+    out
+}
+
+fn blocks(out: &mut String, nodes: &[MdxAst], spread: bool, options: ParseOptions) {
+    for node in nodes {
+        block(out, node, spread, options);
+    }
+}
+
+/// `spread` is false for the children of tight list items, where
+/// paragraphs aren't wrapped in `<p>`
+fn block(out: &mut String, node: &MdxAst, spread: bool, options: ParseOptions) {
+    match node {
+        MdxAst::ATXHeading(heading) => {
+            out.push_str(&format!("<h{}", heading.level));
+            if let Some(id) = &heading.id {
+                out.push_str(&format!(" id=\"{}\"", escape(id)));
+            }
+            out.push('>');
+            inlines(out, &heading.inlines_with_options(options), options);
+            out.push_str(&format!("</h{}>\n", heading.level));
+        }
+        MdxAst::ThematicBreak(_) => out.push_str("<hr />\n"),
+        MdxAst::Paragraph(paragraph) if spread => {
+            out.push_str("<p>");
+            inlines(out, &paragraph.inlines_with_options(options), options);
+            out.push_str("</p>\n");
+        }
+        MdxAst::Paragraph(paragraph) => {
+            inlines(out, &paragraph.inlines_with_options(options), options);
+            out.push('\n');
+        }
+        MdxAst::Codeblock(codeblock) => {
+            out.push_str("<pre><code");
+            if let Some(language) = codeblock.infostring.split_whitespace().next() {
+                out.push_str(&format!(" class=\"language-{}\"", escape(language)));
+            }
+            out.push('>');
+            out.push_str(&escape(&codeblock.code));
+            out.push_str("</code></pre>\n");
+        }
+        MdxAst::Math(math) => {
+            out.push_str("<div class=\"math math-display\">");
+            out.push_str(&escape(&math.value));
+            out.push_str("</div>\n");
+        }
+        MdxAst::Table(table) => self::table(out, table, options),
+        // frontmatter is data about the document, not part of it
+        MdxAst::Frontmatter(_) => (),
+        MdxAst::LeafDirective(directive) => leaf_directive(out, directive, options),
+        MdxAst::ContainerDirective(directive) => container_directive(out, directive, options),
+        MdxAst::BlockQuote(quote) => {
+            out.push_str("<blockquote>\n");
+            blocks(out, &quote.children, true, options);
+            out.push_str("</blockquote>\n");
+        }
+        MdxAst::List(list) => self::list(out, list, options),
+        MdxAst::JsxElement(element) => jsx_element(out, element, options),
+        MdxAst::Esm(_) | MdxAst::Expression(_) => (),
+        MdxAst::Toc(toc) => self::toc(out, toc),
+    }
+}
+
+fn list(out: &mut String, list: &List, options: ParseOptions) {
+    match (list.ordered, list.start) {
+        (true, Some(start)) if start != 1 => out.push_str(&format!("<ol start=\"{}\">\n", start)),
+        (true, _) => out.push_str("<ol>\n"),
+        (false, _) => out.push_str("<ul>\n"),
+    }
+    for item in &list.children {
+        out.push_str("<li>");
+        if list.spread {
+            out.push('\n');
+        }
+        match item.checked {
+            Some(true) => out.push_str("<input type=\"checkbox\" checked disabled /> "),
+            Some(false) => out.push_str("<input type=\"checkbox\" disabled /> "),
+            None => (),
+        }
+        blocks(out, &item.children, list.spread, options);
+        // tight items end right after their text
+        if !list.spread && out.ends_with('\n') {
+            out.pop();
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str(if list.ordered { "</ol>\n" } else { "</ul>\n" });
+}
+
+fn table(out: &mut String, table: &Table, options: ParseOptions) {
+    out.push_str("<table>\n");
+    for (i, row) in table.rows.iter().enumerate() {
+        let cell = if i == 0 { "th" } else { "td" };
+        match i {
+            0 => out.push_str("<thead>\n"),
+            1 => out.push_str("<tbody>\n"),
+            _ => (),
+        }
+        out.push_str("<tr>\n");
+        for (value, align) in row.iter().zip(&table.align) {
+            out.push_str(&format!("<{}", cell));
+            if let Some(align) = align {
+                let align = match align {
+                    Align::Left => "left",
+                    Align::Center => "center",
+                    Align::Right => "right",
+                };
+                out.push_str(&format!(" align=\"{}\"", align));
+            }
+            out.push('>');
+            inlines(out, &inlines_with_options(value, options), options);
+            out.push_str(&format!("</{}>\n", cell));
+        }
+        out.push_str("</tr>\n");
+        if i == 0 {
+            out.push_str("</thead>\n");
+        }
+    }
+    if table.rows.len() > 1 {
+        out.push_str("</tbody>\n");
+    }
+    out.push_str("</table>\n");
+}
+
+fn jsx_element(out: &mut String, element: &JsxElement, options: ParseOptions) {
+    let name = match &element.name {
+        Some(name) => name,
+        // fragments only group their children
+        None => return blocks(out, &element.children, true, options),
+    };
+    out.push('<');
+    out.push_str(name);
+    for attribute in &element.attributes {
+        if let JsxAttribute::Named { name, value } = attribute {
+            match value {
+                None => out.push_str(&format!(" {}", name)),
+                Some(JsxAttributeValue::String(value)) => {
+                    out.push_str(&format!(" {}=\"{}\"", name, escape(value)))
+                }
+                Some(JsxAttributeValue::Expression(_)) => (),
+            }
+        }
+    }
+    if element.self_closing {
+        out.push_str(" />\n");
+        return;
+    }
+    out.push_str(">\n");
+    blocks(out, &element.children, true, options);
+    out.push_str(&format!("</{}>\n", name));
+}
+
+/// Directives become elements with the directive's name as a class,
+/// like `:::note` becoming `<div class="note">`
+fn directive_tag(out: &mut String, tag: &str, name: &str, attributes: &[Attribute]) {
+    let mut class = name.to_string();
+    out.push_str(&format!("<{}", tag));
+    for attribute in attributes {
+        match attribute.key.as_ref() {
+            "class" => {
+                class.push(' ');
+                class.push_str(&attribute.value);
+            }
+            key => out.push_str(&format!(" {}=\"{}\"", key, escape(&attribute.value))),
+        }
+    }
+    out.push_str(&format!(" class=\"{}\">", escape(&class)));
+}
+
+fn leaf_directive(out: &mut String, directive: &LeafDirective, options: ParseOptions) {
+    directive_tag(out, "div", &directive.name, &directive.attributes);
+    if let Some(label) = &directive.label {
+        inlines(out, &inlines_with_options(label, options), options);
+    }
+    out.push_str("</div>\n");
+}
+
+fn container_directive(out: &mut String, directive: &ContainerDirective, options: ParseOptions) {
+    directive_tag(out, "div", &directive.name, &directive.attributes);
+    out.push('\n');
+    blocks(out, &directive.children, true, options);
+    out.push_str("</div>\n");
+}
+
+fn text_directive(out: &mut String, directive: &TextDirective) {
+    directive_tag(out, "span", &directive.name, &directive.attributes);
+    if let Some(label) = &directive.label {
+        out.push_str(&escape(label));
+    }
+    out.push_str("</span>");
+}
+
+fn inlines(out: &mut String, nodes: &[Inline], options: ParseOptions) {
+    for node in nodes {
+        match node {
+            Inline::Text(text) => out.push_str(&escape(text)),
+            Inline::InlineCode(code) => {
+                out.push_str(&format!("<code>{}</code>", escape(&code.value)))
+            }
+            Inline::InlineMath(math) => out.push_str(&format!(
+                "<span class=\"math math-inline\">{}</span>",
+                escape(&math.value)
+            )),
+            Inline::TextDirective(directive) => text_directive(out, directive),
+            Inline::Strikethrough(strikethrough) => {
+                out.push_str("<del>");
+                inlines(out, &inlines_with_options(&strikethrough.value, options), options);
+                out.push_str("</del>");
+            }
+        }
+    }
+}
+
+fn toc(out: &mut String, toc: &Toc) {
+    if !toc.entries.is_empty() {
+        toc_entries(out, &toc.entries);
+    }
+}
+
+fn toc_entries(out: &mut String, entries: &[TocEntry]) {
+    out.push_str("<ul>\n");
+    for entry in entries {
+        out.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a>",
+            escape(&entry.id),
+            escape(&entry.text)
+        ));
+        if !entry.children.is_empty() {
+            out.push('\n');
+            toc_entries(out, &entry.children);
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_with_options};
+
+    #[test]
+    fn blocks_to_html() {
+        let input = "# Fish & `chips`
+
+- one
+- two
+
+1. a
+
+   b
+
+> quoted <text>
+
+```rust extra
+let x = 1 < 2;
+```
+
+***";
+        assert_eq!(
+            to_html(&parse(input).unwrap()),
+            "<h1>Fish &amp; <code>chips</code></h1>
+<ul>
+<li>one</li>
+<li>two</li>
+</ul>
+<ol>
+<li>
+<p>a</p>
+<p>b</p>
+</li>
+</ol>
+<blockquote>
+<p>quoted &lt;text&gt;</p>
+</blockquote>
+<pre><code class=\"language-rust\">let x = 1 &lt; 2;
+</code></pre>
+<hr />
+"
+        );
+    }
+
+    #[test]
+    fn emphasis_and_links_as_text() {
+        assert_eq!(
+            to_html(&parse("# hi *there*\n\n__strong__ [a link](/to <here>)").unwrap()),
+            "<h1>hi *there*</h1>\n<p>__strong__ [a link](/to &lt;here&gt;)</p>\n"
+        );
+    }
+
+    #[test]
+    fn jsx_and_directives_to_html() {
+        let options = ParseOptions {
+            directives: true,
+            heading_ids: true,
+            ..ParseOptions::default()
+        };
+        let input = "import {Chart} from './chart'
+
+<section class=\"intro\" data={data}>
+
+## Hello
+
+<Chart />
+
+</section>
+
+{new Date().getFullYear()}
+
+:::note{.wide #first}
+Some :abbr[HTML]{title=\"markup\"}
+:::";
+        assert_eq!(
+            to_html_with_options(&parse_with_options(input, options).unwrap(), options),
+            "<section class=\"intro\">
+<h2 id=\"hello\">Hello</h2>
+<Chart />
+</section>
+<div id=\"first\" class=\"note wide\">
+<p>Some <span title=\"markup\" class=\"abbr\">HTML</span></p>
+</div>
+"
+        );
+    }
+}
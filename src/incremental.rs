@@ -0,0 +1,317 @@
+//! Reparsing a document after an edit, for editors that change a
+//! few characters at a time in documents that are much bigger.
+//!
+//! A [`Document`] owns its text and the top level blocks parsed from
+//! it. An [`Edit`] is parsed again from the block before it, since a
+//! block can look at the first line of the next one, like a paragraph
+//! checking whether it carries on. Every block only depends on the
+//! text after it, so as soon as the reparse gets to a block that
+//! started after the edit, everything from there on is what it was:
+//! those blocks are moved over with their spans shifted.
+//!
+//! A block that fell back to a paragraph after looking for a closing
+//! `</Tag>`, `}` or `]` that wasn't there might have looked at the
+//! rest of the document, so reparsing starts at the first of those.
+//! So does a frontmatter fence at the start that isn't closed yet.
+//!
+//! ```
+//! use mdx::{incremental::{Document, Edit}, Cst, ParseOptions, Span};
+//!
+//! let mut document = Document::parse("# Title\n\nOne\n\nTwo\n", ParseOptions::default());
+//! let reparsed = document.edit(Edit { span: Span::new(9, 12), text: "Three" });
+//! assert_eq!(document.text(), "# Title\n\nThree\n\nTwo\n");
+//! // the heading and the last paragraph weren't parsed again
+//! assert_eq!(reparsed, 0..2);
+//! assert_eq!(document.cst(), Cst::parse(document.text()));
+//! ```
+use crate::{
+    cst::{blocks, blocks_from, first_block, looks_ahead, opens_frontmatter, End, Parsed},
+    mdx_error::{MdxError, Span},
+    Cst, MdxAst, ParseOptions,
+};
+use std::ops::Range;
+
+/// Text that replaces part of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edit<'e> {
+    /// What's replaced, in bytes of the text before the edit
+    pub span: Span,
+    pub text: &'e str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    /// The line endings and blank lines between the previous block
+    /// and this one
+    pub leading: Span,
+    pub span: Span,
+    pub node: MdxAst<'static>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    text: String,
+    options: ParseOptions,
+    blocks: Vec<Block>,
+    /// The whitespace after the last block
+    trailing: Span,
+    /// Why the text doesn't parse, in which case there are no blocks
+    error: Option<MdxError>,
+}
+impl Document {
+    pub fn parse(text: impl Into<String>, options: ParseOptions) -> Self {
+        let mut document = Document {
+            text: text.into(),
+            options,
+            blocks: vec![],
+            trailing: Span::default(),
+            error: None,
+        };
+        document.parse_all();
+        document
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn options(&self) -> ParseOptions {
+        self.options
+    }
+
+    /// The top level blocks, or why the document doesn't parse
+    pub fn blocks(&self) -> Result<&[Block], &MdxError> {
+        match &self.error {
+            Some(error) => Err(error),
+            None => Ok(&self.blocks),
+        }
+    }
+
+    /// The document as a `Cst`, which is the same as parsing its text
+    /// with `Cst::parse_with_options`, without parsing anything
+    pub fn cst(&self) -> Result<Cst<'_>, MdxError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        let parsed = self
+            .blocks
+            .iter()
+            .map(|block| (self.slice(block.leading), block.span, block.node.clone()))
+            .collect();
+        Ok(Cst::from_parsed(
+            &self.text,
+            parsed,
+            self.slice(self.trailing),
+            self.options,
+        ))
+    }
+
+    /// Applies `edit`, and parses the blocks it could have changed
+    /// again. Returns the indexes of the blocks that were parsed, and
+    /// everything else was moved over from before. A document that
+    /// didn't parse before the edit is parsed from scratch.
+    ///
+    /// Panics if `edit.span` isn't on char boundaries in the text,
+    /// like `String::replace_range` does.
+    pub fn edit(&mut self, edit: Edit) -> Range<usize> {
+        let Edit { span, text } = edit;
+        self.text.replace_range(span.start..span.end, text);
+        if self.error.is_some() || self.blocks.is_empty() {
+            return self.parse_all();
+        }
+        let shift = |offset: usize| offset + text.len() - (span.end - span.start);
+
+        // the block before the first one that ends at or after the
+        // edit, or the first block that could have looked that far
+        let touched = self
+            .blocks
+            .iter()
+            .position(|block| block.span.end >= span.start)
+            .unwrap_or(self.blocks.len());
+        let mut start = touched.saturating_sub(1);
+        if let Some(risky) = self.blocks[..start]
+            .iter()
+            .position(|block| looks_ahead(&self.text[block.span.start..], &block.node))
+        {
+            start = risky;
+        }
+        // closing a frontmatter fence changes the first block. When
+        // the edit starts after it, it's still where it was.
+        if start > 0
+            && opens_frontmatter(
+                &self.text[self.blocks[0].span.start..],
+                &self.blocks[0].node,
+                self.options,
+            )
+        {
+            start = 0;
+        }
+
+        // the blank lines in front of the first block might be edited
+        let (leading, input) = match start {
+            0 => first_block(&self.text),
+            _ => {
+                let first = &self.blocks[start];
+                (self.slice(first.leading), &self.text[first.span.start..])
+            }
+        };
+        // the blocks that started after the edit, where the text that
+        // follows them hasn't changed
+        let after = self
+            .blocks
+            .partition_point(|block| block.span.start < span.end);
+        let reusable = |offset: usize| {
+            offset >= span.start + text.len()
+                && self.blocks[after..]
+                    .binary_search_by_key(&offset, |block| shift(block.span.start))
+                    .is_ok()
+        };
+        let parsed = blocks_from(
+            &self.text,
+            input,
+            leading,
+            start == 0,
+            self.options,
+            reusable,
+        );
+        let (parsed, end) = match parsed {
+            Ok((_, (parsed, End::Trailing(trailing)))) => (owned(parsed), Err(trailing.len())),
+            Ok((_, (parsed, End::Resumed { leading, at }))) => {
+                (owned(parsed), Ok(Span::new(at - leading.len(), at)))
+            }
+            Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => {
+                let error = MdxError::from_error_tree(&self.text, error);
+                self.fail(error);
+                return 0..0;
+            }
+            Err(nom::Err::Incomplete(_)) => unreachable!("only complete parsers are used"),
+        };
+        let rest = match end {
+            Ok(leading) => {
+                let resumed = after
+                    + self.blocks[after..]
+                        .binary_search_by_key(&leading.end, |block| shift(block.span.start))
+                        .expect("blocks are only resumed where one started");
+                let mut rest = self.blocks.split_off(resumed);
+                // what's in front of a block is everything since the
+                // one before it
+                let mut end = leading.start;
+                for block in &mut rest {
+                    block.span = Span::new(shift(block.span.start), shift(block.span.end));
+                    block.leading = Span::new(end, block.span.start);
+                    end = block.span.end;
+                }
+                self.trailing = Span::new(shift(self.trailing.start), self.text.len());
+                rest
+            }
+            Err(trailing) => {
+                self.trailing = Span::new(self.text.len() - trailing, self.text.len());
+                vec![]
+            }
+        };
+        self.blocks.truncate(start);
+        let reparsed = start..start + parsed.len();
+        self.blocks.extend(parsed);
+        self.blocks.extend(rest);
+        reparsed
+    }
+
+    /// Parses all of the text, returning the indexes of every block
+    fn parse_all(&mut self) -> Range<usize> {
+        match blocks(&self.text, self.options) {
+            Ok((_, (parsed, trailing))) => {
+                let trailing = Span::new(self.text.len() - trailing.len(), self.text.len());
+                self.blocks = owned(parsed);
+                self.trailing = trailing;
+                self.error = None;
+                0..self.blocks.len()
+            }
+            Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => {
+                let error = MdxError::from_error_tree(&self.text, error);
+                self.fail(error);
+                0..0
+            }
+            Err(nom::Err::Incomplete(_)) => unreachable!("only complete parsers are used"),
+        }
+    }
+
+    fn fail(&mut self, error: MdxError) {
+        self.blocks.clear();
+        self.trailing = Span::default();
+        self.error = Some(error);
+    }
+
+    fn slice(&self, span: Span) -> &str {
+        &self.text[span.start..span.end]
+    }
+}
+
+fn owned(parsed: Parsed) -> Vec<Block> {
+    parsed
+        .into_iter()
+        .map(|(leading, span, node)| Block {
+            leading: Span::new(span.start - leading.len(), span.start),
+            span,
+            node: node.into_owned(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_nearby_blocks_are_parsed() {
+        let text = "# A\n\none\n\n## B\n\ntwo\n\n## C\n\nthree\n";
+        let mut document = Document::parse(text, ParseOptions::default());
+        let at = text.find("two").unwrap();
+        let reparsed = document.edit(Edit {
+            span: Span::new(at, at + 3),
+            text: "2\n\n- list",
+        });
+        assert_eq!(reparsed, 2..5);
+        assert_eq!(document.cst(), Cst::parse(document.text()));
+        // the blocks after the edit moved along
+        let three = text.find("three").unwrap() + 6;
+        assert_eq!(
+            document.blocks().unwrap()[6].span,
+            Span::new(three, three + 5)
+        );
+    }
+
+    #[test]
+    fn edits_can_join_blocks() {
+        let text = "intro\n\n<Note>\n\ntext\n\nmore\n\nend\n";
+        let mut document = Document::parse(text, ParseOptions::default());
+        let at = text.find("\n\nend").unwrap();
+        document.edit(Edit {
+            span: Span::new(at, at),
+            text: "\n</Note>",
+        });
+        assert_eq!(document.cst(), Cst::parse(document.text()));
+        assert!(matches!(
+            document.blocks().unwrap()[1].node,
+            MdxAst::JsxElement(_)
+        ));
+    }
+
+    #[test]
+    fn unclosed_fences_come_and_go() {
+        let mut document = Document::parse("# A\n\ntext\n", ParseOptions::default());
+        document.edit(Edit {
+            span: Span::new(5, 5),
+            text: "```\n",
+        });
+        assert_eq!(document.cst(), Cst::parse(document.text()));
+        assert!(matches!(
+            document.blocks().unwrap()[1].node,
+            MdxAst::Codeblock(_)
+        ));
+        document.edit(Edit {
+            span: Span::new(5, 9),
+            text: "",
+        });
+        assert_eq!(document.cst(), Cst::parse("# A\n\ntext\n"));
+    }
+}
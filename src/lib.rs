@@ -1,28 +1,87 @@
-use itertools::Itertools;
-
 // mod headings;
 // mod mdx_ast;
-// mod mdx_error;
 pub mod ast;
-pub use ast::{mdx_elements, MdxAst};
+pub mod config;
+pub mod cst;
+pub mod html;
+pub mod incremental;
+pub mod links;
+pub mod lint;
+pub mod lsp;
+pub mod mdx_error;
+pub mod processor;
+pub mod semantic_tokens;
+pub mod stream;
+pub mod stringify;
+pub mod toc;
+pub mod visit;
+pub use ast::{
+    mdx_elements, mdx_elements_recovering, mdx_elements_with_options, MdxAst, ParseOptions,
+};
+pub use config::{Config, Settings};
+pub use cst::{Cst, CstBlock, CstNode};
+pub use html::{to_html, to_html_with_options};
+pub use mdx_error::{ErrorCode, MdxError, Span};
+pub use processor::{Plugin, Processor, VFile};
+pub use semantic_tokens::{semantic_tokens, SemanticToken, TokenKind};
+pub use stringify::{format, stringify_with_options, HeadingStyle, ListItemIndent, StringifyOptions};
+pub use toc::{inject_toc, toc, InjectToc, Toc, TocOptions};
+pub use visit::{fold, visit, visit_mut, Fold, Visit, VisitControl, VisitMut};
 
-#[derive(Debug, PartialEq, Eq)]
+/// A parsed document.
+///
+/// Nodes borrow from the input they were parsed from, but they
+/// can also own their content, for documents that are built by
+/// hand or that need to outlive their input.
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Mdx<'a> {
     pub ast: Vec<MdxAst<'a>>,
 }
+impl<'a> Mdx<'a> {
+    /// Copies everything the document borrows from its input,
+    /// so it can be cached or sent to another thread after the
+    /// input is gone
+    pub fn into_owned(self) -> Mdx<'static> {
+        Mdx {
+            ast: self.ast.into_iter().map(MdxAst::into_owned).collect(),
+        }
+    }
+}
 
-pub fn parse(
-    input: &str,
-) -> Result<Mdx, nom_supreme::error::ErrorTree<nom_supreme::final_parser::Location>> {
+pub fn parse(input: &str) -> Result<Mdx<'_>, MdxError> {
     mdx_elements(input).map(|ast| Mdx { ast })
 }
 
-// TODO: there's probably a trait we can do for this?
-// maybe Display somehow?
+pub fn parse_with_options(
+    input: &str,
+    options: ParseOptions,
+) -> Result<Mdx<'_>, MdxError> {
+    mdx_elements_with_options(input, options).map(|ast| Mdx { ast })
+}
+
+/// A document parsed by `parse_recovering`, along with everything
+/// that went wrong while parsing it
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Recovered<'a> {
+    pub mdx: Mdx<'a>,
+    pub diagnostics: Vec<MdxError>,
+}
+
+/// Parses a document that might have errors in it, like one that's
+/// in the middle of being edited. Blocks that fail to parse are left
+/// out of the document and reported as diagnostics instead.
+pub fn parse_recovering(input: &str) -> Recovered<'_> {
+    parse_recovering_with_options(input, ParseOptions::default())
+}
+
+pub fn parse_recovering_with_options(input: &str, options: ParseOptions) -> Recovered<'_> {
+    let (ast, diagnostics) = mdx_elements_recovering(input, options);
+    Recovered {
+        mdx: Mdx { ast },
+        diagnostics,
+    }
+}
+
 pub fn stringify(m: Mdx) -> String {
-    m.ast
-        .iter()
-        .map(|ast| format!("{}", ast))
-        .intersperse("\n\n".to_string())
-        .collect::<String>()
+    stringify_with_options(&m, StringifyOptions::default())
 }
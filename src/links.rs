@@ -0,0 +1,409 @@
+//! Where the links in a document are.
+//!
+//! Links are still plain text in the syntax tree, so this reads them
+//! from the source of each top level block, which also gives every
+//! link its span. Code, math, ESM and expressions don't have links.
+//!
+//! ```
+//! use mdx::{links::{links, Link}, Cst};
+//!
+//! let cst = Cst::parse("See [the guide](./guide.mdx#usage).").unwrap();
+//! match &links(&cst)[0] {
+//!     Link::Inline { destination, .. } => assert_eq!(destination.value, "./guide.mdx#usage"),
+//!     link => panic!("{:?}", link),
+//! }
+//! ```
+use crate::{ast::inline_code, mdx_error::Span, Cst, MdxAst};
+use nom::{
+    branch::alt,
+    bytes::complete::take_till,
+    character::complete::{char, line_ending, space0},
+    combinator::{opt, recognize},
+    error::{ErrorKind, ParseError},
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
+};
+use nom_supreme::error::ErrorTree;
+
+/// Where a link or definition goes, as it was written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Destination<'a> {
+    pub value: &'a str,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Link<'a> {
+    /// `[text](destination)`, or an image, `![alt](destination)`
+    Inline {
+        span: Span,
+        destination: Destination<'a>,
+    },
+    /// `[text][label]`, `[label][]`, or `[label]` when there's a
+    /// definition for `label`
+    Reference {
+        span: Span,
+        label: &'a str,
+        label_span: Span,
+    },
+    /// `[label]: destination`, at the start of a paragraph
+    Definition {
+        span: Span,
+        label: &'a str,
+        label_span: Span,
+        destination: Destination<'a>,
+    },
+}
+impl<'a> Link<'a> {
+    pub fn span(&self) -> Span {
+        match self {
+            Link::Inline { span, .. }
+            | Link::Reference { span, .. }
+            | Link::Definition { span, .. } => *span,
+        }
+    }
+
+    /// Where the link goes, for inline links and definitions
+    pub fn destination(&self) -> Option<Destination<'a>> {
+        match self {
+            Link::Inline { destination, .. } | Link::Definition { destination, .. } => {
+                Some(*destination)
+            }
+            Link::Reference { .. } => None,
+        }
+    }
+}
+
+/// Labels match regardless of case and of how much whitespace is in
+/// them, so `[Foo  Bar]` uses the definition `[foo bar]: /url`
+pub fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Every link in the document, in the order they appear
+pub fn links<'a>(cst: &Cst<'a>) -> Vec<Link<'a>> {
+    let source = cst.source();
+    let mut found = vec![];
+    for block in cst.blocks() {
+        let span = match block.span {
+            Some(span) => span,
+            None => continue,
+        };
+        let text = &source[span.start..span.end];
+        match block.node() {
+            MdxAst::Codeblock(_) | MdxAst::Math(_) | MdxAst::Esm(_) | MdxAst::Expression(_) => (),
+            MdxAst::Paragraph(_) => {
+                let rest = definitions(source, text, &mut found);
+                scan(source, rest, &mut found);
+            }
+            _ => scan(source, text, &mut found),
+        }
+    }
+    // references are only links if there's a definition for them,
+    // otherwise they're just text in brackets
+    let labels: Vec<String> = found
+        .iter()
+        .filter_map(|link| match link {
+            Link::Definition { label, .. } => Some(normalize_label(label)),
+            _ => None,
+        })
+        .collect();
+    found.retain(|link| match link {
+        Link::Reference { label, .. } => labels.contains(&normalize_label(label)),
+        _ => true,
+    });
+    found
+}
+
+/// Reads the definitions at the start of a paragraph, and returns the
+/// rest of it
+fn definitions<'a>(source: &'a str, mut text: &'a str, found: &mut Vec<Link<'a>>) -> &'a str {
+    while let Ok((rest, (label, destination))) = definition(text) {
+        let start = offset(source, text);
+        let line = &text[..text.len() - rest.len()];
+        found.push(Link::Definition {
+            span: Span::new(start, start + line.trim_end().len()),
+            label,
+            label_span: span_of(source, label),
+            destination: Destination {
+                value: destination,
+                span: span_of(source, destination),
+            },
+        });
+        text = rest;
+    }
+    text
+}
+
+/// Finds the inline and reference links in `text`, skipping code
+/// spans and escaped brackets
+fn scan<'a>(source: &'a str, text: &'a str, found: &mut Vec<Link<'a>>) {
+    let mut rest = text;
+    while let Some(next) = rest.find(['[', '`', '\\']) {
+        let at = &rest[next..];
+        if at.starts_with('\\') {
+            rest = at.get(1..).map_or("", |after| {
+                let skip = after.chars().next().map_or(0, char::len_utf8);
+                &after[skip..]
+            });
+            continue;
+        }
+        if at.starts_with('`') {
+            rest = match inline_code(at) {
+                Ok((after, _)) => after,
+                Err(_) => at.trim_start_matches('`'),
+            };
+            continue;
+        }
+        let image = rest[..next].ends_with('!');
+        let start = offset(source, at) - image as usize;
+        if let Ok((after, (text, destination))) = inline_link(at) {
+            found.push(Link::Inline {
+                span: Span::new(start, offset(source, after)),
+                destination: Destination {
+                    value: destination,
+                    span: span_of(source, destination),
+                },
+            });
+            // an image can be the text of a link
+            scan(source, text, found);
+            rest = after;
+        } else if let Ok((after, (text, label))) = reference_link(at) {
+            // `[label][]` uses its text as the label
+            let label = if label.is_empty() { text } else { label };
+            found.push(Link::Reference {
+                span: Span::new(start, offset(source, after)),
+                label,
+                label_span: span_of(source, label),
+            });
+            scan(source, text, found);
+            rest = after;
+        } else {
+            rest = &at[1..];
+        }
+    }
+}
+
+/// The text between balanced brackets, which can't have a blank line
+/// in it
+fn bracketed(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    let fail = || nom::Err::Error(ErrorTree::from_error_kind(input, ErrorKind::Char));
+    let inner = input.strip_prefix('[').ok_or_else(fail)?;
+    let mut depth = 0;
+    let mut escaped = false;
+    let mut blank = false;
+    for (i, c) in inner.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => depth += 1,
+            ']' if depth == 0 => return Ok((&inner[i + 1..], &inner[..i])),
+            ']' => depth -= 1,
+            '\n' if blank => return Err(fail()),
+            '\n' => {
+                blank = true;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            _ => (),
+        }
+        blank = false;
+    }
+    Err(fail())
+}
+
+/// `<anything but angle brackets>`, or text without spaces where
+/// parentheses are balanced
+fn destination(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    if let Some(inner) = input.strip_prefix('<') {
+        let (rest, value) = take_till(|c| c == '<' || c == '>' || c == '\n')(inner)?;
+        let (rest, _) = char('>')(rest)?;
+        return Ok((rest, value));
+    }
+    let mut depth = 0;
+    let end = input
+        .char_indices()
+        .find(|&(_, c)| match c {
+            '(' => {
+                depth += 1;
+                false
+            }
+            ')' if depth == 0 => true,
+            ')' => {
+                depth -= 1;
+                false
+            }
+            c => c.is_whitespace(),
+        })
+        .map_or(input.len(), |(i, _)| i);
+    if end == 0 {
+        return Err(nom::Err::Error(ErrorTree::from_error_kind(
+            input,
+            ErrorKind::TakeWhile1,
+        )));
+    }
+    Ok((&input[end..], &input[..end]))
+}
+
+/// `"title"`, `'title'` or `(title)`
+fn title(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    alt((
+        delimited(char('"'), take_till(|c| c == '"'), char('"')),
+        delimited(char('\''), take_till(|c| c == '\''), char('\'')),
+        delimited(char('('), take_till(|c| c == ')'), char(')')),
+    ))(input)
+}
+
+/// Spaces and at most one line ending
+fn whitespace(input: &str) -> IResult<&str, &str, ErrorTree<&str>> {
+    recognize(tuple((space0, opt(line_ending), space0)))(input)
+}
+
+/// `[text](destination "title")`, returning the text and the
+/// destination, which is empty for `[text]()`
+fn inline_link(input: &str) -> IResult<&str, (&str, &str), ErrorTree<&str>> {
+    let (rest, text) = bracketed(input)?;
+    let (rest, _) = terminated(char('('), whitespace)(rest)?;
+    let (rest, destination) = opt(destination)(rest)?;
+    let (rest, _) = opt(preceded(whitespace, title))(rest)?;
+    let (rest, _) = preceded(whitespace, char(')'))(rest)?;
+    Ok((rest, (text, destination.unwrap_or(&rest[..0]))))
+}
+
+/// `[text][label]`, `[text][]` or `[text]`, returning the text and the
+/// label, which is empty for the last two
+fn reference_link(input: &str) -> IResult<&str, (&str, &str), ErrorTree<&str>> {
+    let (rest, text) = bracketed(input)?;
+    // `[text]:` is a definition, or just text
+    if rest.starts_with(':') {
+        return Err(nom::Err::Error(ErrorTree::from_error_kind(
+            rest,
+            ErrorKind::Char,
+        )));
+    }
+    match bracketed(rest) {
+        Ok((after, label)) => Ok((after, (text, label))),
+        Err(_) => Ok((rest, (text, &rest[..0]))),
+    }
+}
+
+/// `[label]: destination "title"` on its own line, indented by at
+/// most 3 spaces
+fn definition(input: &str) -> IResult<&str, (&str, &str), ErrorTree<&str>> {
+    let (rest, indent) = space0(input)?;
+    if indent.len() > 3 {
+        return Err(nom::Err::Error(ErrorTree::from_error_kind(
+            rest,
+            ErrorKind::Space,
+        )));
+    }
+    let (rest, label) = bracketed(rest)?;
+    if label.trim().is_empty() {
+        return Err(nom::Err::Error(ErrorTree::from_error_kind(
+            rest,
+            ErrorKind::Char,
+        )));
+    }
+    let (rest, destination) = preceded(pair(char(':'), whitespace), destination)(rest)?;
+    let (rest, _) = opt(preceded(whitespace, title))(rest)?;
+    let (rest, _) = space0(rest)?;
+    let (rest, _) = alt((line_ending, nom::combinator::eof))(rest)?;
+    Ok((rest, (label, destination)))
+}
+
+fn offset(source: &str, slice: &str) -> usize {
+    slice.as_ptr() as usize - source.as_ptr() as usize
+}
+
+fn span_of(source: &str, slice: &str) -> Span {
+    let start = offset(source, slice);
+    Span::new(start, start + slice.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(source: &str) -> Vec<Link<'_>> {
+        links(&Cst::parse(source).unwrap())
+    }
+
+    fn text(source: &str, span: Span) -> &str {
+        &source[span.start..span.end]
+    }
+
+    #[test]
+    fn inline_links() {
+        let source = "# [Home](/)\n\nRead [the *guide*](./guide.mdx#usage \"Guide\") and ![a (chart)](<chart one.png>).\n\n```\n[not](a-link)\n```";
+        let links = find(source);
+        assert_eq!(links.len(), 3);
+        let destinations: Vec<_> = links
+            .iter()
+            .map(|link| link.destination().unwrap().value)
+            .collect();
+        assert_eq!(
+            destinations,
+            vec!["/", "./guide.mdx#usage", "chart one.png"]
+        );
+        assert_eq!(text(source, links[0].span()), "[Home](/)");
+        assert_eq!(
+            text(source, links[1].span()),
+            "[the *guide*](./guide.mdx#usage \"Guide\")"
+        );
+        assert_eq!(
+            text(source, links[2].span()),
+            "![a (chart)](<chart one.png>)"
+        );
+        let destination = links[1].destination().unwrap();
+        assert_eq!(text(source, destination.span), "./guide.mdx#usage");
+    }
+
+    #[test]
+    fn references_and_definitions() {
+        let source = "[docs]: ./docs.mdx\n[Other Page]: <./other.mdx> 'Other'\n\nSee [the docs][docs], [other page][] and [Other   page], but not [nothing] or `[docs]`.\n\n- [docs]";
+        let links = find(source);
+        let kinds: Vec<_> = links
+            .iter()
+            .map(|link| match link {
+                Link::Inline { .. } => format!("inline {}", text(source, link.span())),
+                Link::Reference { label, .. } => format!("reference {}", label),
+                Link::Definition {
+                    label, destination, ..
+                } => format!("definition {} {}", label, destination.value),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "definition docs ./docs.mdx",
+                "definition Other Page ./other.mdx",
+                "reference docs",
+                "reference other page",
+                "reference Other   page",
+                "reference docs",
+            ]
+        );
+        assert_eq!(text(source, links[0].span()), "[docs]: ./docs.mdx");
+        assert_eq!(text(source, links[3].span()), "[other page][]");
+    }
+
+    #[test]
+    fn nested_and_escaped() {
+        let source = "[![logo](logo.png)](./index.mdx) \\[not](a link)";
+        let links = find(source);
+        let destinations: Vec<_> = links
+            .iter()
+            .map(|link| link.destination().unwrap().value)
+            .collect();
+        assert_eq!(destinations, vec!["./index.mdx", "logo.png"]);
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(normalize_label(" Foo \n  BAR "), "foo bar");
+    }
+}
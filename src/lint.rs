@@ -0,0 +1,311 @@
+//! Checks for documents that parse fine, but don't follow a style,
+//! like remark-lint.
+//!
+//! A [`Rule`] looks at a parsed [`Cst`], so it has the source and
+//! the span of every block to point at, nested ones included. Rules report
+//! [`MdxError`]s with an `ErrorCode::Lint` code, which render the
+//! same way parse errors do. A [`Linter`] runs a set of rules, each
+//! at its own [`RuleLevel`].
+//!
+//! ```
+//! use mdx::lint::{Linter, RuleLevel};
+//!
+//! let mut linter = Linter::default();
+//! linter.set_level("first-heading-level", RuleLevel::Off);
+//! let diagnostics = linter.check("## Intro\n\n#### Details");
+//! assert_eq!(diagnostics[0].code.as_str(), "heading-increment");
+//! ```
+use crate::{
+    ast::ATXHeading,
+    mdx_error::{ErrorCode, MdxError, Span},
+    processor::Severity,
+    Cst, MdxAst, ParseOptions,
+};
+use std::{fmt, str::FromStr};
+
+pub mod fenced_code_language_required;
+pub mod first_heading_level;
+pub mod heading_increment;
+pub mod no_duplicate_headings;
+pub mod no_empty_headings;
+pub mod thematic_break_style;
+
+pub use fenced_code_language_required::FencedCodeLanguageRequired;
+pub use first_heading_level::FirstHeadingLevel;
+pub use heading_increment::HeadingIncrement;
+pub use no_duplicate_headings::NoDuplicateHeadings;
+pub use no_empty_headings::NoEmptyHeadings;
+pub use thematic_break_style::ThematicBreakStyle;
+
+/// How much a rule matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RuleLevel {
+    /// The rule doesn't run
+    Off,
+    Warn,
+    Error,
+}
+impl RuleLevel {
+    fn severity(self) -> Option<Severity> {
+        match self {
+            RuleLevel::Off => None,
+            RuleLevel::Warn => Some(Severity::Warning),
+            RuleLevel::Error => Some(Severity::Error),
+        }
+    }
+}
+impl FromStr for RuleLevel {
+    type Err = String;
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level {
+            "off" => Ok(RuleLevel::Off),
+            "warn" => Ok(RuleLevel::Warn),
+            "error" => Ok(RuleLevel::Error),
+            _ => Err(format!(
+                "`{}` isn't a rule level, use off, warn or error",
+                level
+            )),
+        }
+    }
+}
+impl fmt::Display for RuleLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleLevel::Off => write!(f, "off"),
+            RuleLevel::Warn => write!(f, "warn"),
+            RuleLevel::Error => write!(f, "error"),
+        }
+    }
+}
+
+pub trait Rule {
+    /// The name the rule is configured and reported by, like
+    /// `heading-increment`
+    fn name(&self) -> &'static str;
+    fn check(&self, context: &mut LintContext);
+}
+
+/// What a rule gets to look at, and where it reports what it finds
+pub struct LintContext<'c, 'a> {
+    cst: &'c Cst<'a>,
+    rule: &'static str,
+    severity: Severity,
+    diagnostics: Vec<MdxError>,
+}
+impl<'c, 'a> LintContext<'c, 'a> {
+    pub fn cst(&self) -> &'c Cst<'a> {
+        self.cst
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.cst.source()
+    }
+
+    /// Calls `f` with every node in the document, nested ones
+    /// included, in document order, each with its span. Blocks that
+    /// were edited in the `Cst` have no span, and are left out.
+    pub fn for_each_node<F>(&self, mut f: F)
+    where
+        F: FnMut(Span, &'c MdxAst<'a>),
+    {
+        for block in self.cst.blocks() {
+            block.for_each_node(|node, span| f(span, node));
+        }
+    }
+
+    /// Every heading in the document, as `for_each_node` finds them
+    pub fn headings(&self) -> Vec<(Span, &'c ATXHeading<'a>)> {
+        let mut headings = vec![];
+        self.for_each_node(|span, node| {
+            if let MdxAst::ATXHeading(heading) = node {
+                headings.push((span, heading));
+            }
+        });
+        headings
+    }
+
+    /// A diagnostic for a problem at `span`, with the rule's code and
+    /// severity. It can be given labels and help before it's reported.
+    pub fn diagnostic(&self, span: Span, message: impl Into<String>) -> MdxError {
+        MdxError::new(ErrorCode::Lint(self.rule), message, span).with_severity(self.severity)
+    }
+
+    pub fn report(&mut self, diagnostic: MdxError) {
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+/// A set of rules, each at its own level
+pub struct Linter {
+    rules: Vec<(Box<dyn Rule>, RuleLevel)>,
+}
+impl Default for Linter {
+    /// Every built in rule, as warnings
+    fn default() -> Self {
+        Linter::new()
+            .rule(HeadingIncrement, RuleLevel::Warn)
+            .rule(NoDuplicateHeadings, RuleLevel::Warn)
+            .rule(FirstHeadingLevel::default(), RuleLevel::Warn)
+            .rule(FencedCodeLanguageRequired, RuleLevel::Warn)
+            .rule(NoEmptyHeadings, RuleLevel::Warn)
+            .rule(ThematicBreakStyle::default(), RuleLevel::Warn)
+    }
+}
+impl Linter {
+    /// A linter without any rules
+    pub fn new() -> Self {
+        Linter { rules: vec![] }
+    }
+
+    /// Adds a rule, or replaces the rule that has the same name
+    pub fn rule<R: Rule + 'static>(mut self, rule: R, level: RuleLevel) -> Self {
+        self.rules
+            .retain(|(existing, _)| existing.name() != rule.name());
+        self.rules.push((Box::new(rule), level));
+        self
+    }
+
+    /// Changes the level of the rule called `name`. Returns `false`
+    /// if there's no rule by that name.
+    pub fn set_level(&mut self, name: &str, level: RuleLevel) -> bool {
+        match self.rules.iter_mut().find(|(rule, _)| rule.name() == name) {
+            Some((_, existing)) => {
+                *existing = level;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The name and level of every rule
+    pub fn rules(&self) -> impl Iterator<Item = (&'static str, RuleLevel)> + '_ {
+        self.rules.iter().map(|(rule, level)| (rule.name(), *level))
+    }
+
+    /// Runs every rule that isn't off. Diagnostics are in the order
+    /// they appear in the source, along with the document's parse
+    /// warnings.
+    pub fn lint(&self, cst: &Cst) -> Vec<MdxError> {
+        let mut diagnostics = cst.warnings();
+        for (rule, level) in &self.rules {
+            let severity = match level.severity() {
+                Some(severity) => severity,
+                None => continue,
+            };
+            let mut context = LintContext {
+                cst,
+                rule: rule.name(),
+                severity,
+                diagnostics: vec![],
+            };
+            rule.check(&mut context);
+            diagnostics.append(&mut context.diagnostics);
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        diagnostics
+    }
+
+    /// Parses `source` and lints it. A document that doesn't parse
+    /// only gets its parse error.
+    pub fn check(&self, source: &str) -> Vec<MdxError> {
+        self.check_with_options(source, ParseOptions::default())
+    }
+
+    pub fn check_with_options(&self, source: &str, options: ParseOptions) -> Vec<MdxError> {
+        match Cst::parse_with_options(source, options) {
+            Ok(cst) => self.lint(&cst),
+            Err(error) => vec![error],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoParagraphs;
+    impl Rule for NoParagraphs {
+        fn name(&self) -> &'static str {
+            "no-paragraphs"
+        }
+        fn check(&self, context: &mut LintContext) {
+            let spans: Vec<Span> = context
+                .cst()
+                .blocks()
+                .iter()
+                .filter(|block| matches!(block.node(), crate::MdxAst::Paragraph(_)))
+                .filter_map(|block| block.span)
+                .collect();
+            for span in spans {
+                context.report(
+                    context
+                        .diagnostic(span, "no paragraphs")
+                        .with_help("use a list"),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn levels() {
+        let source = "Text\n\n# Title\n\nMore";
+        let mut linter = Linter::new().rule(NoParagraphs, RuleLevel::Error);
+        let diagnostics = linter.check(source);
+        assert_eq!(
+            diagnostics,
+            vec![
+                MdxError::new(
+                    ErrorCode::Lint("no-paragraphs"),
+                    "no paragraphs",
+                    Span::new(0, 4)
+                )
+                .with_help("use a list"),
+                MdxError::new(
+                    ErrorCode::Lint("no-paragraphs"),
+                    "no paragraphs",
+                    Span::new(15, 19)
+                )
+                .with_help("use a list"),
+            ]
+        );
+
+        assert!(linter.set_level("no-paragraphs", RuleLevel::Warn));
+        assert!(linter
+            .check(source)
+            .iter()
+            .all(|diagnostic| diagnostic.severity == Severity::Warning));
+        assert!(linter.set_level("no-paragraphs", RuleLevel::Off));
+        assert!(linter.check(source).is_empty());
+        assert!(!linter.set_level("no-such-rule", RuleLevel::Off));
+    }
+
+    #[test]
+    fn diagnostics_are_sorted() {
+        let linter = Linter::default();
+        let diagnostics = linter.check("## Intro\n\n#### Details\n\n```\ncode\n```");
+        let codes: Vec<&str> = diagnostics.iter().map(|d| d.code.as_str()).collect();
+        assert_eq!(
+            codes,
+            vec![
+                "first-heading-level",
+                "heading-increment",
+                "fenced-code-language-required"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_warnings_are_reported() {
+        let diagnostics = Linter::new().check("```js\nunclosed");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, ErrorCode::UnclosedFence);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn parse_rule_level() {
+        assert_eq!("warn".parse(), Ok(RuleLevel::Warn));
+        assert!("loud".parse::<RuleLevel>().is_err());
+    }
+}
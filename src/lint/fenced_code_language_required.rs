@@ -0,0 +1,46 @@
+use super::{LintContext, Rule};
+use crate::MdxAst;
+
+/// Every fenced code block says what language it's in
+pub struct FencedCodeLanguageRequired;
+impl Rule for FencedCodeLanguageRequired {
+    fn name(&self) -> &'static str {
+        "fenced-code-language-required"
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        let mut missing = vec![];
+        context.for_each_node(|span, node| {
+            if let MdxAst::Codeblock(codeblock) = node {
+                // the language is the first word of the infostring
+                if codeblock.infostring.trim().is_empty() {
+                    missing.push(span);
+                }
+            }
+        });
+        for span in missing {
+            let diagnostic = context
+                .diagnostic(span, "code blocks should have a language")
+                .with_help(
+                    "add one after the opening fence, like ```js, or ```text for plain text",
+                );
+            context.report(diagnostic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::{Linter, RuleLevel};
+
+    #[test]
+    fn fenced_code_language_required() {
+        let linter = Linter::new().rule(FencedCodeLanguageRequired, RuleLevel::Warn);
+        assert!(linter.check("```rust\nfn main() {}\n```").is_empty());
+        assert_eq!(
+            linter.check("```\ncode\n```\n\n- ~~~\n  code\n  ~~~").len(),
+            2
+        );
+    }
+}
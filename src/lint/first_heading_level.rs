@@ -0,0 +1,49 @@
+use super::{LintContext, Rule};
+
+/// The first heading in a document is at `level`
+pub struct FirstHeadingLevel {
+    pub level: u8,
+}
+impl Default for FirstHeadingLevel {
+    fn default() -> Self {
+        FirstHeadingLevel { level: 1 }
+    }
+}
+impl Rule for FirstHeadingLevel {
+    fn name(&self) -> &'static str {
+        "first-heading-level"
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        if let Some(&(span, heading)) = context.headings().first() {
+            if heading.level != self.level {
+                let diagnostic = context
+                    .diagnostic(
+                        span,
+                        format!("the first heading should be level {}", self.level),
+                    )
+                    .with_help(format!(
+                        "start the heading with {}",
+                        "#".repeat(self.level.into())
+                    ));
+                context.report(diagnostic);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::{Linter, RuleLevel};
+
+    #[test]
+    fn first_heading_level() {
+        let linter = Linter::new().rule(FirstHeadingLevel::default(), RuleLevel::Warn);
+        assert!(linter.check("Text\n\n# Title\n\n### Deep").is_empty());
+        assert_eq!(linter.check("## Title\n\n# Other").len(), 1);
+
+        let linter = Linter::new().rule(FirstHeadingLevel { level: 2 }, RuleLevel::Warn);
+        assert!(linter.check("## Title").is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+use super::{LintContext, Rule};
+
+/// Headings go down one level at a time, so `#` is followed by `##`
+/// and not by `###`
+pub struct HeadingIncrement;
+impl Rule for HeadingIncrement {
+    fn name(&self) -> &'static str {
+        "heading-increment"
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        let mut previous = None;
+        for (span, heading) in context.headings() {
+            if let Some((previous_span, previous_level)) = previous {
+                if heading.level > previous_level + 1 {
+                    let diagnostic = context
+                        .diagnostic(
+                            span,
+                            format!(
+                                "heading levels should go down one at a time, expected level {} or less",
+                                previous_level + 1
+                            ),
+                        )
+                        .with_label(previous_span, format!("level {}", previous_level));
+                    context.report(diagnostic);
+                }
+            }
+            previous = Some((span, heading.level));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lint::{Linter, RuleLevel},
+        Span,
+    };
+
+    #[test]
+    fn heading_increment() {
+        let linter = Linter::new().rule(HeadingIncrement, RuleLevel::Warn);
+        assert!(linter
+            .check("# One\n\n## Two\n\n# One\n\n## Two")
+            .is_empty());
+        let diagnostics = linter.check("# One\n\n### Three\n\n## Two");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, Span::new(7, 16));
+        assert_eq!(diagnostics[0].labels[0].span, Span::new(0, 5));
+
+        // nested headings point at themselves, not the list they're in
+        let diagnostics = linter.check("# One\n\n- item\n\n  ### Three\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, Span::new(17, 26));
+    }
+}
@@ -0,0 +1,50 @@
+use super::{LintContext, Rule};
+use std::collections::HashMap;
+
+/// No two headings at the same level have the same text
+pub struct NoDuplicateHeadings;
+impl Rule for NoDuplicateHeadings {
+    fn name(&self) -> &'static str {
+        "no-duplicate-headings"
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        let mut seen = HashMap::new();
+        for (span, heading) in context.headings() {
+            let text = heading.value.trim();
+            if text.is_empty() {
+                continue;
+            }
+            match seen.get(&(heading.level, text)) {
+                Some(&first) => {
+                    let diagnostic = context
+                        .diagnostic(span, format!("duplicate heading `{}`", text))
+                        .with_label(first, "first used here");
+                    context.report(diagnostic);
+                }
+                None => {
+                    seen.insert((heading.level, text), span);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lint::{Linter, RuleLevel},
+        Span,
+    };
+
+    #[test]
+    fn no_duplicate_headings() {
+        let linter = Linter::new().rule(NoDuplicateHeadings, RuleLevel::Warn);
+        assert!(linter.check("# Usage\n\n## Usage\n\n## Install").is_empty());
+        let diagnostics = linter.check("## Usage\n\n## Install\n\n## Usage ");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "duplicate heading `Usage`");
+        assert_eq!(diagnostics[0].labels[0].span, Span::new(0, 8));
+    }
+}
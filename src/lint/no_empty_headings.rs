@@ -0,0 +1,30 @@
+use super::{LintContext, Rule};
+
+/// Every heading has some text
+pub struct NoEmptyHeadings;
+impl Rule for NoEmptyHeadings {
+    fn name(&self) -> &'static str {
+        "no-empty-headings"
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        for (span, heading) in context.headings() {
+            if heading.value.trim().is_empty() {
+                context.report(context.diagnostic(span, "headings shouldn't be empty"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::{Linter, RuleLevel};
+
+    #[test]
+    fn no_empty_headings() {
+        let linter = Linter::new().rule(NoEmptyHeadings, RuleLevel::Warn);
+        assert!(linter.check("# Title").is_empty());
+        assert_eq!(linter.check("#\n\n## \n\n> ###").len(), 3);
+    }
+}
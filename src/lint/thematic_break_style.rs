@@ -0,0 +1,80 @@
+use super::{LintContext, Rule};
+use crate::MdxAst;
+
+/// Every thematic break is written the same way, as `style` or, when
+/// that's `None`, like the first one in the document
+#[derive(Default)]
+pub struct ThematicBreakStyle {
+    /// Like `***` or `- - -`
+    pub style: Option<String>,
+}
+impl Rule for ThematicBreakStyle {
+    fn name(&self) -> &'static str {
+        "thematic-break-style"
+    }
+
+    fn check(&self, context: &mut LintContext) {
+        let source = context.source();
+        let mut breaks = vec![];
+        // breaks are checked as they were written
+        context.for_each_node(|span, node| {
+            if let MdxAst::ThematicBreak(_) = node {
+                breaks.push((span, source[span.start..span.end].trim().to_string()));
+            }
+        });
+        let mut expected = self.style.clone();
+        for (span, markup) in breaks {
+            match &expected {
+                Some(style) if *style != markup => {
+                    let diagnostic = context
+                        .diagnostic(span, format!("thematic breaks should be `{}`", style))
+                        .with_label(span, format!("this one is `{}`", markup));
+                    context.report(diagnostic);
+                }
+                Some(_) => (),
+                None => expected = Some(markup),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lint::{Linter, RuleLevel},
+        Span,
+    };
+
+    #[test]
+    fn consistent_with_the_first() {
+        let linter = Linter::new().rule(ThematicBreakStyle::default(), RuleLevel::Warn);
+        assert!(linter.check("***\n\ntext\n\n***").is_empty());
+        let diagnostics = linter.check("***\n\ntext\n\n- - -\n\n***");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "thematic breaks should be `***`");
+    }
+
+    #[test]
+    fn configured_style() {
+        let linter = Linter::new().rule(
+            ThematicBreakStyle {
+                style: Some("---".into()),
+            },
+            RuleLevel::Warn,
+        );
+        assert!(linter.check("---").is_empty());
+        assert_eq!(linter.check("***\n\n> ___").len(), 2);
+    }
+
+    #[test]
+    fn nested_breaks_as_written() {
+        let linter = Linter::new().rule(ThematicBreakStyle::default(), RuleLevel::Warn);
+        assert!(linter.check("* * *\n\n> * * *").is_empty());
+        assert!(linter.check("- - -\n\n- a\n\n  - - -").is_empty());
+        let diagnostics = linter.check("* * *\n\n- a\n\n  ***");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].labels[0].message, "this one is `***`");
+        assert_eq!(diagnostics[0].span, Span::new(14, 17));
+    }
+}
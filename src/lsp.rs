@@ -0,0 +1,394 @@
+//! A language server for MDX.
+//!
+//! [`run`] speaks the Language Server Protocol over a
+//! [`Connection`], which is stdin and stdout for `mdx lsp`, or an
+//! in-memory channel for tests. Documents are synced with the edits
+//! made to them, and only the blocks around an edit are parsed again.
+//! Every open document gets:
+//!
+//! - diagnostics for parse errors and lint rules, sent whenever it
+//!   changes
+//! - document symbols, an outline built from its headings
+//! - folding ranges for code blocks, JSX elements and sections
+//! - formatting with `format`
+//! - go to definition, from links to the documents and headings they
+//!   point at, from reference links to their definitions, and from
+//!   JSX components to their imports
+//! - references to headings, from every document in the workspace
+//! - semantic tokens, highlighting markdown and JSX syntax from the
+//!   parse tree
+//!
+//! Each document uses the settings from the `mdx.toml` that applies
+//! to it, which are read again when it's opened or saved.
+use crate::{
+    config::{Config, ConfigError, Settings},
+    format,
+    incremental::{self, Edit},
+    Cst,
+};
+use lsp_server::{Connection, ErrorCode, Message, Notification, ProtocolError, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+        Notification as _, PublishDiagnostics, ShowMessage,
+    },
+    request::{
+        DocumentSymbolRequest, FoldingRangeRequest, Formatting, GotoDefinition, References,
+        Request as _, SemanticTokensFullRequest,
+    },
+    DocumentSymbolResponse, FoldingRangeProviderCapability, GotoDefinitionResponse,
+    InitializeParams, MessageType, OneOf, PublishDiagnosticsParams, SemanticTokensFullOptions,
+    SemanticTokensOptions, SemanticTokensResult, ServerCapabilities, ServerInfo, ShowMessageParams,
+    TextDocumentContentChangeEvent, TextDocumentSyncCapability, TextDocumentSyncKind,
+    TextDocumentSyncOptions, TextDocumentSyncSaveOptions, TextEdit, Url,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::HashMap, path::PathBuf};
+
+pub mod diagnostics;
+pub mod folding;
+pub mod line_index;
+pub mod navigation;
+pub mod semantic_tokens;
+pub mod symbols;
+pub mod workspace;
+
+pub use line_index::LineIndex;
+
+/// What the server can do, as it tells the client when it starts
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::INCREMENTAL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..TextDocumentSyncOptions::default()
+            },
+        )),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(
+            SemanticTokensOptions {
+                legend: semantic_tokens::legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..SemanticTokensOptions::default()
+            }
+            .into(),
+        ),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Answers requests on `connection` until the client shuts the
+/// server down
+pub fn run(connection: &Connection) -> Result<(), ProtocolError> {
+    let initialize = serde_json::json!({
+        "capabilities": capabilities(),
+        "serverInfo": ServerInfo {
+            name: "mdx".to_string(),
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        },
+    });
+    let (id, params) = connection.initialize_start()?;
+    connection.initialize_finish(id, initialize)?;
+
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+        roots: serde_json::from_value(params).map_or_else(|_| vec![], roots),
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                server.request(request);
+            }
+            Message::Notification(notification) => server.notification(notification),
+            Message::Response(_) => (),
+        }
+    }
+    Ok(())
+}
+
+/// An open document
+struct Document {
+    parsed: incremental::Document,
+    version: i32,
+    settings: Settings,
+}
+impl Document {
+    fn text(&self) -> &str {
+        self.parsed.text()
+    }
+
+    /// Applies a change from the client, which replaces a range of
+    /// the text, or all of it when there's no range
+    fn change(&mut self, change: TextDocumentContentChangeEvent) {
+        match change.range {
+            Some(range) => {
+                let span = LineIndex::new(self.text()).span(range);
+                self.parsed.edit(Edit {
+                    span,
+                    text: &change.text,
+                });
+            }
+            None => self.parsed = incremental::Document::parse(change.text, self.settings.parse),
+        }
+    }
+}
+
+/// The directories of the workspace folders the client opened
+#[allow(deprecated)]
+fn roots(params: InitializeParams) -> Vec<PathBuf> {
+    let uris = match (params.workspace_folders, params.root_uri) {
+        (Some(folders), _) => folders.into_iter().map(|folder| folder.uri).collect(),
+        (None, Some(root)) => vec![root],
+        (None, None) => vec![],
+    };
+    uris.iter()
+        .filter_map(|uri| uri.to_file_path().ok())
+        .collect()
+}
+
+struct Server<'c> {
+    connection: &'c Connection,
+    documents: HashMap<Url, Document>,
+    roots: Vec<PathBuf>,
+}
+impl Server<'_> {
+    fn request(&mut self, request: Request) {
+        let response = match request.method.as_str() {
+            DocumentSymbolRequest::METHOD => self.handle(
+                request,
+                |server, params: lsp_types::DocumentSymbolParams| {
+                    server.with_cst(&params.text_document.uri, |cst| {
+                        DocumentSymbolResponse::Nested(symbols::document_symbols(cst))
+                    })
+                },
+            ),
+            FoldingRangeRequest::METHOD => {
+                self.handle(request, |server, params: lsp_types::FoldingRangeParams| {
+                    server.with_cst(&params.text_document.uri, |cst| {
+                        folding::folding_ranges(cst)
+                    })
+                })
+            }
+            Formatting::METHOD => self.handle(
+                request,
+                |server, params: lsp_types::DocumentFormattingParams| {
+                    server.format(&params.text_document.uri)
+                },
+            ),
+            GotoDefinition::METHOD => self.handle(
+                request,
+                |server, params: lsp_types::GotoDefinitionParams| {
+                    let position = params.text_document_position_params;
+                    let uri = &position.text_document.uri;
+                    let offset = server.offset(uri, position.position)?;
+                    navigation::definition(&server.workspace(), uri, offset)
+                        .map(GotoDefinitionResponse::Scalar)
+                },
+            ),
+            References::METHOD => {
+                self.handle(request, |server, params: lsp_types::ReferenceParams| {
+                    let position = params.text_document_position;
+                    let uri = &position.text_document.uri;
+                    let offset = server.offset(uri, position.position)?;
+                    navigation::references(
+                        &server.workspace(),
+                        uri,
+                        offset,
+                        params.context.include_declaration,
+                    )
+                })
+            }
+            SemanticTokensFullRequest::METHOD => self.handle(
+                request,
+                |server, params: lsp_types::SemanticTokensParams| {
+                    let document = server.documents.get(&params.text_document.uri)?;
+                    let tokens = semantic_tokens::semantic_tokens(&document.parsed);
+                    Some(SemanticTokensResult::Tokens(tokens))
+                },
+            ),
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("{} isn't supported", request.method),
+            ),
+        };
+        self.send(response);
+    }
+
+    /// Reads the params of `request`, and answers it with whatever
+    /// `f` makes of them
+    fn handle<P, R, F>(&mut self, request: Request, f: F) -> Response
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+        F: FnOnce(&mut Self, P) -> R,
+    {
+        match serde_json::from_value(request.params) {
+            Ok(params) => Response::new_ok(request.id, f(self, params)),
+            Err(error) => Response::new_err(
+                request.id,
+                ErrorCode::InvalidParams as i32,
+                error.to_string(),
+            ),
+        }
+    }
+
+    fn notification(&mut self, notification: Notification) {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                if let Some(params) = params::<lsp_types::DidOpenTextDocumentParams>(notification) {
+                    let document = params.text_document;
+                    let settings = self.settings(&document.uri);
+                    self.documents.insert(
+                        document.uri.clone(),
+                        Document {
+                            parsed: incremental::Document::parse(document.text, settings.parse),
+                            version: document.version,
+                            settings,
+                        },
+                    );
+                    self.publish_diagnostics(&document.uri);
+                }
+            }
+            DidChangeTextDocument::METHOD => {
+                if let Some(params) = params::<lsp_types::DidChangeTextDocumentParams>(notification)
+                {
+                    let uri = params.text_document.uri;
+                    if let Some(document) = self.documents.get_mut(&uri) {
+                        // each change is made to the text the one before it left
+                        for change in params.content_changes {
+                            document.change(change);
+                        }
+                        document.version = params.text_document.version;
+                    }
+                    self.publish_diagnostics(&uri);
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                if let Some(params) = params::<lsp_types::DidSaveTextDocumentParams>(notification) {
+                    let uri = params.text_document.uri;
+                    let settings = self.settings(&uri);
+                    if let Some(document) = self.documents.get_mut(&uri) {
+                        if settings.parse != document.settings.parse {
+                            let text = document.text().to_string();
+                            document.parsed = incremental::Document::parse(text, settings.parse);
+                        }
+                        document.settings = settings;
+                    }
+                    self.publish_diagnostics(&uri);
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                if let Some(params) = params::<lsp_types::DidCloseTextDocumentParams>(notification)
+                {
+                    let uri = params.text_document.uri;
+                    self.documents.remove(&uri);
+                    // diagnostics for closed documents would stay around
+                    self.notify::<PublishDiagnostics>(PublishDiagnosticsParams::new(
+                        uri,
+                        vec![],
+                        None,
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// The settings from the config that applies to `uri`. A config
+    /// that can't be loaded is shown to the user, and the defaults are
+    /// used instead.
+    fn settings(&self, uri: &Url) -> Settings {
+        let path = match uri.to_file_path() {
+            Ok(path) => path,
+            Err(()) => return Settings::default(),
+        };
+        match Config::discover(&path) {
+            Ok(Some(config)) => config.settings(&path),
+            Ok(None) => Settings::default(),
+            Err(error) => {
+                self.show_config_error(&error);
+                Settings::default()
+            }
+        }
+    }
+
+    fn show_config_error(&self, error: &ConfigError) {
+        self.notify::<ShowMessage>(ShowMessageParams {
+            typ: MessageType::WARNING,
+            message: format!("{}, using the default settings", error),
+        });
+    }
+
+    fn publish_diagnostics(&self, uri: &Url) {
+        if let Some(document) = self.documents.get(uri) {
+            let diagnostics = diagnostics::diagnostics(uri, &document.parsed, &document.settings);
+            self.notify::<PublishDiagnostics>(PublishDiagnosticsParams::new(
+                uri.clone(),
+                diagnostics,
+                Some(document.version),
+            ));
+        }
+    }
+
+    /// Runs `f` on the document at `uri`. Returns `None` if it isn't
+    /// open or doesn't parse.
+    fn with_cst<R>(&self, uri: &Url, f: impl FnOnce(&Cst) -> R) -> Option<R> {
+        let document = self.documents.get(uri)?;
+        let cst = document.parsed.cst().ok()?;
+        Some(f(&cst))
+    }
+
+    /// The open documents, and the files in the workspace folders
+    fn workspace(&self) -> workspace::Workspace<'_> {
+        workspace::Workspace {
+            documents: &self.documents,
+            roots: &self.roots,
+        }
+    }
+
+    /// The byte offset of `position` in the open document at `uri`
+    fn offset(&self, uri: &Url, position: lsp_types::Position) -> Option<usize> {
+        let document = self.documents.get(uri)?;
+        Some(LineIndex::new(document.text()).offset(position))
+    }
+
+    /// One edit that replaces the whole document, if formatting
+    /// changes anything
+    fn format(&self, uri: &Url) -> Option<Vec<TextEdit>> {
+        let document = self.documents.get(uri)?;
+        let text = document.text();
+        let formatted = format(text, document.settings.parse, document.settings.stringify).ok()?;
+        if formatted == text {
+            return Some(vec![]);
+        }
+        let index = LineIndex::new(text);
+        let whole = crate::Span::new(0, text.len());
+        Some(vec![TextEdit::new(index.range(whole), formatted)])
+    }
+
+    fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) {
+        self.send(Notification::new(N::METHOD.to_string(), params));
+    }
+
+    fn send(&self, message: impl Into<Message>) {
+        // the client is gone if this fails, which ends the main loop
+        let _ = self.connection.sender.send(message.into());
+    }
+}
+
+/// The params of a notification, or `None` if they're malformed,
+/// since there's no way to answer a notification with an error
+fn params<P: DeserializeOwned>(notification: Notification) -> Option<P> {
+    serde_json::from_value(notification.params).ok()
+}
@@ -0,0 +1,103 @@
+//! Parse errors and lint diagnostics, as LSP diagnostics.
+use super::line_index::LineIndex;
+use crate::{
+    config::Settings, incremental::Document, parse_recovering_with_options, processor::Severity,
+    MdxError,
+};
+use lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Url,
+};
+
+/// Everything that's wrong with `document`. A document that parses is
+/// linted, and one that doesn't gets every parse error there is, so
+/// one mistake doesn't hide the rest.
+pub fn diagnostics(uri: &Url, document: &Document, settings: &Settings) -> Vec<Diagnostic> {
+    let text = document.text();
+    let errors = match document.cst() {
+        Ok(cst) => settings.linter().lint(&cst),
+        Err(_) => parse_recovering_with_options(text, settings.parse).diagnostics,
+    };
+    let index = LineIndex::new(text);
+    errors
+        .iter()
+        .map(|error| to_diagnostic(uri, &index, error))
+        .collect()
+}
+
+pub fn to_diagnostic(uri: &Url, index: &LineIndex, error: &MdxError) -> Diagnostic {
+    let mut message = error.message.clone();
+    if let Some(help) = &error.help {
+        message.push_str("\nhelp: ");
+        message.push_str(help);
+    }
+    // a label on the error's own span is what the range already shows
+    let related: Vec<_> = error
+        .labels
+        .iter()
+        .filter(|label| label.span != error.span)
+        .map(|label| DiagnosticRelatedInformation {
+            location: Location::new(uri.clone(), index.range(label.span)),
+            message: label.message.clone(),
+        })
+        .collect();
+    Diagnostic {
+        range: index.range(error.span),
+        severity: Some(match error.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Info => DiagnosticSeverity::INFORMATION,
+        }),
+        code: Some(NumberOrString::String(error.code.as_str().to_string())),
+        source: Some("mdx".to_string()),
+        message,
+        related_information: if related.is_empty() {
+            None
+        } else {
+            Some(related)
+        },
+        ..Diagnostic::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::RuleLevel;
+    use lsp_types::Position;
+
+    fn uri() -> Url {
+        Url::parse("file:///docs/a.mdx").unwrap()
+    }
+
+    #[test]
+    fn lint_diagnostics() {
+        let mut settings = Settings::default();
+        settings
+            .lint
+            .insert("heading-increment".to_string(), RuleLevel::Error);
+        let document = Document::parse("# A\n\n### C\n", settings.parse);
+        let diagnostics = diagnostics(&uri(), &document, &settings);
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.range.start, Position::new(2, 0));
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(
+            diagnostic.code,
+            Some(NumberOrString::String("heading-increment".to_string()))
+        );
+    }
+
+    #[test]
+    fn unclosed_fence_warning() {
+        let text = "# One\n\n~~~js\nb\n\n## Two";
+        let document = Document::parse(text, Default::default());
+        let diagnostics = diagnostics(&uri(), &document, &Settings::default());
+        assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+        assert_eq!(diagnostics[0].range.start, Position::new(2, 0));
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("MDX003".to_string()))
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Folding ranges for code blocks, JSX elements and sections.
+use super::{
+    line_index::{trim_end, LineIndex},
+    symbols::sections,
+};
+use crate::{mdx_error::Span, Cst, MdxAst};
+use lsp_types::{FoldingRange, FoldingRangeKind};
+
+pub fn folding_ranges(cst: &Cst) -> Vec<FoldingRange> {
+    let source = cst.source();
+    let index = LineIndex::new(source);
+    let mut ranges = vec![];
+    let mut fold = |span: Span, kind: Option<FoldingRangeKind>| {
+        let span = trim_end(source, span);
+        let (start_line, end_line) = (index.position(span.start).line, index.last_line(span));
+        if end_line > start_line {
+            ranges.push(FoldingRange {
+                start_line,
+                end_line,
+                kind,
+                ..FoldingRange::default()
+            });
+        }
+    };
+    for block in cst.blocks() {
+        match (block.span, block.node()) {
+            (Some(span), MdxAst::Codeblock(_)) | (Some(span), MdxAst::JsxElement(_)) => {
+                fold(span, None)
+            }
+            _ => (),
+        }
+    }
+    for section in sections(cst) {
+        fold(section.span, Some(FoldingRangeKind::Region));
+    }
+    ranges.sort_by_key(|range| (range.start_line, std::cmp::Reverse(range.end_line)));
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        let source = "# Title
+
+```js
+let a = 1;
+```
+
+<Note>
+
+Some text
+
+</Note>
+
+## Empty
+";
+        let ranges = folding_ranges(&Cst::parse(source).unwrap());
+        let lines: Vec<_> = ranges
+            .iter()
+            .map(|range| (range.start_line, range.end_line, range.kind.clone()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (0, 12, Some(FoldingRangeKind::Region)),
+                (2, 4, None),
+                (6, 10, None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Converting between byte offsets, which spans use, and LSP
+//! positions, which are lines and UTF-16 code units. Lines end the
+//! way the parser ends them, at `\n`, `\r\n` or a lone `\r`.
+use crate::{
+    ast::lines::{split_lines, trim_eol},
+    mdx_error::Span,
+};
+use lsp_types::{Position, Range};
+
+pub struct LineIndex<'a> {
+    text: &'a str,
+    /// The byte offset every line starts at
+    line_starts: Vec<usize>,
+}
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        let mut start = 0;
+        for line in split_lines(text) {
+            start += line.len();
+            if trim_eol(line).len() < line.len() {
+                line_starts.push(start);
+            }
+        }
+        LineIndex { text, line_starts }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let before = self.text.get(start..offset).unwrap_or_default();
+        Position::new(line as u32, before.encode_utf16().count() as u32)
+    }
+
+    /// The byte offset of `position`. Positions past the end of a line
+    /// are the end of the line, and positions past the last line are
+    /// the end of the text.
+    pub fn offset(&self, position: Position) -> usize {
+        let start = match self.line_starts.get(position.line as usize) {
+            Some(&start) => start,
+            None => return self.text.len(),
+        };
+        let line = trim_eol(split_lines(&self.text[start..]).next().unwrap_or_default());
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= position.character as usize {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        start + line.len()
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start), self.position(span.end))
+    }
+
+    pub fn span(&self, range: Range) -> Span {
+        Span::new(self.offset(range.start), self.offset(range.end))
+    }
+
+    /// The line `span` ends on, not counting whitespace at its end
+    pub fn last_line(&self, span: Span) -> u32 {
+        self.position(trim_end(self.text, span).end).line
+    }
+}
+
+/// `span` without the whitespace at its end, like the line ending
+/// after a block
+pub fn trim_end(text: &str, span: Span) -> Span {
+    Span::new(
+        span.start,
+        span.start + text[span.start..span.end].trim_end().len(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions() {
+        let text = "# a\r\nb 😀 c\n\nend";
+        let index = LineIndex::new(text);
+        assert_eq!(index.position(0), Position::new(0, 0));
+        assert_eq!(index.position(5), Position::new(1, 0));
+        // the emoji is 4 bytes, but 2 UTF-16 code units
+        assert_eq!(index.position(12), Position::new(1, 5));
+        assert_eq!(index.position(text.len()), Position::new(3, 3));
+        assert_eq!(index.position(100), Position::new(3, 3));
+
+        // a lone `\r` ends a line too, and `\r\n` is one line ending
+        let index = LineIndex::new("a\rb\r\n\rc\r");
+        assert_eq!(index.position(2), Position::new(1, 0));
+        assert_eq!(index.position(5), Position::new(2, 0));
+        assert_eq!(index.position(6), Position::new(3, 0));
+        assert_eq!(index.position(8), Position::new(4, 0));
+    }
+
+    #[test]
+    fn offsets() {
+        let text = "# a\r\nb 😀 c\n\nend";
+        let index = LineIndex::new(text);
+        for offset in [0, 3, 5, 7, 11, 12, 15, 16, text.len()] {
+            assert_eq!(index.offset(index.position(offset)), offset);
+        }
+        assert_eq!(index.offset(Position::new(0, 80)), 3);
+        assert_eq!(index.offset(Position::new(9, 0)), text.len());
+
+        let text = "a\rb\r\n\rc\r";
+        let index = LineIndex::new(text);
+        for offset in [0, 1, 2, 3, 5, 6, 7, text.len()] {
+            assert_eq!(index.offset(index.position(offset)), offset);
+        }
+        assert_eq!(index.offset(Position::new(1, 80)), 3);
+        assert_eq!(index.offset(Position::new(2, 80)), 5);
+    }
+
+    #[test]
+    fn last_line() {
+        let index = LineIndex::new("```\ncode\n```\n\nafter");
+        assert_eq!(index.last_line(Span::new(0, 14)), 2);
+    }
+}
@@ -0,0 +1,325 @@
+//! Go to definition and find references: links go to the documents
+//! and headings they point at, reference links to their definitions,
+//! and JSX components to the imports they come from.
+use super::{
+    line_index::{trim_end, LineIndex},
+    workspace::Workspace,
+};
+use crate::{
+    links::{links, normalize_label, Link},
+    mdx_error::Span,
+    Cst, MdxAst, ParseOptions,
+};
+use lsp_types::{Location, Position, Range, Url};
+
+/// A document, and maybe the id of a heading in it
+type Target = (Url, Option<String>);
+
+/// Where the thing at `offset` in the document at `uri` is defined
+pub fn definition(workspace: &Workspace, uri: &Url, offset: usize) -> Option<Location> {
+    let text = workspace.text(uri)?;
+    let cst = Cst::parse_with_options(&text, workspace.parse_options(uri)).ok()?;
+    if let Some(location) = import(uri, &cst, offset) {
+        return Some(location);
+    }
+    let links = links(&cst);
+    match innermost(&links, offset)? {
+        Link::Reference { label, .. } => {
+            let label = normalize_label(label);
+            let definition = links.iter().find(|link| {
+                matches!(link, Link::Definition { label: defined, .. } if normalize_label(defined) == label)
+            })?;
+            let index = LineIndex::new(&text);
+            Some(Location::new(uri.clone(), index.range(definition.span())))
+        }
+        link => {
+            let (target, anchor) = resolve(uri, link.destination()?.value)?;
+            // a heading that isn't there still finds its document
+            let range = anchor
+                .and_then(|anchor| heading(workspace, &target, &anchor))
+                .or_else(|| start_of(workspace, &target))?;
+            Some(Location::new(target, range))
+        }
+    }
+}
+
+/// Every link in the workspace to the heading at `offset`, or to the
+/// heading that the link at `offset` goes to
+pub fn references(
+    workspace: &Workspace,
+    uri: &Url,
+    offset: usize,
+    include_declaration: bool,
+) -> Option<Vec<Location>> {
+    let text = workspace.text(uri)?;
+    let options = workspace.parse_options(uri);
+    let under_cursor = anchors(&text, options)
+        .into_iter()
+        .find(|(_, span)| contains(*span, offset));
+    let target = match under_cursor {
+        Some((id, _)) => (uri.clone(), Some(id)),
+        None => {
+            let cst = Cst::parse_with_options(&text, options).ok()?;
+            let links = links(&cst);
+            let link = innermost(&links, offset)?;
+            match resolve(uri, link.destination()?.value)? {
+                target @ (_, Some(_)) => target,
+                (_, None) => return None,
+            }
+        }
+    };
+
+    let mut locations = vec![];
+    if include_declaration {
+        if let Some(range) = heading(workspace, &target.0, target.1.as_deref()?) {
+            locations.push(Location::new(target.0.clone(), range));
+        }
+    }
+    for uri in workspace.uris() {
+        let text = match workspace.text(&uri) {
+            Some(text) => text,
+            None => continue,
+        };
+        let cst = match Cst::parse_with_options(&text, workspace.parse_options(&uri)) {
+            Ok(cst) => cst,
+            Err(_) => continue,
+        };
+        let index = LineIndex::new(&text);
+        let links = links(&cst);
+        // reference links go wherever their definition goes
+        let defined: Vec<String> = links
+            .iter()
+            .filter_map(|link| match link {
+                Link::Definition {
+                    label, destination, ..
+                } if resolve(&uri, destination.value).as_ref() == Some(&target) => {
+                    Some(normalize_label(label))
+                }
+                _ => None,
+            })
+            .collect();
+        for link in &links {
+            let found = match link {
+                Link::Reference { label, .. } => defined.contains(&normalize_label(label)),
+                link => link.destination().is_some_and(|destination| {
+                    resolve(&uri, destination.value).as_ref() == Some(&target)
+                }),
+            };
+            if found {
+                locations.push(Location::new(uri.clone(), index.range(link.span())));
+            }
+        }
+    }
+    Some(locations)
+}
+
+/// The document and heading `destination` points at, relative to the
+/// document at `base`. Only local files are documents, so URLs with
+/// other schemes, like `https:` or `mailto:`, are `None`.
+pub fn resolve(base: &Url, destination: &str) -> Option<Target> {
+    let (path, anchor) = match destination.split_once('#') {
+        Some((path, anchor)) => (path, Some(anchor).filter(|anchor| !anchor.is_empty())),
+        None => (destination, None),
+    };
+    let path = path.split('?').next().unwrap_or_default();
+    let mut url = if path.is_empty() {
+        base.clone()
+    } else {
+        base.join(path).ok()?
+    };
+    if url.scheme() != "file" {
+        return None;
+    }
+    url.set_fragment(None);
+    url.set_query(None);
+    // `./guide` is `./guide.mdx` or `./guide.md`, if there's no `./guide`
+    if let Ok(file) = url.to_file_path() {
+        if file.extension().is_none() && !file.exists() {
+            let found = ["mdx", "md"]
+                .iter()
+                .map(|extension| file.with_extension(extension))
+                .find(|file| file.is_file());
+            if let Some(file) = found {
+                url = Url::from_file_path(file).ok()?;
+            }
+        }
+    }
+    Some((url, anchor.map(str::to_string)))
+}
+
+/// The ids of a document's top level headings, and where they are
+pub fn anchors(text: &str, options: ParseOptions) -> Vec<(String, Span)> {
+    let options = ParseOptions {
+        heading_ids: true,
+        ..options
+    };
+    let cst = match Cst::parse_with_options(text, options) {
+        Ok(cst) => cst,
+        Err(_) => return vec![],
+    };
+    cst.blocks()
+        .iter()
+        .filter_map(|block| match (block.span, block.node()) {
+            (Some(span), MdxAst::ATXHeading(heading)) => {
+                Some((heading.id.as_ref()?.to_string(), trim_end(text, span)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The heading with the id `anchor` in the document at `uri`
+fn heading(workspace: &Workspace, uri: &Url, anchor: &str) -> Option<Range> {
+    let text = workspace.text(uri)?;
+    let (_, span) = anchors(&text, workspace.parse_options(uri))
+        .into_iter()
+        .find(|(id, _)| id == anchor)?;
+    Some(LineIndex::new(&text).range(span))
+}
+
+/// The start of the document at `uri`, if there is one
+fn start_of(workspace: &Workspace, uri: &Url) -> Option<Range> {
+    workspace.text(uri)?;
+    Some(Range::new(Position::new(0, 0), Position::new(0, 0)))
+}
+
+/// The smallest link around `offset`, which is the one in the text of
+/// any others
+fn innermost<'l, 'a>(links: &'l [Link<'a>], offset: usize) -> Option<&'l Link<'a>> {
+    links
+        .iter()
+        .filter(|link| contains(link.span(), offset))
+        .min_by_key(|link| link.span().end - link.span().start)
+}
+
+fn contains(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+/// The import that brings in the JSX component named at `offset`,
+/// like `Chart` in `<Chart />`, or `UI` in `</UI.Button>`
+fn import(uri: &Url, cst: &Cst, offset: usize) -> Option<Location> {
+    let source = cst.source();
+    let is_name = |c: char| c.is_alphanumeric() || c == '_' || c == '$' || c == '.';
+    let start = source[..offset].rfind(|c| !is_name(c)).map_or(0, |i| i + 1);
+    let end = source[offset..]
+        .find(|c| !is_name(c))
+        .map_or(source.len(), |i| offset + i);
+    let before = &source[..start];
+    if !before.ends_with('<') {
+        return None;
+    }
+    let binding = source[start..end].split('.').next()?;
+    if !binding.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '$') {
+        return None;
+    }
+    cst.blocks()
+        .iter()
+        .find_map(|block| match (block.span, block.node()) {
+            (Some(span), MdxAst::Esm(esm)) => {
+                let import = esm
+                    .imports()
+                    .into_iter()
+                    .find(|import| import.local == binding)?;
+                let start = span.start + import.offset;
+                let index = LineIndex::new(source);
+                Some(Location::new(
+                    uri.clone(),
+                    index.range(Span::new(start, start + import.local.len())),
+                ))
+            }
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn uri(path: &str) -> Url {
+        Url::from_file_path(std::env::temp_dir().join("mdx-navigation").join(path)).unwrap()
+    }
+
+    #[test]
+    fn resolving() {
+        let base = uri("docs/guide.mdx");
+        assert_eq!(
+            resolve(&base, "../api.mdx#usage"),
+            Some((uri("api.mdx"), Some("usage".to_string())))
+        );
+        assert_eq!(
+            resolve(&base, "#install"),
+            Some((base.clone(), Some("install".to_string())))
+        );
+        assert_eq!(
+            resolve(&base, "other.md?raw"),
+            Some((uri("docs/other.md"), None))
+        );
+        assert_eq!(resolve(&base, "https://mdxjs.com"), None);
+        assert_eq!(resolve(&base, "mailto:someone@example.com"), None);
+    }
+
+    #[test]
+    fn anchors_are_heading_ids() {
+        let anchors = anchors(
+            "# Getting started\n\ntext\n\n## Getting started\n",
+            ParseOptions::default(),
+        );
+        let ids: Vec<_> = anchors.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["getting-started", "getting-started-1"]);
+        assert_eq!(anchors[0].1, Span::new(0, 17));
+    }
+
+    #[test]
+    fn definitions_in_one_document() {
+        let text = "import { Chart as C, Table } from './charts'\n\n# Intro\n\nSee [the table][t] and [intro](#intro).\n\n[t]: #table\n\n<C data={1} />\n";
+        let doc = uri("doc.mdx");
+        let mut documents = HashMap::new();
+        documents.insert(
+            doc.clone(),
+            super::super::Document {
+                parsed: crate::incremental::Document::parse(text, Default::default()),
+                version: 1,
+                settings: Default::default(),
+            },
+        );
+        let workspace = Workspace {
+            documents: &documents,
+            roots: &[],
+        };
+        let at = |needle: &str| text.find(needle).unwrap();
+        let range = |location: Location| location.range;
+
+        // a reference goes to its definition
+        let location = definition(&workspace, &doc, at("[t]") + 1).unwrap();
+        assert_eq!(
+            range(location),
+            Range::new(Position::new(6, 0), Position::new(6, 11))
+        );
+        // an anchor goes to its heading
+        let location = definition(&workspace, &doc, at("(#intro)")).unwrap();
+        assert_eq!(location.uri, doc);
+        assert_eq!(
+            range(location),
+            Range::new(Position::new(2, 0), Position::new(2, 7))
+        );
+        // a component goes to its import
+        let location = definition(&workspace, &doc, at("<C ") + 1).unwrap();
+        assert_eq!(
+            range(location),
+            Range::new(Position::new(0, 18), Position::new(0, 19))
+        );
+        assert!(definition(&workspace, &doc, at("See")).is_none());
+
+        let found = references(&workspace, &doc, at("# Intro"), true).unwrap();
+        let ranges: Vec<_> = found.into_iter().map(range).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                Range::new(Position::new(2, 0), Position::new(2, 7)),
+                Range::new(Position::new(4, 23), Position::new(4, 38)),
+            ]
+        );
+    }
+}
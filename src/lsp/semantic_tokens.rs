@@ -0,0 +1,155 @@
+//! Highlighting from the parse tree, which editors layer over their
+//! grammar for the parts of MDX that grammars get wrong.
+use super::LineIndex;
+use crate::{incremental::Document, semantic_tokens::TokenKind, Cst};
+use lsp_types::{Position, SemanticToken, SemanticTokenType, SemanticTokens, SemanticTokensLegend};
+
+/// The type a kind of token has in the legend. Markdown's own
+/// syntax mostly gets the closest standard type, so that themes
+/// color it, but emphasis and strong text have none.
+fn token_type(kind: TokenKind) -> SemanticTokenType {
+    match kind {
+        TokenKind::HeadingMarker => SemanticTokenType::KEYWORD,
+        TokenKind::Emphasis => SemanticTokenType::new("emphasis"),
+        TokenKind::Strong => SemanticTokenType::new("strong"),
+        TokenKind::CodeFence => SemanticTokenType::STRING,
+        TokenKind::InfoString => SemanticTokenType::DECORATOR,
+        TokenKind::JsxTagName => SemanticTokenType::CLASS,
+        TokenKind::JsxAttributeName => SemanticTokenType::PROPERTY,
+        TokenKind::ExpressionBrace => SemanticTokenType::OPERATOR,
+    }
+}
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TokenKind::ALL
+            .iter()
+            .map(|&kind| token_type(kind))
+            .collect(),
+        token_modifiers: vec![],
+    }
+}
+
+/// The tokens in `document`, from the blocks it keeps up to date as
+/// it's edited. Only a document that doesn't parse is parsed again,
+/// recovering what it can. LSP tokens can't overlap, so a nested
+/// token cuts the one around it in two, and they can't span lines
+/// either, so those are split up too.
+pub fn semantic_tokens(document: &Document) -> SemanticTokens {
+    let text = document.text();
+    let cst = document
+        .cst()
+        .unwrap_or_else(|_| Cst::parse_recovering(text, document.options()));
+    // the innermost token every byte is in
+    let mut kinds = vec![None; text.len()];
+    for token in crate::semantic_tokens(&cst) {
+        kinds[token.span.start..token.span.end].fill(Some(token.kind));
+    }
+
+    let index = LineIndex::new(text);
+    let bytes = text.as_bytes();
+    let mut data = vec![];
+    let mut previous = Position::new(0, 0);
+    let mut start = 0;
+    while start < text.len() {
+        let kind = match kinds[start] {
+            Some(kind) if !matches!(bytes[start], b'\r' | b'\n') => kind,
+            _ => {
+                start += 1;
+                continue;
+            }
+        };
+        let mut end = start;
+        while end < text.len() && kinds[end] == Some(kind) && !matches!(bytes[end], b'\r' | b'\n') {
+            end += 1;
+        }
+        let (from, to) = (index.position(start), index.position(end));
+        data.push(SemanticToken {
+            delta_line: from.line - previous.line,
+            delta_start: match from.line == previous.line {
+                true => from.character - previous.character,
+                false => from.character,
+            },
+            length: to.character - from.character,
+            token_type: TokenKind::ALL.iter().position(|&k| k == kind).unwrap_or(0) as u32,
+            token_modifiers_bitset: 0,
+        });
+        previous = from;
+        start = end;
+    }
+    SemanticTokens {
+        result_id: None,
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{incremental::Edit, mdx_error::Span, ParseOptions};
+
+    /// Tokens as absolute (line, character, length, type)
+    fn absolute(tokens: &SemanticTokens) -> Vec<(u32, u32, u32, TokenKind)> {
+        let mut position = Position::new(0, 0);
+        tokens
+            .data
+            .iter()
+            .map(|token| {
+                if token.delta_line > 0 {
+                    position = Position::new(position.line + token.delta_line, 0);
+                }
+                position.character += token.delta_start;
+                let kind = TokenKind::ALL[token.token_type as usize];
+                (position.line, position.character, token.length, kind)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_and_multiline_tokens() {
+        let text = "# **a *b* c**\n\nsome *long\nline* 😀 <X\n";
+        let tokens = semantic_tokens(&Document::parse(text, ParseOptions::default()));
+        assert_eq!(
+            absolute(&tokens),
+            vec![
+                (0, 0, 1, TokenKind::HeadingMarker),
+                (0, 2, 4, TokenKind::Strong),
+                (0, 6, 3, TokenKind::Emphasis),
+                (0, 9, 4, TokenKind::Strong),
+                (2, 5, 5, TokenKind::Emphasis),
+                (3, 0, 5, TokenKind::Emphasis),
+            ]
+        );
+        assert_eq!(legend().token_types.len(), TokenKind::ALL.len());
+    }
+
+    #[test]
+    fn tokens_after_edits() {
+        let mut document = Document::parse("# a\n\nsome *b*\n", ParseOptions::default());
+        document.edit(Edit {
+            span: Span::new(10, 13),
+            text: "**b**",
+        });
+        assert_eq!(
+            absolute(&semantic_tokens(&document)),
+            vec![
+                (0, 0, 1, TokenKind::HeadingMarker),
+                (2, 5, 5, TokenKind::Strong),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_fences_have_tokens() {
+        let text = "## Title\n\n```js\nunclosed\n";
+        let tokens = semantic_tokens(&Document::parse(text, ParseOptions::default()));
+        assert_eq!(
+            absolute(&tokens),
+            vec![
+                (0, 0, 2, TokenKind::HeadingMarker),
+                (2, 0, 3, TokenKind::CodeFence),
+                (2, 3, 2, TokenKind::InfoString),
+            ]
+        );
+    }
+}
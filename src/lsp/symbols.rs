@@ -0,0 +1,128 @@
+//! The outline of a document, from its headings.
+use super::line_index::{trim_end, LineIndex};
+use crate::{ast::ATXHeading, mdx_error::Span, Cst, MdxAst};
+use lsp_types::{DocumentSymbol, SymbolKind};
+
+/// A heading, and everything up to the next heading at the same
+/// level or above
+pub struct Section<'c, 'a> {
+    pub heading: &'c ATXHeading<'a>,
+    pub heading_span: Span,
+    /// From the start of the heading to the end of the last block in
+    /// the section
+    pub span: Span,
+}
+
+/// Every top level heading's section, in document order
+pub fn sections<'c, 'a>(cst: &'c Cst<'a>) -> Vec<Section<'c, 'a>> {
+    let blocks: Vec<_> = cst
+        .blocks()
+        .iter()
+        .filter_map(|block| Some((trim_end(cst.source(), block.span?), block.node())))
+        .collect();
+    let mut sections = vec![];
+    for (i, (heading_span, node)) in blocks.iter().enumerate() {
+        let heading = match node {
+            MdxAst::ATXHeading(heading) => heading,
+            _ => continue,
+        };
+        let rest = &blocks[i + 1..];
+        let last = rest
+            .iter()
+            .position(
+                |(_, node)| matches!(node, MdxAst::ATXHeading(next) if next.level <= heading.level),
+            )
+            .unwrap_or(rest.len());
+        let end = match last {
+            0 => heading_span.end,
+            last => rest[last - 1].0.end,
+        };
+        sections.push(Section {
+            heading,
+            heading_span: *heading_span,
+            span: Span::new(heading_span.start, end),
+        });
+    }
+    sections
+}
+
+/// Headings as symbols, with deeper headings nested in the ones
+/// above them
+pub fn document_symbols(cst: &Cst) -> Vec<DocumentSymbol> {
+    let index = LineIndex::new(cst.source());
+    let mut roots = vec![];
+    // the headings that new headings can still be nested in
+    let mut open: Vec<(u8, DocumentSymbol)> = vec![];
+    for section in sections(cst) {
+        let level = section.heading.level;
+        while open.last().is_some_and(|(open, _)| *open >= level) {
+            close(&mut open, &mut roots);
+        }
+        let name = section.heading.value.trim();
+        #[allow(deprecated)]
+        let symbol = DocumentSymbol {
+            name: if name.is_empty() {
+                "#".repeat(level as usize)
+            } else {
+                name.to_string()
+            },
+            detail: None,
+            kind: SymbolKind::STRING,
+            tags: None,
+            deprecated: None,
+            range: index.range(section.span),
+            selection_range: index.range(section.heading_span),
+            children: Some(vec![]),
+        };
+        open.push((level, symbol));
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut roots);
+    }
+    roots
+}
+
+/// Moves the innermost open heading into the one around it
+fn close(open: &mut Vec<(u8, DocumentSymbol)>, roots: &mut Vec<DocumentSymbol>) {
+    if let Some((_, symbol)) = open.pop() {
+        match open.last_mut() {
+            Some((_, parent)) => parent.children.get_or_insert_with(Vec::new).push(symbol),
+            None => roots.push(symbol),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{Position, Range};
+
+    #[test]
+    fn nested_headings() {
+        let source = "Intro\n\n# One\n\ntext\n\n### Deep\n\n## Two\n\nmore\n\n# Three\n";
+        let cst = Cst::parse(source).unwrap();
+        let symbols = document_symbols(&cst);
+        let names = |symbols: &[DocumentSymbol]| -> Vec<String> {
+            symbols.iter().map(|symbol| symbol.name.clone()).collect()
+        };
+        assert_eq!(names(&symbols), vec!["One", "Three"]);
+        let one = &symbols[0];
+        assert_eq!(names(one.children.as_ref().unwrap()), vec!["Deep", "Two"]);
+        assert_eq!(
+            one.range,
+            Range::new(Position::new(2, 0), Position::new(10, 4))
+        );
+        assert_eq!(
+            one.selection_range,
+            Range::new(Position::new(2, 0), Position::new(2, 5))
+        );
+        let deep = &one.children.as_ref().unwrap()[0];
+        assert_eq!(deep.range.end, Position::new(6, 8));
+    }
+
+    #[test]
+    fn empty_headings_have_names() {
+        let cst = Cst::parse("##\n").unwrap();
+        assert_eq!(document_symbols(&cst)[0].name, "##");
+    }
+}
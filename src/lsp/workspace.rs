@@ -0,0 +1,83 @@
+//! The documents the server can look at: the open ones, as they are
+//! in the editor, and the rest of the workspace as it is on disk.
+use super::Document;
+use crate::{config::Config, ParseOptions};
+use lsp_types::Url;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub struct Workspace<'w> {
+    pub(super) documents: &'w HashMap<Url, Document>,
+    /// The workspace folders the client opened
+    pub(super) roots: &'w [PathBuf],
+}
+impl<'w> Workspace<'w> {
+    /// The text of an open document, or of a file on disk
+    pub fn text(&self, uri: &Url) -> Option<Cow<'w, str>> {
+        match self.documents.get(uri) {
+            Some(document) => Some(Cow::Borrowed(document.text())),
+            None => fs::read_to_string(uri.to_file_path().ok()?)
+                .ok()
+                .map(Cow::Owned),
+        }
+    }
+
+    /// How the document at `uri` is parsed, from its config
+    pub fn parse_options(&self, uri: &Url) -> ParseOptions {
+        if let Some(document) = self.documents.get(uri) {
+            return document.settings.parse;
+        }
+        let path = match uri.to_file_path() {
+            Ok(path) => path,
+            Err(()) => return ParseOptions::default(),
+        };
+        match Config::discover(&path) {
+            Ok(Some(config)) => config.settings(&path).parse,
+            _ => ParseOptions::default(),
+        }
+    }
+
+    /// Every open document, and every `.md` and `.mdx` file in the
+    /// workspace folders, skipping hidden directories and
+    /// `node_modules`
+    pub fn uris(&self) -> Vec<Url> {
+        let mut files = vec![];
+        for root in self.roots {
+            markdown_files(root, &mut files);
+        }
+        let mut uris: Vec<Url> = files
+            .into_iter()
+            .filter_map(|path| Url::from_file_path(path).ok())
+            .chain(self.documents.keys().cloned())
+            .collect();
+        uris.sort();
+        uris.dedup();
+        uris
+    }
+}
+
+fn markdown_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "node_modules" {
+                markdown_files(&path, files);
+            }
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "md" || extension == "mdx")
+        {
+            files.push(path);
+        }
+    }
+}
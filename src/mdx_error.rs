@@ -0,0 +1,531 @@
+//! Errors that point at the source they came from.
+//!
+//! An [`MdxError`] has a code, a primary span, any number of
+//! labelled spans and an optional help message. It can be rendered
+//! as an annotated snippet of the source for a terminal, or as
+//! JSON for editors and other tools.
+use crate::processor::Severity;
+use nom::error::ErrorKind;
+use nom_supreme::error::{BaseErrorKind, ErrorTree, Expectation};
+use owo_colors::OwoColorize;
+use serde_json::json;
+use std::fmt;
+
+/// A stable code for every kind of error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// Something the parser didn't expect, that no other code covers
+    Syntax,
+    /// A block ended, but there's more on its line
+    UnexpectedContent,
+    /// The content of a block quote or list item couldn't be parsed
+    InvalidContainerContent,
+    /// A code fence that's never closed
+    UnclosedFence,
+    /// A lint rule, by name, like `heading-increment`
+    Lint(&'static str),
+}
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Syntax => "MDX000",
+            ErrorCode::UnexpectedContent => "MDX001",
+            ErrorCode::InvalidContainerContent => "MDX002",
+            ErrorCode::UnclosedFence => "MDX003",
+            ErrorCode::Lint(rule) => rule,
+        }
+    }
+}
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A range of bytes in the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// The 1-based line and column (in chars) of the start of the
+    /// span
+    pub fn start_position(&self, source: &str) -> (usize, usize) {
+        position(source, self.start)
+    }
+
+    /// The 1-based line and column of the end of the span
+    pub fn end_position(&self, source: &str) -> (usize, usize) {
+        position(source, self.end)
+    }
+}
+
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MdxError {
+    pub severity: Severity,
+    pub code: ErrorCode,
+    pub message: String,
+    /// Where the error is. A label with the same span is drawn as
+    /// the primary annotation.
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub help: Option<String>,
+}
+impl MdxError {
+    pub fn new(code: ErrorCode, message: impl Into<String>, span: Span) -> Self {
+        MdxError {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            span,
+            labels: vec![],
+            help: None,
+        }
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Renders the error as an annotated snippet of `source`, which
+    /// has to be the source the error came from
+    pub fn snippet<'a>(&'a self, source: &'a str) -> Snippet<'a> {
+        Snippet {
+            error: self,
+            source,
+            path: None,
+            color: false,
+        }
+    }
+
+    /// The error as JSON, with line and column numbers resolved
+    /// against `source`
+    pub fn to_json(&self, source: &str) -> serde_json::Value {
+        let span = |span: &Span| {
+            let (line, column) = span.start_position(source);
+            let (end_line, end_column) = span.end_position(source);
+            json!({
+                "start": span.start,
+                "end": span.end,
+                "line": line,
+                "column": column,
+                "endLine": end_line,
+                "endColumn": end_column,
+            })
+        };
+        json!({
+            "severity": severity_name(self.severity),
+            "code": self.code.as_str(),
+            "message": self.message,
+            "span": span(&self.span),
+            "labels": self
+                .labels
+                .iter()
+                .map(|label| json!({ "message": label.message, "span": span(&label.span) }))
+                .collect::<Vec<_>>(),
+            "help": self.help,
+        })
+    }
+
+    /// Moves every span `offset` bytes further into the source
+    pub(crate) fn shift(mut self, offset: usize) -> Self {
+        let shift = |span: Span| Span::new(span.start + offset, span.end + offset);
+        self.span = shift(self.span);
+        for label in &mut self.labels {
+            label.span = shift(label.span);
+        }
+        self
+    }
+
+    /// Turns a parser error into an `MdxError`, pointing at the
+    /// furthest point the parser got to in `input`.
+    ///
+    /// Parsers can also fail with an `MdxError` of their own, as an
+    /// external error. Its spans are relative to where it was raised.
+    pub(crate) fn from_error_tree(input: &str, error: ErrorTree<&str>) -> Self {
+        let (location, kind) = furthest(&error);
+        let offset = input.len() - location.len();
+        if let BaseErrorKind::External(external) = kind {
+            if let Some(error) = external.downcast_ref::<MdxError>() {
+                return error.clone().shift(offset);
+            }
+        }
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = offset + location.find('\n').unwrap_or(location.len());
+        match kind {
+            BaseErrorKind::Expected(Expectation::Eof) | BaseErrorKind::Kind(ErrorKind::Eof) => {
+                let line = &input[line_start..offset];
+                let block_start = line_start + line.len() - line.trim_start().len();
+                let error = MdxError::new(
+                    ErrorCode::UnexpectedContent,
+                    "unexpected content after the end of a block",
+                    Span::new(offset, line_end),
+                )
+                .with_label(Span::new(offset, line_end), "expected a line ending here")
+                .with_help("start this content on a new line, or escape the characters before it");
+                if block_start < offset {
+                    error.with_label(Span::new(block_start, offset), "the block ends here")
+                } else {
+                    error
+                }
+            }
+            BaseErrorKind::Kind(ErrorKind::Many1) => MdxError::new(
+                ErrorCode::InvalidContainerContent,
+                "couldn't parse the content of this block",
+                Span::new(offset, line_end),
+            )
+            .with_label(Span::new(offset, line_end), "in the block starting here"),
+            kind => {
+                let label = match kind {
+                    BaseErrorKind::Expected(expectation) => format!("expected {} here", expectation),
+                    _ => "no block could be parsed from here".to_string(),
+                };
+                MdxError::new(
+                    ErrorCode::Syntax,
+                    "couldn't parse this line",
+                    Span::new(offset, line_end),
+                )
+                .with_label(Span::new(offset, line_end), label)
+                .with_help(
+                    "check the markup that starts here, or escape its first character with `\\` \
+                     to write it as text",
+                )
+            }
+        }
+    }
+}
+impl fmt::Display for MdxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {}",
+            severity_name(self.severity),
+            self.code,
+            self.message
+        )
+    }
+}
+impl std::error::Error for MdxError {}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "info",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+/// The base error that got the furthest into the input, which is
+/// the most useful one to report out of all the alternatives
+fn furthest<'e, 'a>(error: &'e ErrorTree<&'a str>) -> (&'a str, &'e BaseErrorKind) {
+    match error {
+        ErrorTree::Base { location, kind } => (location, kind),
+        ErrorTree::Stack { base, .. } => furthest(base),
+        ErrorTree::Alt(alternatives) => alternatives
+            .iter()
+            .map(furthest)
+            .min_by_key(|(location, _)| location.len())
+            .expect("alternatives are never empty"),
+    }
+}
+
+/// An [`MdxError`] rendered against its source, see
+/// [`MdxError::snippet`]
+///
+/// ```text
+/// error[MDX001]: unexpected content after the end of a block
+///  --> README.mdx:3:4
+///   |
+/// 3 | ***bold***
+///   |    ^^^^^^^ expected a line ending here
+///   | --- the block ends here
+///   |
+///   = help: start this content on a new line, or escape the characters before it
+/// ```
+pub struct Snippet<'a> {
+    error: &'a MdxError,
+    source: &'a str,
+    path: Option<&'a str>,
+    color: bool,
+}
+impl<'a> Snippet<'a> {
+    /// The path shown next to the line and column
+    pub fn with_path(mut self, path: &'a str) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Colors for a terminal
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn paint(&self, text: &str, paint: fn(&str) -> String) -> String {
+        if self.color {
+            paint(text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+struct Annotation<'a> {
+    line: usize,
+    column: usize,
+    width: usize,
+    message: &'a str,
+    primary: bool,
+}
+
+impl<'a> fmt::Display for Snippet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let error = self.error;
+        let source = self.source;
+        let lines: Vec<&str> = source.split('\n').collect();
+
+        let mut annotations: Vec<Annotation> = error
+            .labels
+            .iter()
+            .map(|label| (label.span, label.message.as_str()))
+            .chain(
+                Some((error.span, ""))
+                    .filter(|_| !error.labels.iter().any(|label| label.span == error.span)),
+            )
+            .map(|(span, message)| {
+                let (line, column) = span.start_position(source);
+                let text = lines.get(line - 1).copied().unwrap_or("");
+                let width = source[span.start.min(source.len())..span.end.min(source.len())]
+                    .split('\n')
+                    .next()
+                    .unwrap_or("")
+                    .chars()
+                    .count()
+                    .min(text.chars().count().saturating_sub(column - 1));
+                Annotation {
+                    line,
+                    column,
+                    width: width.max(1),
+                    message,
+                    primary: span == error.span,
+                }
+            })
+            .collect();
+        annotations.sort_by_key(|annotation| (annotation.line, !annotation.primary));
+
+        let (line, column) = error.span.start_position(source);
+        let gutter = annotations
+            .iter()
+            .map(|annotation| annotation.line)
+            .max()
+            .unwrap_or(line)
+            .to_string()
+            .len();
+        let bar = self.paint("|", |s| s.blue().bold().to_string());
+        let blank = " ".repeat(gutter);
+
+        let header = format!("{}[{}]", severity_name(error.severity), error.code);
+        let header = match error.severity {
+            Severity::Error => self.paint(&header, |s| s.red().bold().to_string()),
+            Severity::Warning => self.paint(&header, |s| s.yellow().bold().to_string()),
+            Severity::Info => self.paint(&header, |s| s.cyan().bold().to_string()),
+        };
+        writeln!(
+            f,
+            "{}: {}",
+            header,
+            self.paint(&error.message, |s| s.bold().to_string())
+        )?;
+        let location = match self.path {
+            Some(path) => format!("{}:{}:{}", path, line, column),
+            None => format!("{}:{}", line, column),
+        };
+        writeln!(
+            f,
+            "{}{} {}",
+            blank,
+            self.paint("-->", |s| s.blue().bold().to_string()),
+            location
+        )?;
+        writeln!(f, "{} {}", blank, bar)?;
+
+        let mut previous: Option<usize> = None;
+        for annotation in &annotations {
+            if previous != Some(annotation.line) {
+                if previous.is_some_and(|previous| annotation.line > previous + 1) {
+                    writeln!(f, "{}", self.paint("...", |s| s.blue().bold().to_string()))?;
+                }
+                let number = format!("{:>width$}", annotation.line, width = gutter);
+                writeln!(
+                    f,
+                    "{} {} {}",
+                    self.paint(&number, |s| s.blue().bold().to_string()),
+                    bar,
+                    lines.get(annotation.line - 1).copied().unwrap_or("")
+                )?;
+                previous = Some(annotation.line);
+            }
+            let marker = if annotation.primary { "^" } else { "-" };
+            let underline = format!("{} {}", marker.repeat(annotation.width), annotation.message);
+            let underline = if annotation.primary {
+                self.paint(underline.trim_end(), |s| s.red().bold().to_string())
+            } else {
+                self.paint(underline.trim_end(), |s| s.blue().bold().to_string())
+            };
+            writeln!(
+                f,
+                "{} {} {}{}",
+                blank,
+                bar,
+                " ".repeat(annotation.column - 1),
+                underline
+            )?;
+        }
+
+        if let Some(help) = &error.help {
+            writeln!(f, "{} {}", blank, bar)?;
+            write!(
+                f,
+                "{} {} {}",
+                blank,
+                self.paint("=", |s| s.blue().bold().to_string()),
+                self.paint("help:", |s| s.bold().to_string())
+            )?;
+            write!(f, " {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::ParseError;
+
+    #[test]
+    fn span_positions() {
+        let source = "# é\n\nabc";
+        assert_eq!(Span::new(0, 1).start_position(source), (1, 1));
+        assert_eq!(Span::new(7, 8).start_position(source), (3, 2));
+        assert_eq!(Span::new(2, 4).end_position(source), (1, 4));
+    }
+
+    /// What the parser reports when a block leaves text after it on
+    /// its line, here after the first three stars
+    fn unexpected_content(source: &str) -> MdxError {
+        MdxError::from_error_tree(
+            source,
+            ErrorTree::from_error_kind(&source[12..], ErrorKind::Eof),
+        )
+    }
+
+    #[test]
+    fn parse_error_unexpected_content() {
+        let error = unexpected_content("# Title\n\n***bold***");
+        assert_eq!(error.code, ErrorCode::UnexpectedContent);
+        assert_eq!(error.span, Span::new(12, 19));
+        assert_eq!(
+            error.labels[1],
+            Label {
+                span: Span::new(9, 12),
+                message: "the block ends here".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_error_syntax() {
+        let source = "one\n<two";
+        let error = MdxError::from_error_tree(
+            source,
+            ErrorTree::from_error_kind(&source[4..], ErrorKind::Alt),
+        );
+        assert_eq!(error.code, ErrorCode::Syntax);
+        assert_eq!(error.message, "couldn't parse this line");
+        assert_eq!(error.span, Span::new(4, 8));
+        assert_eq!(error.labels[0].message, "no block could be parsed from here");
+        assert!(error.help.is_some());
+    }
+
+    #[test]
+    fn render_snippet() {
+        let source = "# Title\n\n***bold***";
+        let error = unexpected_content(source);
+        assert_eq!(
+            error.snippet(source).with_path("README.mdx").to_string(),
+            "error[MDX001]: unexpected content after the end of a block
+ --> README.mdx:3:4
+  |
+3 | ***bold***
+  |    ^^^^^^^ expected a line ending here
+  | --- the block ends here
+  |
+  = help: start this content on a new line, or escape the characters before it"
+        );
+    }
+
+    #[test]
+    fn render_snippet_without_labels() {
+        let source = "one\ntwo\nthree";
+        let error = MdxError::new(ErrorCode::Syntax, "oops", Span::new(4, 7))
+            .with_label(Span::new(0, 1), "see here")
+            .with_severity(Severity::Warning);
+        assert_eq!(
+            error.snippet(source).to_string(),
+            "warning[MDX000]: oops
+ --> 2:1
+  |
+1 | one
+  | - see here
+2 | two
+  | ^^^
+"
+        );
+    }
+
+    #[test]
+    fn render_json() {
+        let source = "# Title\n\n***bold***";
+        let error = unexpected_content(source);
+        let json = error.to_json(source);
+        assert_eq!(json["code"], "MDX001");
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["span"]["line"], 3);
+        assert_eq!(json["span"]["column"], 4);
+        assert_eq!(json["labels"][1]["span"]["endColumn"], 4);
+    }
+}
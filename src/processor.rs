@@ -0,0 +1,308 @@
+//! A pipeline in the style of unified: parse a file, run it
+//! through a list of plugins that transform the AST, and compile
+//! the result. Everything that happens along the way is reported
+//! as a message on the file.
+use crate::{parse_with_options, stringify, Mdx, MdxError, ParseOptions};
+use std::{collections::HashMap, fmt, path::PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    /// Fatal, nothing else runs after an error is reported
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub severity: Severity,
+    pub reason: String,
+    /// What reported the message, usually a plugin name
+    pub source: String,
+}
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {} ({})", severity, self.reason, self.source)
+    }
+}
+
+/// A file moving through a `Processor`.
+///
+/// `value` is the source to start with, and the compiled output
+/// once processing is done.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VFile {
+    pub path: Option<PathBuf>,
+    pub value: String,
+    pub messages: Vec<Message>,
+    /// Anything plugins want to share with each other
+    pub data: HashMap<String, String>,
+}
+impl VFile {
+    pub fn new(value: impl Into<String>) -> Self {
+        VFile {
+            value: value.into(),
+            ..VFile::default()
+        }
+    }
+
+    pub fn with_path(path: impl Into<PathBuf>, value: impl Into<String>) -> Self {
+        VFile {
+            path: Some(path.into()),
+            ..VFile::new(value)
+        }
+    }
+
+    pub fn info(&mut self, reason: impl Into<String>, source: impl Into<String>) {
+        self.push(Severity::Info, reason.into(), source.into());
+    }
+
+    pub fn warn(&mut self, reason: impl Into<String>, source: impl Into<String>) {
+        self.push(Severity::Warning, reason.into(), source.into());
+    }
+
+    /// Reports an error, which stops the processor after the
+    /// current plugin is done
+    pub fn fail(&mut self, reason: impl Into<String>, source: impl Into<String>) {
+        self.push(Severity::Error, reason.into(), source.into());
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.messages
+            .iter()
+            .any(|message| message.severity == Severity::Error)
+    }
+
+    fn push(&mut self, severity: Severity, reason: String, source: String) {
+        self.messages.push(Message {
+            severity,
+            reason,
+            source,
+        });
+    }
+}
+
+/// A transform that runs on the AST between parsing and compiling.
+///
+/// Plugins are configured through their own fields, so options
+/// are passed in when the plugin is constructed. Closures taking
+/// `(&mut Mdx, &mut VFile)` are plugins too.
+pub trait Plugin {
+    /// Used as the source of messages the processor reports for
+    /// this plugin
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    fn transform(&mut self, mdx: &mut Mdx<'_>, file: &mut VFile);
+}
+impl<F> Plugin for F
+where
+    F: for<'a> FnMut(&mut Mdx<'a>, &mut VFile),
+{
+    fn transform(&mut self, mdx: &mut Mdx<'_>, file: &mut VFile) {
+        self(mdx, file)
+    }
+}
+
+type Parser = Box<dyn for<'a> Fn(&'a str) -> Result<Mdx<'a>, MdxError>>;
+type Compiler = Box<dyn for<'a> FnMut(Mdx<'a>, &mut VFile) -> String>;
+
+#[derive(Debug)]
+pub enum ProcessError {
+    /// The file couldn't be parsed, the file's value is unchanged
+    Parse(MdxError),
+    /// A plugin reported an error, see the file's messages
+    Plugin(String),
+}
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProcessError::Parse(error) => write!(f, "failed to parse: {}", error),
+            ProcessError::Plugin(name) => write!(f, "plugin {} failed", name),
+        }
+    }
+}
+impl std::error::Error for ProcessError {}
+
+/// Parses, transforms and compiles files.
+///
+/// ```
+/// use mdx::{processor::{Processor, VFile}, Mdx};
+///
+/// let mut processor = Processor::new().use_plugin(|mdx: &mut Mdx, file: &mut VFile| {
+///     if mdx.ast.is_empty() {
+///         file.warn("empty document", "no-empty");
+///     }
+/// });
+/// let mut file = VFile::new("# hello");
+/// processor.process(&mut file).unwrap();
+/// assert_eq!(file.value, "# hello");
+/// ```
+pub struct Processor {
+    parser: Parser,
+    plugins: Vec<Box<dyn Plugin>>,
+    compiler: Compiler,
+}
+impl Default for Processor {
+    fn default() -> Self {
+        Processor::new()
+    }
+}
+impl Processor {
+    /// Parses with the default `ParseOptions` and compiles
+    /// with `stringify`
+    pub fn new() -> Self {
+        Processor {
+            parser: Box::new(|input| parse_with_options(input, ParseOptions::default())),
+            plugins: vec![],
+            compiler: Box::new(|mdx, _| stringify(mdx)),
+        }
+    }
+
+    pub fn parse_options(self, options: ParseOptions) -> Self {
+        self.parser(move |input| parse_with_options(input, options))
+    }
+
+    pub fn parser<P>(mut self, parser: P) -> Self
+    where
+        P: for<'a> Fn(&'a str) -> Result<Mdx<'a>, MdxError> + 'static,
+    {
+        self.parser = Box::new(parser);
+        self
+    }
+
+    /// Adds a plugin. Plugins run in the order they were added.
+    pub fn use_plugin<P: Plugin + 'static>(mut self, plugin: P) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    pub fn compiler<C>(mut self, compiler: C) -> Self
+    where
+        C: for<'a> FnMut(Mdx<'a>, &mut VFile) -> String + 'static,
+    {
+        self.compiler = Box::new(compiler);
+        self
+    }
+
+    /// Runs the parser and then every plugin, without compiling
+    pub fn run<'a>(&mut self, input: &'a str, file: &mut VFile) -> Result<Mdx<'a>, ProcessError> {
+        let mut mdx = match (self.parser)(input) {
+            Ok(mdx) => mdx,
+            Err(error) => {
+                file.fail(error.to_string(), "parse");
+                return Err(ProcessError::Parse(error));
+            }
+        };
+        for plugin in self.plugins.iter_mut() {
+            plugin.transform(&mut mdx, file);
+            if file.has_errors() {
+                return Err(ProcessError::Plugin(plugin.name().to_string()));
+            }
+        }
+        Ok(mdx)
+    }
+
+    /// Parses, transforms and compiles the file. On success the
+    /// file's value is replaced with the compiled output.
+    pub fn process(&mut self, file: &mut VFile) -> Result<(), ProcessError> {
+        let input = std::mem::take(&mut file.value);
+        match self.run(&input, file) {
+            Ok(mdx) => {
+                file.value = (self.compiler)(mdx, file);
+                Ok(())
+            }
+            Err(error) => {
+                file.value = input;
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MdxAst;
+
+    struct MaxHeadings {
+        max: usize,
+    }
+    impl Plugin for MaxHeadings {
+        fn name(&self) -> &str {
+            "max-headings"
+        }
+        fn transform(&mut self, mdx: &mut Mdx<'_>, file: &mut VFile) {
+            let count = mdx
+                .ast
+                .iter()
+                .filter(|node| matches!(node, MdxAst::ATXHeading(_)))
+                .count();
+            if count > self.max {
+                file.fail(
+                    format!("{} headings, at most {} allowed", count, self.max),
+                    self.name(),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn process_runs_plugins_in_order() {
+        let mut processor = Processor::new()
+            .use_plugin(|mdx: &mut Mdx, file: &mut VFile| {
+                mdx.ast
+                    .retain(|node| !matches!(node, MdxAst::ThematicBreak(_)));
+                file.data.insert("breaks".into(), "removed".into());
+            })
+            .use_plugin(|_: &mut Mdx, file: &mut VFile| {
+                let breaks = file.data["breaks"].clone();
+                file.info(breaks, "second");
+            });
+        let mut file = VFile::with_path("doc.mdx", "# a\n\n---\n\n# b");
+        processor.process(&mut file).unwrap();
+        assert_eq!(file.value, "# a\n\n# b");
+        assert_eq!(
+            file.messages,
+            vec![Message {
+                severity: Severity::Info,
+                reason: "removed".into(),
+                source: "second".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn process_stops_on_plugin_errors() {
+        let mut processor = Processor::new()
+            .use_plugin(MaxHeadings { max: 1 })
+            .use_plugin(|_: &mut Mdx, file: &mut VFile| file.info("ran", "never"));
+        let mut file = VFile::new("# a\n\n# b");
+        assert!(matches!(
+            processor.process(&mut file),
+            Err(ProcessError::Plugin(name)) if name == "max-headings"
+        ));
+        assert_eq!(file.value, "# a\n\n# b");
+        assert_eq!(file.messages.len(), 1);
+        assert_eq!(file.messages[0].reason, "2 headings, at most 1 allowed");
+    }
+
+    #[test]
+    fn process_parse_options_and_compiler() {
+        let mut processor = Processor::new()
+            .parse_options(ParseOptions {
+                math: true,
+                ..ParseOptions::default()
+            })
+            .compiler(|mdx, _| format!("{} nodes", mdx.ast.len()));
+        let mut file = VFile::new("$$\nx\n$$\n\ntext");
+        processor.process(&mut file).unwrap();
+        assert_eq!(file.value, "2 nodes");
+    }
+}
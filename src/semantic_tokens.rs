@@ -0,0 +1,572 @@
+//! Classifying the syntax of a document for editors to highlight,
+//! from the parse tree rather than a grammar of regular expressions.
+//!
+//! Every node of a [`Cst`] has its span, so that's where its tokens
+//! are looked for. Most of the tree's text borrows from the source,
+//! which says exactly where it is. Content that the parser had to
+//! rebuild, like a paragraph in a block quote with its markers
+//! stripped, is found line by line in the lines of its node.
+//!
+//! ```
+//! use mdx::{semantic_tokens, Cst, Span, TokenKind};
+//!
+//! let tokens = semantic_tokens(&Cst::parse("# Hello *world*").unwrap());
+//! assert_eq!(tokens[0].kind, TokenKind::HeadingMarker);
+//! assert_eq!(tokens[1].kind, TokenKind::Emphasis);
+//! assert_eq!(tokens[1].span, Span::new(8, 15));
+//! ```
+use crate::{
+    ast::{
+        emphasis::delimiter_runs,
+        inlines::{inlines_with_options, Inline},
+        lines::{split_lines, trim_eol},
+        ATXHeading, FencedCodeblock, JsxAttribute, JsxAttributeValue, JsxElement,
+    },
+    mdx_error::Span,
+    Cst, MdxAst, ParseOptions,
+};
+use std::{cmp::Reverse, ops::Range};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// The `#`s in front of a heading, or the `===` or `---` under it
+    HeadingMarker,
+    /// `*text*` or `_text_`, delimiters included
+    Emphasis,
+    /// `**text**` or `__text__`, delimiters included
+    Strong,
+    /// The ```` ``` ```` or `~~~` around a code block
+    CodeFence,
+    /// What follows the opening fence, like `js title="a.js"`
+    InfoString,
+    /// `Chart` in `<Chart>` and `</Chart>`
+    JsxTagName,
+    /// `type` in `<Note type="info">`
+    JsxAttributeName,
+    /// The braces around an expression, as a block or an attribute
+    ExpressionBrace,
+}
+impl TokenKind {
+    pub const ALL: [TokenKind; 8] = [
+        TokenKind::HeadingMarker,
+        TokenKind::Emphasis,
+        TokenKind::Strong,
+        TokenKind::CodeFence,
+        TokenKind::InfoString,
+        TokenKind::JsxTagName,
+        TokenKind::JsxAttributeName,
+        TokenKind::ExpressionBrace,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// The tokens in `cst`, ordered by where they start. Tokens can be
+/// nested, like emphasis in strong text, and then the outer one
+/// comes first. Blocks that were edited have nowhere to be in the
+/// source, so they don't have any.
+///
+/// This takes a `Cst` rather than an `Mdx`, because an `Mdx` only has
+/// the nodes, and neither the source nor where they are in it.
+pub fn semantic_tokens(cst: &Cst) -> Vec<SemanticToken> {
+    let mut tokens = Tokens {
+        source: cst.source(),
+        options: cst.options(),
+        tokens: vec![],
+    };
+    for block in cst.blocks() {
+        block.for_each_node(|node, span| tokens.node(node, span));
+    }
+    let mut tokens = tokens.tokens;
+    tokens.sort_by_key(|token| (token.span.start, Reverse(token.span.end)));
+    tokens
+}
+
+/// Where a piece of the tree's text is in the source, as the offset
+/// every line of it starts at in both
+struct Located {
+    lines: Vec<(usize, usize)>,
+}
+impl Located {
+    /// The offset in the source of `offset` in the text
+    fn start(&self, offset: usize) -> usize {
+        let (text, source) = self
+            .lines
+            .iter()
+            .rev()
+            .find(|(text, _)| *text <= offset)
+            .unwrap_or(&self.lines[0]);
+        source + offset.saturating_sub(*text)
+    }
+
+    /// Like `start`, but an offset at the start of a line is the end
+    /// of the line before
+    fn end(&self, offset: usize) -> usize {
+        let (text, source) = self
+            .lines
+            .iter()
+            .rev()
+            .find(|(text, _)| *text < offset)
+            .unwrap_or(&self.lines[0]);
+        source + offset.saturating_sub(*text)
+    }
+
+    fn span(&self, span: Span) -> Span {
+        Span::new(self.start(span.start), self.end(span.end))
+    }
+}
+
+struct Tokens<'s> {
+    source: &'s str,
+    options: ParseOptions,
+    tokens: Vec<SemanticToken>,
+}
+impl Tokens<'_> {
+    fn node(&mut self, node: &MdxAst, span: Span) {
+        match node {
+            MdxAst::ATXHeading(heading) => {
+                self.heading(heading, span);
+            }
+            MdxAst::Paragraph(paragraph) => {
+                if let Some(located) = self.locate(&paragraph.words, span.start, span.end) {
+                    self.inline(&paragraph.words, &located);
+                }
+            }
+            MdxAst::Codeblock(codeblock) => {
+                self.codeblock(codeblock, span);
+            }
+            MdxAst::JsxElement(element) => {
+                self.jsx_element(element, span);
+            }
+            MdxAst::Expression(_) => {
+                let text = &self.source[span.start..span.end];
+                let open = span.start + text.len() - text.trim_start().len();
+                self.brace(open, '{');
+                if let Some(close) = (span.start + text.trim_end().len()).checked_sub(1) {
+                    self.brace(close, '}');
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Where `text` is in the source, looking from `from` on, and no
+    /// further than `end`. Text that isn't borrowed from the source
+    /// has every line after its first at the end of the next line
+    /// there, after the markers and indentation its container took
+    /// off.
+    fn locate(&self, text: &str, from: usize, end: usize) -> Option<Located> {
+        if let Some(start) = offset_in(self.source, text) {
+            return Some(Located {
+                lines: vec![(0, start)],
+            });
+        }
+        let mut lines = vec![];
+        let mut from = from;
+        let mut offset = 0;
+        for (i, line) in text.split('\n').enumerate() {
+            let source_line = split_lines(self.source.get(from..end)?).next()?;
+            let in_source = trim_eol(source_line).trim_end();
+            let trimmed = line.trim();
+            let at = match i > 0 && in_source.ends_with(trimmed) {
+                true => in_source.len() - trimmed.len(),
+                false => in_source.find(trimmed)?,
+            };
+            lines.push((offset + line.len() - line.trim_start().len(), from + at));
+            from += source_line.len();
+            offset += line.len() + 1;
+        }
+        Some(Located { lines })
+    }
+
+    /// Where the next line after `offset` starts
+    fn next_line(&self, offset: usize) -> usize {
+        let line = split_lines(&self.source[offset..]).next().unwrap_or_default();
+        offset + line.len()
+    }
+
+    fn push(&mut self, kind: TokenKind, span: Span) {
+        if span.start < span.end {
+            self.tokens.push(SemanticToken { kind, span });
+        }
+    }
+
+    /// The run of `c` at `start`, after skipping spaces and tabs
+    fn run_at(&self, start: usize, c: char) -> Option<Span> {
+        let after = self.source.get(start..)?.trim_start_matches([' ', '\t']);
+        let start = self.source.len() - after.len();
+        let run = after.len() - after.trim_start_matches(c).len();
+        (run > 0).then(|| Span::new(start, start + run))
+    }
+
+    /// Emphasis and strong text in `text`, skipping code spans and
+    /// whatever else isn't text
+    fn inline(&mut self, text: &str, located: &Located) {
+        let ranges: Vec<_> = inlines_with_options(text, self.options)
+            .into_iter()
+            .filter_map(|node| match node {
+                Inline::Text(words) => {
+                    offset_in(text, &words).map(|offset| offset..offset + words.len())
+                }
+                _ => None,
+            })
+            .collect();
+        for (kind, span) in emphasis(text, &ranges) {
+            self.push(kind, located.span(span));
+        }
+    }
+
+    fn brace(&mut self, at: usize, brace: char) {
+        if self
+            .source
+            .get(at..)
+            .is_some_and(|rest| rest.starts_with(brace))
+        {
+            self.push(TokenKind::ExpressionBrace, Span::new(at, at + 1));
+        }
+    }
+
+    fn heading(&mut self, heading: &ATXHeading, span: Span) -> Option<()> {
+        // `#text` underlined on the next line is a setext heading
+        let atx = self.run_at(span.start, '#').filter(|marker| {
+            self.source[marker.end..].starts_with([' ', '\t', '\r', '\n'])
+                || marker.end == span.end
+        });
+        match atx {
+            Some(marker) => {
+                self.push(TokenKind::HeadingMarker, marker);
+                let value = self.locate(&heading.value, marker.end, span.end)?;
+                self.inline(&heading.value, &value);
+            }
+            // a setext heading, underlined on the line after its text
+            None => {
+                let value = self.locate(&heading.value, span.start, span.end)?;
+                let mut underline = value.end(heading.value.len());
+                underline = self.next_line(underline);
+                let line = trim_eol(split_lines(self.source.get(underline..span.end)?).next()?);
+                let marker = line.trim_end();
+                let run = ['=', '-']
+                    .iter()
+                    .map(|&c| marker.len() - marker.trim_end_matches(c).len())
+                    .max()?;
+                let start = underline + marker.len() - run;
+                self.push(TokenKind::HeadingMarker, Span::new(start, start + run));
+                self.inline(&heading.value, &value);
+            }
+        }
+        Some(())
+    }
+
+    fn codeblock(&mut self, codeblock: &FencedCodeblock, span: Span) -> Option<()> {
+        let (opening, c) = self
+            .run_at(span.start, '`')
+            .map(|span| (span, '`'))
+            .or_else(|| self.run_at(span.start, '~').map(|span| (span, '~')))?;
+        self.push(TokenKind::CodeFence, opening);
+        let info = codeblock.infostring.trim();
+        if !info.is_empty() {
+            let line = trim_eol(split_lines(&self.source[opening.end..]).next()?);
+            let start = opening.end + line.find(info)?;
+            self.push(TokenKind::InfoString, Span::new(start, start + info.len()));
+        }
+        // the last line, unless the fence was never closed
+        let text = self.source[opening.end..span.end].trim_end();
+        let line_start = text.rfind(['\n', '\r'])? + 1;
+        let line = &text[line_start..];
+        let run = line.len() - line.trim_end_matches(c).len();
+        let before = &line[..line.len() - run];
+        if run >= opening.end - opening.start && before.chars().all(|c| matches!(c, ' ' | '\t' | '>')) {
+            let end = opening.end + text.len();
+            self.push(TokenKind::CodeFence, Span::new(end - run, end));
+        }
+        Some(())
+    }
+
+    fn jsx_element(&mut self, element: &JsxElement, span: Span) {
+        let mut from = span.start;
+        if let Some(name) = &element.name {
+            if let Some(located) = self.locate(name, from, span.end) {
+                let name = located.span(Span::new(0, name.len()));
+                self.push(TokenKind::JsxTagName, name);
+                from = name.end;
+            }
+        }
+        for attribute in &element.attributes {
+            match attribute {
+                JsxAttribute::Named { name, value } => {
+                    if let Some(located) = self.locate(name, from, span.end) {
+                        let name = located.span(Span::new(0, name.len()));
+                        self.push(TokenKind::JsxAttributeName, name);
+                        from = name.end;
+                    }
+                    match value {
+                        Some(JsxAttributeValue::Expression(expression)) => {
+                            from = self.expression(expression, from, span.end).unwrap_or(from);
+                        }
+                        Some(JsxAttributeValue::String(value)) => {
+                            if let Some(located) = self.locate(value, from, span.end) {
+                                from = located.end(value.len());
+                            }
+                        }
+                        None => (),
+                    }
+                }
+                JsxAttribute::Spread(expression) => {
+                    from = self.spread(expression, from, span.end).unwrap_or(from);
+                }
+            }
+        }
+        self.closing_tag(element, span);
+    }
+
+    /// The braces around `{...expression}`, returning where it ends
+    fn spread(&mut self, expression: &str, from: usize, end: usize) -> Option<usize> {
+        let located = self.locate(expression, from, end)?;
+        let before = self.source.get(..located.start(0))?.trim_end();
+        let before = before.strip_suffix("...")?.trim_end();
+        self.brace(before.len().checked_sub(1)?, '{');
+        let after = self
+            .source
+            .get(located.end(expression.len())..)?
+            .trim_start();
+        let close = self.source.len() - after.len();
+        self.brace(close, '}');
+        Some(close + 1)
+    }
+
+    /// The closing tag of an element, which ends its span
+    fn closing_tag(&mut self, element: &JsxElement, span: Span) -> Option<()> {
+        let name = element.name.as_deref().filter(|_| !element.self_closing)?;
+        let text = &self.source[span.start..span.end];
+        let at = span.start + text.rfind("</")? + 2;
+        let after = self.source[at..span.end].trim_start();
+        if after.starts_with(name) {
+            let start = span.end - after.len();
+            self.push(TokenKind::JsxTagName, Span::new(start, start + name.len()));
+        }
+        Some(())
+    }
+
+    /// The braces around `expression`, which is the source between
+    /// them, returning where it ends
+    fn expression(&mut self, expression: &str, from: usize, end: usize) -> Option<usize> {
+        let located = self.locate(expression, from, end)?;
+        if let Some(open) = located.start(0).checked_sub(1) {
+            self.brace(open, '{');
+        }
+        let close = located.end(expression.len());
+        self.brace(close, '}');
+        Some(close + 1)
+    }
+}
+
+/// What's left of a delimiter run while it's paired up
+struct Delimiter {
+    c: char,
+    start: usize,
+    len: usize,
+    can_open: bool,
+    can_close: bool,
+}
+
+/// Emphasis and strong text in `text`, with their delimiters, where
+/// `ranges` are its plain text. Runs of `*` and `_` are paired up like
+/// CommonMark does, including the rule of three, so `*a **b** c*` is
+/// emphasis with strong text in it.
+fn emphasis(text: &str, ranges: &[Range<usize>]) -> Vec<(TokenKind, Span)> {
+    let mut delimiters: Vec<_> = delimiter_runs(text, ranges)
+        .into_iter()
+        .map(|run| Delimiter {
+            c: run.c,
+            start: run.range.start,
+            len: run.range.len(),
+            can_open: run.can_open(),
+            can_close: run.can_close(),
+        })
+        .collect();
+
+    let mut found = vec![];
+    // indexes of the delimiters that could still open something
+    let mut openers: Vec<usize> = vec![];
+    for closer in 0..delimiters.len() {
+        if delimiters[closer].can_close {
+            while delimiters[closer].len > 0 {
+                let opener = openers.iter().rposition(|&opener| {
+                    let (opener, closer) = (&delimiters[opener], &delimiters[closer]);
+                    let sum = opener.len + closer.len;
+                    opener.c == closer.c
+                        && !((opener.can_close || closer.can_open)
+                            && sum % 3 == 0
+                            && (opener.len % 3 != 0 || closer.len % 3 != 0))
+                });
+                let position = match opener {
+                    Some(position) => position,
+                    None => break,
+                };
+                let opener = openers[position];
+                let used = if delimiters[opener].len >= 2 && delimiters[closer].len >= 2 {
+                    2
+                } else {
+                    1
+                };
+                delimiters[opener].len -= used;
+                let start = delimiters[opener].start + delimiters[opener].len;
+                let end = delimiters[closer].start + used;
+                delimiters[closer].start += used;
+                delimiters[closer].len -= used;
+                let kind = match used {
+                    2 => TokenKind::Strong,
+                    _ => TokenKind::Emphasis,
+                };
+                found.push((kind, Span::new(start, end)));
+                // nothing between the two can be paired anymore
+                openers.truncate(position + 1);
+                if delimiters[opener].len == 0 {
+                    openers.pop();
+                }
+            }
+        }
+        if delimiters[closer].can_open && delimiters[closer].len > 0 {
+            openers.push(closer);
+        }
+    }
+    found
+}
+
+/// Where `inner` starts in `outer`, if it's a slice of it
+fn offset_in(outer: &str, inner: &str) -> Option<usize> {
+    let start = (inner.as_ptr() as usize).checked_sub(outer.as_ptr() as usize)?;
+    (start + inner.len() <= outer.len()).then_some(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<(TokenKind, &str)> {
+        tokens_with_options(source, ParseOptions::default())
+    }
+
+    fn tokens_with_options(source: &str, options: ParseOptions) -> Vec<(TokenKind, &str)> {
+        semantic_tokens(&Cst::parse_with_options(source, options).unwrap())
+            .into_iter()
+            .map(|token| (token.kind, &source[token.span.start..token.span.end]))
+            .collect()
+    }
+
+    #[test]
+    fn emphasis_pairs() {
+        let spans = |text: &str| -> Vec<(TokenKind, String)> {
+            emphasis(text, &[Range { start: 0, end: text.len() }])
+                .into_iter()
+                .map(|(kind, span)| (kind, text[span.start..span.end].to_string()))
+                .collect()
+        };
+        assert_eq!(
+            spans("*a **b** c*"),
+            vec![
+                (TokenKind::Strong, "**b**".to_string()),
+                (TokenKind::Emphasis, "*a **b** c*".to_string()),
+            ]
+        );
+        assert_eq!(
+            spans("***both***"),
+            vec![
+                (TokenKind::Strong, "**both**".to_string()),
+                (TokenKind::Emphasis, "***both***".to_string()),
+            ]
+        );
+        assert!(spans("snake_case_name and 2 * 3 * 4 and \\*not\\*").is_empty());
+        assert_eq!(
+            spans("__strong__ _em_"),
+            vec![
+                (TokenKind::Strong, "__strong__".to_string()),
+                (TokenKind::Emphasis, "_em_".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn headings_and_code() {
+        assert_eq!(
+            tokens("## A *b*\n\nTitle\n=====\n\n```js title=\"a.js\"\nlet a = `*x*`\n```\n"),
+            vec![
+                (TokenKind::HeadingMarker, "##"),
+                (TokenKind::Emphasis, "*b*"),
+                (TokenKind::HeadingMarker, "====="),
+                (TokenKind::CodeFence, "```"),
+                (TokenKind::InfoString, "js title=\"a.js\""),
+                (TokenKind::CodeFence, "```"),
+            ]
+        );
+        // code spans aren't emphasis
+        assert!(tokens("`*not*` text").is_empty());
+        // but emphasis can be around one
+        assert_eq!(
+            tokens("*a `*` b*"),
+            vec![(TokenKind::Emphasis, "*a `*` b*")]
+        );
+    }
+
+    #[test]
+    fn jsx_and_expressions() {
+        assert_eq!(
+            tokens("<Note type=\"info\" data={1} {...props}>\n  Some **bold**\n</Note>\n\n{a}\n"),
+            vec![
+                (TokenKind::JsxTagName, "Note"),
+                (TokenKind::JsxAttributeName, "type"),
+                (TokenKind::JsxAttributeName, "data"),
+                (TokenKind::ExpressionBrace, "{"),
+                (TokenKind::ExpressionBrace, "}"),
+                (TokenKind::ExpressionBrace, "{"),
+                (TokenKind::ExpressionBrace, "}"),
+                (TokenKind::Strong, "**bold**"),
+                (TokenKind::JsxTagName, "Note"),
+                (TokenKind::ExpressionBrace, "{"),
+                (TokenKind::ExpressionBrace, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rebuilt_content_is_found() {
+        let source = "- one *a*\n  two *b*\n- ```sh\n  ls\n  ```\n\n> # Quoted\n> <Box />\n";
+        assert_eq!(
+            tokens(source),
+            vec![
+                (TokenKind::Emphasis, "*a*"),
+                (TokenKind::Emphasis, "*b*"),
+                (TokenKind::CodeFence, "```"),
+                (TokenKind::InfoString, "sh"),
+                (TokenKind::CodeFence, "```"),
+                (TokenKind::HeadingMarker, "#"),
+                (TokenKind::JsxTagName, "Box"),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_text_is_found_where_it_is() {
+        let source = "> - *a*\n>   x\n> - *a*\n>   y\n";
+        let tokens = semantic_tokens(&Cst::parse(source).unwrap());
+        let starts: Vec<usize> = tokens.iter().map(|token| token.span.start).collect();
+        assert_eq!(starts, vec![4, 18]);
+    }
+
+    #[test]
+    fn math_is_not_emphasis() {
+        let source = "> $a*b$ and *c* $d*e$\n> more\n";
+        let math = ParseOptions {
+            math: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            tokens_with_options(source, math),
+            vec![(TokenKind::Emphasis, "*c*")]
+        );
+    }
+}
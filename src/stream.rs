@@ -0,0 +1,425 @@
+//! Parsing documents that are too big to hold in memory at once, a
+//! chunk at a time.
+//!
+//! A [`Parser`] is given the text as it arrives, and hands back each
+//! top level block as soon as nothing that comes later can change
+//! it. That's once the first line of the block after it is complete,
+//! since that's as far as a block looks, so only the block being read
+//! is kept around. [`Blocks`] does the same for anything that
+//! implements `Read`, as an iterator.
+//!
+//! The exception is a `<Tag>`, `{` or directive that isn't closed,
+//! or a frontmatter fence at the start: a full parse looks for the
+//! end of it all the way to the end of the document. A [`Parser`]
+//! only looks [`LOOKAHEAD`] bytes past where it starts, and then
+//! settles on what it is without the end, usually a paragraph, at
+//! the next blank line. So at most that much is held, and a document
+//! that does close one further down than that is parsed differently
+//! than it would be all at once.
+//!
+//! ```
+//! use mdx::{stream::Parser, MdxAst, ParseOptions};
+//!
+//! let mut parser = Parser::new(ParseOptions::default());
+//! assert!(parser.push("# Title\n\nSome ").is_empty());
+//! // the paragraph can't have ended yet, but the heading has
+//! let blocks = parser.push("text\n\n- item\n");
+//! assert!(matches!(blocks[0].node, MdxAst::ATXHeading(_)));
+//! assert_eq!(blocks.len(), 2);
+//! let rest = parser.finish().unwrap();
+//! assert!(matches!(rest[0].node, MdxAst::List(_)));
+//! ```
+use crate::{
+    ast::{
+        first_ast,
+        lines::separator,
+        mdx_ast,
+        slugs::{add_heading_ids, Slugger},
+    },
+    cst::{blocks, blocks_from, first_block, looks_ahead, opens_frontmatter, Parsed},
+    incremental::Block,
+    mdx_error::{MdxError, Span},
+    ParseOptions,
+};
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, Read},
+    slice, str,
+};
+
+/// How much is read at a time
+const CHUNK: usize = 64 * 1024;
+
+/// How far past the start of a block that isn't closed the parser
+/// looks for its end, before it gives up on it
+pub const LOOKAHEAD: usize = 1024 * 1024;
+
+/// Less than this is quick enough to parse again every time something
+/// is pushed
+const SMALL: usize = 4 * 1024;
+
+/// Parses a document that's given to it in pieces
+#[derive(Debug, Clone)]
+pub struct Parser {
+    options: ParseOptions,
+    /// What's been pushed, from the end of the last block that was
+    /// handed back
+    buffer: String,
+    /// Where `buffer` starts in the document
+    offset: usize,
+    /// How much of `buffer` is in front of the next block, which
+    /// isn't known until there's something other than whitespace
+    leading: Option<usize>,
+    /// How long `buffer` has to get before it's parsed again, so a
+    /// long block isn't parsed over and over as it comes in
+    retry_at: usize,
+    slugger: Slugger,
+}
+impl Parser {
+    pub fn new(options: ParseOptions) -> Self {
+        Parser {
+            options,
+            buffer: String::new(),
+            offset: 0,
+            leading: None,
+            retry_at: 0,
+            slugger: Slugger::default(),
+        }
+    }
+
+    /// Adds the next piece of the document, and returns the blocks it
+    /// finished. Spans are in bytes from the start of the document.
+    pub fn push(&mut self, chunk: &str) -> Vec<Block> {
+        self.buffer.push_str(chunk);
+        if self.buffer.len() < self.retry_at {
+            return vec![];
+        }
+        let blocks = self.settled();
+        // a block that's still coming in waits until there's twice as
+        // much of it, so it's parsed a few times and not once a line,
+        // but not past the lookahead, so giving up isn't put off
+        let twice = self.buffer.len() * 2;
+        self.retry_at = match blocks.is_empty() && self.buffer.len() >= SMALL {
+            true if self.buffer.len() <= LOOKAHEAD => twice.min(LOOKAHEAD + 1),
+            true => twice,
+            false => 0,
+        };
+        blocks
+    }
+
+    /// Parses whatever's left at the end of the document. Fails like
+    /// `Cst::parse_with_options` does for the whole document, with
+    /// spans in the whole document.
+    pub fn finish(mut self) -> Result<Vec<Block>, MdxError> {
+        let buffer = self.buffer.as_str();
+        let parsed = match self.leading {
+            // nothing's been handed back, so this is the whole document
+            None => blocks(buffer, self.options).map(|(rest, (parsed, _))| (rest, parsed)),
+            Some(leading) => blocks_from(
+                buffer,
+                &buffer[leading..],
+                &buffer[..leading],
+                self.offset == 0,
+                self.options,
+                |_| false,
+            )
+            .map(|(rest, (parsed, _))| (rest, parsed)),
+        };
+        match parsed {
+            Ok((_, parsed)) => Ok(owned(parsed, self.offset, self.options, &mut self.slugger)),
+            Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => {
+                Err(MdxError::from_error_tree(buffer, error).shift(self.offset))
+            }
+            Err(nom::Err::Incomplete(_)) => unreachable!("only complete parsers are used"),
+        }
+    }
+
+    /// Takes the blocks that can't change from the front of the
+    /// buffer
+    fn settled(&mut self) -> Vec<Block> {
+        let mut parsed = vec![];
+        let mut consumed = 0;
+        loop {
+            let rest = &self.buffer[consumed..];
+            let leading = match self.leading {
+                Some(leading) => leading,
+                None => match first_block(rest) {
+                    (_, "") => break,
+                    (leading, _) => leading.len(),
+                },
+            };
+            let input = &rest[leading..];
+            let first = self.leading.is_none();
+            let gave_up = input.len() > LOOKAHEAD;
+            let block = if first {
+                first_ast(self.options)(input)
+            } else {
+                mdx_ast(self.options)(input)
+            };
+            let (after, node) = match block {
+                Ok((_, node))
+                    if first && !gave_up && opens_frontmatter(input, &node, self.options) =>
+                {
+                    break
+                }
+                Ok(parsed) if gave_up || !looks_ahead(input, &parsed.1) => parsed,
+                _ => break,
+            };
+            // the next block has to have started, and its first line
+            // has to be all there
+            let (next, blank) = match separator(after) {
+                Ok(separated) => separated,
+                Err(_) => break,
+            };
+            if !next.trim_end_matches('\r').contains(['\n', '\r']) {
+                break;
+            }
+            let end = consumed + rest.len() - after.len();
+            let start = consumed + leading;
+            parsed.push((&rest[..leading], Span::new(start, end), node));
+            consumed = end;
+            self.leading = Some(blank.len());
+        }
+        let blocks = owned(parsed, self.offset, self.options, &mut self.slugger);
+        self.buffer.drain(..consumed);
+        self.offset += consumed;
+        blocks
+    }
+}
+
+/// Blocks with spans in `buffer`, moved to `offset` in the document
+fn owned(
+    parsed: Parsed,
+    offset: usize,
+    options: ParseOptions,
+    slugger: &mut Slugger,
+) -> Vec<Block> {
+    parsed
+        .into_iter()
+        .map(|(leading, span, node)| {
+            let span = Span::new(offset + span.start, offset + span.end);
+            let mut node = node.into_owned();
+            // blocks are handed back before the rest of the document
+            // is read, so an `{#id}` further down can't be reserved
+            // yet, the way it is for a whole document
+            if options.heading_ids {
+                add_heading_ids(slice::from_mut(&mut node), options, slugger);
+            }
+            Block {
+                leading: Span::new(span.start - leading.len(), span.start),
+                span,
+                node,
+            }
+        })
+        .collect()
+}
+
+/// Why a document couldn't be read to the end
+#[derive(Debug)]
+pub enum StreamError {
+    /// The reader failed, or what it read isn't UTF-8
+    Io(io::Error),
+    /// The document doesn't parse
+    Parse(MdxError),
+}
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Io(error) => write!(f, "couldn't read the document: {}", error),
+            StreamError::Parse(error) => error.fmt(f),
+        }
+    }
+}
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Io(error) => Some(error),
+            StreamError::Parse(error) => Some(error),
+        }
+    }
+}
+
+/// The top level blocks of a document that's read from `R`, as
+/// they're finished. Stops after the first error.
+pub struct Blocks<R> {
+    reader: R,
+    parser: Option<Parser>,
+    ready: VecDeque<Block>,
+    /// The start of a character that was split between reads
+    partial: Vec<u8>,
+}
+impl<R: Read> Blocks<R> {
+    pub fn new(reader: R, options: ParseOptions) -> Self {
+        Blocks {
+            reader,
+            parser: Some(Parser::new(options)),
+            ready: VecDeque::new(),
+            partial: vec![],
+        }
+    }
+
+    /// Reads the next chunk into the parser, or finishes it at the end
+    fn read(&mut self) -> Result<(), StreamError> {
+        let mut chunk = std::mem::take(&mut self.partial);
+        let start = chunk.len();
+        chunk.resize(start + CHUNK, 0);
+        let read = loop {
+            match self.reader.read(&mut chunk[start..]) {
+                Ok(read) => break read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+                Err(error) => return Err(StreamError::Io(error)),
+            }
+        };
+        chunk.truncate(start + read);
+        if read == 0 {
+            if !chunk.is_empty() {
+                return Err(StreamError::Io(not_utf8()));
+            }
+            let parser = self.parser.take().expect("only finished once");
+            self.ready
+                .extend(parser.finish().map_err(StreamError::Parse)?);
+            return Ok(());
+        }
+        let valid = match str::from_utf8(&chunk) {
+            Ok(text) => text.len(),
+            // a character that's cut off at the end of the chunk
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(_) => return Err(StreamError::Io(not_utf8())),
+        };
+        let text = str::from_utf8(&chunk[..valid]).expect("checked above");
+        let parser = self.parser.as_mut().expect("only read until finished");
+        self.ready.extend(parser.push(text));
+        self.partial = chunk[valid..].to_vec();
+        Ok(())
+    }
+}
+impl<R: Read> Iterator for Blocks<R> {
+    type Item = Result<Block, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(block) = self.ready.pop_front() {
+                return Some(Ok(block));
+            }
+            self.parser.as_ref()?;
+            if let Err(error) = self.read() {
+                self.parser = None;
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+fn not_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "the document isn't valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cst, MdxAst};
+
+    /// The spans and nodes of every block, parsed all at once
+    fn whole(text: &str, options: ParseOptions) -> Vec<(Span, MdxAst<'static>)> {
+        Cst::parse_with_options(text, options)
+            .unwrap()
+            .blocks()
+            .iter()
+            .map(|block| (block.span.unwrap(), block.node().clone().into_owned()))
+            .collect()
+    }
+
+    fn streamed(blocks: Vec<Block>) -> Vec<(Span, MdxAst<'static>)> {
+        blocks
+            .into_iter()
+            .map(|block| (block.span, block.node))
+            .collect()
+    }
+
+    #[test]
+    fn one_character_at_a_time() {
+        let text = "\n# Title {#top}\n\nSome\ntext\n\n- one\n\n  two\n\n```js\n\nlet a;\n```\n\n<Note>\n\nhi\n</Note>\n\n## Title\n";
+        let options = ParseOptions {
+            heading_attributes: true,
+            heading_ids: true,
+            ..ParseOptions::default()
+        };
+        let mut parser = Parser::new(options);
+        let mut blocks = vec![];
+        for c in text.chars() {
+            blocks.extend(parser.push(c.encode_utf8(&mut [0; 4])));
+        }
+        let finished = blocks.len();
+        blocks.extend(parser.finish().unwrap());
+        // only the last heading waited for the end
+        assert_eq!(finished, 5);
+        assert_eq!(streamed(blocks), whole(text, options));
+    }
+
+    #[test]
+    fn unclosed_tags_wait_for_the_end() {
+        let mut parser = Parser::new(ParseOptions::default());
+        assert!(parser.push("<Note>\n\ntext\n\nmore\n\n").is_empty());
+        let blocks = parser.push("</Note>\n\nafter\n");
+        assert!(matches!(blocks[0].node, MdxAst::JsxElement(_)));
+    }
+
+    #[test]
+    fn unclosed_tags_give_up_after_the_lookahead() {
+        let text = "text\n\n".repeat(LOOKAHEAD / 6 + 1);
+        let mut parser = Parser::new(ParseOptions::default());
+        assert!(parser.push("<Note>\n\n").is_empty());
+        let blocks = parser.push(&text);
+        assert!(matches!(blocks[0].node, MdxAst::Paragraph(_)));
+        assert_eq!(parser.finish().unwrap().len(), 1);
+
+        let options = ParseOptions {
+            frontmatter: true,
+            ..ParseOptions::default()
+        };
+        let mut parser = Parser::new(options);
+        assert!(parser.push("---\n\n").is_empty());
+        let blocks = parser.push(&text);
+        assert!(matches!(blocks[0].node, MdxAst::ThematicBreak(_)));
+    }
+
+    #[test]
+    fn unclosed_fences_wait_for_the_end() {
+        let text = "# A\n\n```js\n\n# B\n\n";
+        let mut parser = Parser::new(ParseOptions::default());
+        let mut blocks = parser.push(text);
+        assert_eq!(blocks.len(), 1);
+        blocks.extend(parser.finish().unwrap());
+        assert_eq!(streamed(blocks), whole(text, ParseOptions::default()));
+    }
+
+    #[test]
+    fn characters_split_between_reads() {
+        /// Reads one byte at a time
+        struct Bytes<'a>(&'a [u8]);
+        impl Read for Bytes<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.split_first() {
+                    Some((byte, rest)) => {
+                        buf[0] = *byte;
+                        self.0 = rest;
+                        Ok(1)
+                    }
+                    None => Ok(0),
+                }
+            }
+        }
+        let text = "# Grüße\n\n😀 text\n";
+        let blocks: Result<Vec<_>, _> =
+            Blocks::new(Bytes(text.as_bytes()), ParseOptions::default()).collect();
+        assert_eq!(
+            streamed(blocks.unwrap()),
+            whole(text, ParseOptions::default())
+        );
+
+        let mut blocks = Blocks::new(&b"# A\n\n\xff\n"[..], ParseOptions::default());
+        assert!(matches!(blocks.next(), Some(Err(StreamError::Io(_)))));
+        assert!(blocks.next().is_none());
+    }
+}
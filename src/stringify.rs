@@ -0,0 +1,646 @@
+//! Writing documents back out as markdown.
+//!
+//! Markdown leaves a lot of choices open, like which character
+//! bullets use or how long code fences are. `StringifyOptions` makes
+//! those choices the same way everywhere in a document, the way
+//! remark-stringify does, so `stringify_with_options` can be used as
+//! a formatter.
+use crate::{
+    ast::{
+        emphasis::delimiter_runs,
+        inlines_with_options,
+        lines::{self, indentation, split_lines, trim_eol},
+        paragraphs::interrupts_paragraph,
+        ATXHeading, BlockQuote, ContainerDirective, FencedCodeblock, Inline, JsxElement, List,
+        Paragraph, Table, ThematicBreak,
+    },
+    parse_with_options, Mdx, MdxAst, MdxError, ParseOptions,
+};
+use serde::Deserialize;
+use std::{borrow::Cow, ops::Range};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadingStyle {
+    /// `# Heading`
+    Atx,
+    /// A heading underlined with `=` or `-`. Only levels 1 and 2
+    /// can be written this way, so deeper headings, and headings
+    /// with attributes, still use hashes.
+    Setext,
+}
+
+/// Where the content of a list item starts, after its marker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListItemIndent {
+    /// One space after the marker
+    One,
+    /// The next tab stop after the marker
+    Tab,
+    /// `One` for tight lists and `Tab` for spread ones
+    Mixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StringifyOptions {
+    /// `-`, `*` or `+`. A bullet list that directly follows another
+    /// one gets a different bullet, so the two don't merge.
+    pub bullet: char,
+    /// `*` or `_` for emphasis, or `None` to keep whichever was used
+    pub emphasis: Option<char>,
+    /// `*` or `_` for strong emphasis, or `None` to keep whichever
+    /// was used
+    pub strong: Option<char>,
+    /// `` ` `` or `~` for fenced code
+    pub fence: char,
+    /// How long code fences are. Fences get longer than this when
+    /// the code has a line that would close them otherwise.
+    pub fence_length: usize,
+    pub heading_style: HeadingStyle,
+    /// The thematic break to write for every thematic break, or
+    /// `None` to keep each one as it was
+    pub thematic_break: Option<ThematicBreak>,
+    pub list_item_indent: ListItemIndent,
+    /// Paragraphs are refilled to fit in this many columns, or keep
+    /// the line breaks they had for `None`
+    pub line_width: Option<usize>,
+}
+impl Default for StringifyOptions {
+    fn default() -> Self {
+        StringifyOptions {
+            bullet: '-',
+            emphasis: None,
+            strong: None,
+            fence: '`',
+            fence_length: 3,
+            heading_style: HeadingStyle::Atx,
+            thematic_break: None,
+            list_item_indent: ListItemIndent::One,
+            line_width: None,
+        }
+    }
+}
+
+pub fn stringify_with_options(mdx: &Mdx, options: StringifyOptions) -> String {
+    blocks(&mdx.ast, "\n\n", options)
+}
+
+/// Formats a document: parses it with `parse` and stringifies it
+/// again with `options`. The result ends in a line ending, and uses
+/// the same line endings as `input`, and keeps a BOM if it has one.
+/// ESM and expressions are kept as they were written, and so are
+/// heading ids, which aren't generated for headings without one.
+pub fn format(
+    input: &str,
+    parse: ParseOptions,
+    options: StringifyOptions,
+) -> Result<String, MdxError> {
+    let (bom, body) = match input.strip_prefix('\u{feff}') {
+        Some(body) => ("\u{feff}", body),
+        None => ("", input),
+    };
+    if body.trim().is_empty() {
+        return Ok(bom.to_string());
+    }
+    let parse = ParseOptions {
+        heading_ids: false,
+        ..parse
+    };
+    let mdx = parse_with_options(input, parse)?;
+    let out = format!("{}{}\n", bom, stringify_with_options(&mdx, options));
+    Ok(lines::with_line_ending(out, lines::line_ending(input)))
+}
+
+/// Writes a run of sibling blocks, joined by `separator`
+pub(crate) fn blocks(nodes: &[MdxAst], separator: &str, options: StringifyOptions) -> String {
+    let mut out = String::new();
+    let mut previous: Option<&MdxAst> = None;
+    let mut alternate = false;
+    for node in nodes {
+        if previous.is_some() {
+            out.push_str(separator);
+        }
+        match node {
+            MdxAst::List(list) => {
+                let follows_list = matches!(
+                    previous,
+                    Some(MdxAst::List(previous)) if previous.ordered == list.ordered
+                );
+                alternate = follows_list && !alternate;
+                out.push_str(&self::list(list, alternate, options));
+            }
+            node => {
+                alternate = false;
+                out.push_str(&block(node, options));
+            }
+        }
+        previous = Some(node);
+    }
+    out
+}
+
+pub(crate) fn block(node: &MdxAst, options: StringifyOptions) -> String {
+    match node {
+        MdxAst::ATXHeading(heading) => self::heading(heading, options),
+        MdxAst::ThematicBreak(thematic_break) => options
+            .thematic_break
+            .unwrap_or(*thematic_break)
+            .to_string(),
+        MdxAst::Paragraph(paragraph) => self::paragraph(paragraph, options),
+        MdxAst::Codeblock(codeblock) => self::codeblock(codeblock, options),
+        MdxAst::ContainerDirective(directive) => container_directive(directive, options),
+        MdxAst::BlockQuote(quote) => block_quote(quote, options),
+        MdxAst::List(list) => self::list(list, false, options),
+        MdxAst::JsxElement(element) => jsx_element(element, options),
+        MdxAst::Table(table) => self::table(table, options),
+        // ESM and expressions are JavaScript, which is left alone
+        MdxAst::Math(_)
+        | MdxAst::Frontmatter(_)
+        | MdxAst::LeafDirective(_)
+        | MdxAst::Esm(_)
+        | MdxAst::Expression(_)
+        | MdxAst::Toc(_) => node.to_string(),
+    }
+}
+
+fn heading(heading: &ATXHeading, options: StringifyOptions) -> String {
+    let value = phrasing(&heading.value, options);
+    if options.heading_style == HeadingStyle::Setext
+        && heading.level <= 2
+        && heading.written_attributes().is_empty()
+        && !value.trim().is_empty()
+    {
+        let underline = if heading.level == 1 { "=" } else { "-" };
+        return format!("{}\n{}", value, underline.repeat(value.chars().count()));
+    }
+    match value {
+        Cow::Borrowed(_) => heading.to_string(),
+        Cow::Owned(value) => ATXHeading {
+            value: value.into(),
+            ..heading.clone()
+        }
+        .to_string(),
+    }
+}
+
+fn paragraph(paragraph: &Paragraph, options: StringifyOptions) -> String {
+    let words = phrasing(&paragraph.words, options);
+    match options.line_width {
+        Some(width) => wrap(&words, width),
+        None => Paragraph {
+            words: words.as_ref().into(),
+        }
+        .to_string(),
+    }
+}
+
+fn table(table: &Table, options: StringifyOptions) -> String {
+    Table {
+        align: table.align.clone(),
+        rows: table
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| phrasing(cell, options)).collect())
+            .collect(),
+    }
+    .to_string()
+}
+
+pub(crate) fn codeblock(codeblock: &FencedCodeblock, options: StringifyOptions) -> String {
+    // backticks can't be in the infostring of a backtick fence
+    let c = if options.fence == '`' && codeblock.infostring.contains('`') {
+        '~'
+    } else {
+        options.fence
+    };
+    // any line that starts with as many of the fence character as
+    // the fence would close it
+    let longest = split_lines(&codeblock.code)
+        .map(trim_eol)
+        .filter_map(|line| {
+            let (columns, indent) = indentation(line);
+            let line = &line[indent..];
+            Some(line.len() - line.trim_start_matches(c).len()).filter(|_| columns <= 3)
+        })
+        .max()
+        .unwrap_or(0);
+    let fence = c
+        .to_string()
+        .repeat(options.fence_length.max(3).max(longest + 1));
+    // the code keeps the line ending before the closing fence,
+    // whichever kind it was
+    let eol = if codeblock.code.is_empty() || codeblock.code.ends_with(['\n', '\r']) {
+        ""
+    } else {
+        "\n"
+    };
+    format!(
+        "{}{}\n{}{}{}",
+        fence, codeblock.infostring, codeblock.code, eol, fence
+    )
+}
+
+pub(crate) fn container_directive(
+    directive: &ContainerDirective,
+    options: StringifyOptions,
+) -> String {
+    let content = blocks(&directive.children, "\n\n", options);
+    // the fence is longer than any run of colons a line of the
+    // content starts with, so nothing in there can close it
+    let longest = split_lines(&content)
+        .map(trim_eol)
+        .filter_map(|line| {
+            let (columns, indent) = indentation(line);
+            let line = &line[indent..];
+            Some(line.len() - line.trim_start_matches(':').len()).filter(|_| columns <= 3)
+        })
+        .max()
+        .unwrap_or(0);
+    let fence = ":".repeat(longest.max(2) + 1);
+    if content.is_empty() {
+        format!("{}{}\n{}", fence, directive.opening(), fence)
+    } else {
+        format!("{}{}\n{}\n{}", fence, directive.opening(), content, fence)
+    }
+}
+
+pub(crate) fn jsx_element(element: &JsxElement, options: StringifyOptions) -> String {
+    let opening = element.opening();
+    if element.self_closing {
+        return opening;
+    }
+    let name = element.name.as_deref().unwrap_or("");
+    let content = blocks(&element.children, "\n\n", options);
+    if content.is_empty() {
+        format!("{}</{}>", opening, name)
+    } else {
+        format!("{}\n{}\n</{}>", opening, content, name)
+    }
+}
+
+pub(crate) fn block_quote(quote: &BlockQuote, options: StringifyOptions) -> String {
+    blocks(&quote.children, "\n\n", options)
+        .split('\n')
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `alternate` lists use the other bullet, or `)` instead of `.`
+pub(crate) fn list(list: &List, alternate: bool, options: StringifyOptions) -> String {
+    let bullet = match (alternate, options.bullet) {
+        (false, bullet) => bullet,
+        (true, '-') => '*',
+        (true, _) => '-',
+    };
+    let delimiter = if alternate { ')' } else { '.' };
+    let tab = match options.list_item_indent {
+        ListItemIndent::One => false,
+        ListItemIndent::Tab => true,
+        ListItemIndent::Mixed => list.spread,
+    };
+    let start = list.start.unwrap_or(1);
+    let mut out = String::new();
+    for (i, item) in list.children.iter().enumerate() {
+        if i > 0 {
+            out.push_str(if list.spread { "\n\n" } else { "\n" });
+        }
+        let marker = if list.ordered {
+            format!("{}{}", start + i as u32, delimiter)
+        } else {
+            bullet.to_string()
+        };
+        let width = if tab {
+            (marker.len() + 4) / 4 * 4
+        } else {
+            marker.len() + 1
+        };
+        out.push_str(&marker);
+        let separator = if item.spread { "\n\n" } else { "\n" };
+        let mut content = blocks(&item.children, separator, options);
+        if let Some(checked) = item.checked {
+            let checkbox = if checked { "[x]" } else { "[ ]" };
+            if !content.is_empty() {
+                content.insert(0, ' ');
+            }
+            content.insert_str(0, checkbox);
+        }
+        for (i, line) in content.split('\n').enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            if !line.is_empty() {
+                let indent = if i == 0 { width - marker.len() } else { width };
+                out.push_str(&" ".repeat(indent));
+                out.push_str(line);
+            }
+        }
+    }
+    out
+}
+
+/// Refills text to fit in `width` columns. Lines are only broken at
+/// spaces in plain text, not inside code spans or other inline
+/// nodes, and not where the next line would start another block.
+/// Hard line breaks stay where they are.
+fn wrap(text: &str, width: usize) -> String {
+    let mut lines: Vec<String> = vec![];
+    let mut segment = String::new();
+    for line in split_lines(text).map(trim_eol) {
+        if !segment.is_empty() {
+            segment.push(' ');
+        }
+        segment.push_str(line.trim());
+        if line.ends_with('\\') {
+            fill(&segment, width, &mut lines);
+            segment.clear();
+        } else if line.ends_with("  ") {
+            fill(&segment, width, &mut lines);
+            if let Some(last) = lines.last_mut() {
+                last.push_str("  ");
+            }
+            segment.clear();
+        }
+    }
+    if !segment.is_empty() {
+        fill(&segment, width, &mut lines);
+    }
+    lines.join("\n")
+}
+
+fn fill(segment: &str, width: usize, lines: &mut Vec<String>) {
+    let text = text_ranges(segment);
+    let mut words = vec![];
+    let mut start = 0;
+    for (i, c) in segment.char_indices() {
+        if c == ' ' && text.iter().any(|range| range.contains(&i)) {
+            words.push(&segment[start..i]);
+            start = i + 1;
+        }
+    }
+    words.push(&segment[start..]);
+
+    let mut line = String::new();
+    for word in words.into_iter().filter(|word| !word.is_empty()) {
+        let fits = line.chars().count() + 1 + word.chars().count() <= width;
+        if line.is_empty() || fits || !can_start_line(word) {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        } else {
+            lines.push(std::mem::take(&mut line));
+            line.push_str(word);
+        }
+    }
+    lines.push(line);
+}
+
+/// Whether a line in the middle of a paragraph can start with `word`
+/// without turning into another block, or into a setext underline
+fn can_start_line(word: &str) -> bool {
+    let underline = word.chars().all(|c| c == '=') || word.chars().all(|c| c == '-');
+    !word.is_empty() && !underline && !interrupts_paragraph(&format!("{} x", word))
+}
+
+/// The byte ranges of `text` that are plain text, rather than code
+/// spans, math or directives, which have to be left alone
+fn text_ranges(text: &str) -> Vec<Range<usize>> {
+    let options = ParseOptions {
+        math: true,
+        directives: true,
+        ..ParseOptions::default()
+    };
+    inlines_with_options(text, options)
+        .into_iter()
+        .filter_map(|node| match node {
+            // text nodes always point into the input
+            Inline::Text(Cow::Borrowed(value)) => {
+                let start = value.as_ptr() as usize - text.as_ptr() as usize;
+                Some(start..start + value.len())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Rewrites the markers of emphasis and strong emphasis in some
+/// phrasing content to the ones in `options`
+fn phrasing<'t>(text: &'t str, options: StringifyOptions) -> Cow<'t, str> {
+    if options.emphasis.is_none() && options.strong.is_none() {
+        return Cow::Borrowed(text);
+    }
+    let runs = delimiter_runs(text, &text_ranges(text));
+    let mut openers: Vec<usize> = vec![];
+    let mut replacements: Vec<(Range<usize>, String)> = vec![];
+    for (i, run) in runs.iter().enumerate() {
+        let matched = if run.can_close() {
+            openers.iter().rposition(|opener| {
+                runs[*opener].c == run.c && runs[*opener].range.len() == run.range.len()
+            })
+        } else {
+            None
+        };
+        let position = match matched {
+            Some(position) => position,
+            None => {
+                if run.can_open() {
+                    openers.push(i);
+                }
+                continue;
+            }
+        };
+        let opener = &runs[openers[position]];
+        openers.truncate(position);
+        let emphasis = options.emphasis.unwrap_or(run.c);
+        let strong = options.strong.unwrap_or(run.c);
+        let (opening, closing) = match run.range.len() {
+            1 => (emphasis.to_string(), emphasis.to_string()),
+            2 => (strong.to_string().repeat(2), strong.to_string().repeat(2)),
+            3 => (
+                format!("{}{}{}", strong, strong, emphasis),
+                format!("{}{}{}", emphasis, strong, strong),
+            ),
+            _ => continue,
+        };
+        // `_` can't open or close emphasis inside of a word
+        let inside_word =
+            (opening.contains('_') && opener.right_flanking && !opener.punctuation_before)
+                || (closing.contains('_') && run.left_flanking && !run.punctuation_after);
+        if !inside_word {
+            replacements.push((opener.range.clone(), opening));
+            replacements.push((run.range.clone(), closing));
+        }
+    }
+    if replacements.is_empty() {
+        return Cow::Borrowed(text);
+    }
+    replacements.sort_by_key(|(range, _)| range.start);
+    let mut out = String::with_capacity(text.len());
+    let mut offset = 0;
+    for (range, replacement) in replacements {
+        out.push_str(&text[offset..range.start]);
+        out.push_str(&replacement);
+        offset = range.end;
+    }
+    out.push_str(&text[offset..]);
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn render(input: &str, options: StringifyOptions) -> String {
+        stringify_with_options(&parse(input).unwrap(), options)
+    }
+
+    #[test]
+    fn stringify_defaults_keep_markers() {
+        let input = "# *a* __b__\n\n***\n\n- one\n- two";
+        assert_eq!(render(input, StringifyOptions::default()), input);
+    }
+
+    #[test]
+    fn stringify_emphasis_markers() {
+        let options = StringifyOptions {
+            emphasis: Some('_'),
+            strong: Some('*'),
+            ..StringifyOptions::default()
+        };
+        assert_eq!(
+            render("*a* __b__ ***c*** `*d*` snake*case*word", options),
+            "_a_ **b** **_c_** `*d*` snake*case*word"
+        );
+    }
+
+    #[test]
+    fn stringify_fences() {
+        let options = StringifyOptions {
+            fence: '~',
+            fence_length: 4,
+            ..StringifyOptions::default()
+        };
+        assert_eq!(
+            render("```js\nlet a;\n~~~~~\n```", options),
+            "~~~~~~js\nlet a;\n~~~~~\n~~~~~~"
+        );
+    }
+
+    #[test]
+    fn stringify_setext_and_thematic_breaks() {
+        let options = StringifyOptions {
+            heading_style: HeadingStyle::Setext,
+            thematic_break: Some(ThematicBreak {
+                char_count: 3,
+                break_char: '*',
+            }),
+            ..StringifyOptions::default()
+        };
+        let output = render("# Title\n\n## Sub\n\n### Deep\n\n- - -", options);
+        assert_eq!(output, "Title\n=====\n\nSub\n---\n\n### Deep\n\n***");
+        assert_eq!(render(&output, options), output);
+    }
+
+    #[test]
+    fn stringify_list_indent() {
+        let options = StringifyOptions {
+            bullet: '*',
+            list_item_indent: ListItemIndent::Tab,
+            ..StringifyOptions::default()
+        };
+        assert_eq!(
+            render("- a\n  b\n\n10. c\n\n- d\n\n+ e", options),
+            "*   a\n    b\n\n10. c\n\n*   d\n\n-   e"
+        );
+    }
+
+    #[test]
+    fn stringify_line_width() {
+        let options = StringifyOptions {
+            line_width: Some(7),
+            ..StringifyOptions::default()
+        };
+        assert_eq!(
+            render("one two - three four `five six`\nhard  \nbreak", options),
+            "one two -\nthree\nfour\n`five six`\nhard  \nbreak"
+        );
+    }
+
+    #[test]
+    fn format_keeps_javascript_verbatim() {
+        let input = "import {Chart}   from './chart'\r\n\r\n{ items.map(item =>   item) }\r\n\r\n* one\r\n";
+        let options = StringifyOptions {
+            line_width: Some(10),
+            ..StringifyOptions::default()
+        };
+        let formatted = super::format(input, ParseOptions::default(), options).unwrap();
+        assert_eq!(
+            formatted,
+            "import {Chart}   from './chart'\r\n\r\n{ items.map(item =>   item) }\r\n\r\n- one\r\n"
+        );
+        assert_eq!(
+            super::format(&formatted, ParseOptions::default(), options).unwrap(),
+            formatted
+        );
+        assert_eq!(
+            super::format("\u{feff} \n\n", ParseOptions::default(), options).unwrap(),
+            "\u{feff}"
+        );
+    }
+
+    #[test]
+    fn format_with_parse_options() {
+        let input = "# Sum {#sum}\n\n$$\n\\sum_{i=0}^n i\n$$\n\n:::note{.info}\n*   Math   *still* works\n:::\n\n## Heading\n";
+        let parse = ParseOptions {
+            math: true,
+            directives: true,
+            heading_attributes: true,
+            heading_ids: true,
+            ..ParseOptions::default()
+        };
+        let formatted = super::format(input, parse, StringifyOptions::default()).unwrap();
+        assert_eq!(
+            formatted,
+            "# Sum {#sum}\n\n$$\n\\sum_{i=0}^n i\n$$\n\n:::note{.info}\n- Math   *still* works\n:::\n\n## Heading\n"
+        );
+        assert_eq!(
+            super::format(&formatted, parse, StringifyOptions::default()).unwrap(),
+            formatted
+        );
+    }
+
+    #[test]
+    fn format_containers_twice() {
+        let parse = ParseOptions {
+            math: true,
+            directives: true,
+            ..ParseOptions::default()
+        };
+        let format = |input: &str| super::format(input, parse, StringifyOptions::default());
+        for input in [
+            ":::note\nx\n:::note\ny\n:::",
+            ":::note\n:::tip\nx\n:::\n:::",
+            ":::note\n- :::tip\n  x\n:::",
+            "::::note\n:::tip\nx\n::::",
+            ":::note\nnever closed",
+            ":::note\n$$\n:::\n",
+            ":::note\nexport const a = 1\n```\n:::",
+        ] {
+            let formatted = format(input).unwrap();
+            assert_eq!(format(&formatted).unwrap(), formatted, "{:?}", input);
+        }
+        assert_eq!(
+            format(":::note\n- :::tip\n  x\n:::").unwrap(),
+            "::::note\n- :::tip\n  x\n  :::\n::::\n"
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use crate::{
+    ast::{
+        slugs::{reserve_ids, slug, Slugger},
+        text_content, ATXHeading,
+    },
+    processor::{Plugin, VFile},
+    visit::{visit, Visit, VisitControl},
+    Mdx, MdxAst, ParseOptions,
+};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocOptions {
+    /// Headings shallower than this are left out
+    pub min_depth: u8,
+    /// Headings deeper than this are left out
+    pub max_depth: u8,
+    /// The heading that marks where the table of contents goes.
+    /// Headings match when their slugs do, so `## Table of Contents`
+    /// and `## table of contents` are both the same marker.
+    pub heading: String,
+    /// The options the document was parsed with, which decide
+    /// what inline markup gets stripped from heading text
+    pub parse_options: ParseOptions,
+}
+impl Default for TocOptions {
+    fn default() -> Self {
+        TocOptions {
+            min_depth: 1,
+            max_depth: 6,
+            heading: "Table of contents".to_string(),
+            parse_options: ParseOptions::default(),
+        }
+    }
+}
+
+/// A nested outline of a document's headings
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Toc {
+    pub entries: Vec<TocEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocEntry {
+    pub depth: u8,
+    /// The heading's id, or the slug it would get
+    pub id: String,
+    /// The heading's text, without any inline markup
+    pub text: String,
+    pub children: Vec<TocEntry>,
+}
+
+/// A toc is written as a nested list of links to each heading
+impl fmt::Display for Toc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut lines = vec![];
+        let mut stack: Vec<(&TocEntry, usize)> =
+            self.entries.iter().rev().map(|entry| (entry, 0)).collect();
+        while let Some((entry, indent)) = stack.pop() {
+            lines.push(format!(
+                "{}- [{}](#{})",
+                "  ".repeat(indent),
+                escape(&entry.text),
+                entry.id
+            ));
+            stack.extend(entry.children.iter().rev().map(|child| (child, indent + 1)));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Heading text is plain text, so anything that would be markup
+/// in a link's text is escaped
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_[]<>{}$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn is_marker(heading: &ATXHeading, options: &TocOptions) -> bool {
+    slug(&heading_text(heading, options)) == slug(&options.heading)
+}
+
+fn heading_text(heading: &ATXHeading, options: &TocOptions) -> String {
+    text_content(&heading.inlines_with_options(options.parse_options))
+}
+
+/// Nests each entry under the closest entry before it that has
+/// a smaller depth
+fn nest(flat: Vec<TocEntry>) -> Vec<TocEntry> {
+    let mut entries: Vec<TocEntry> = vec![];
+    for entry in flat {
+        let mut siblings = &mut entries;
+        while siblings.last().is_some_and(|last| last.depth < entry.depth) {
+            siblings = &mut siblings.last_mut().unwrap().children;
+        }
+        siblings.push(entry);
+    }
+    entries
+}
+
+struct Entries<'o> {
+    options: &'o TocOptions,
+    slugger: Slugger,
+    flat: Vec<TocEntry>,
+}
+impl<'a, 'o> Visit<'a> for Entries<'o> {
+    fn enter_atx_heading(&mut self, heading: &ATXHeading<'a>) -> VisitControl {
+        let options = self.options;
+        let text = heading_text(heading, options);
+        let id = match &heading.id {
+            Some(id) => id.to_string(),
+            None => self.slugger.slug(&text),
+        };
+        let in_range = heading.level >= options.min_depth && heading.level <= options.max_depth;
+        if in_range && !text.is_empty() && !is_marker(heading, options) {
+            self.flat.push(TocEntry {
+                depth: heading.level,
+                id,
+                text,
+                children: vec![],
+            });
+        }
+        VisitControl::Continue
+    }
+}
+
+/// Builds a table of contents from the headings of a document,
+/// including the ones in block quotes, lists and other containers,
+/// except for the table of contents heading itself.
+///
+/// Headings are linked to by their `id`, or otherwise by the slug
+/// `ParseOptions::heading_ids` would have given them.
+pub fn toc(mdx: &Mdx, options: &TocOptions) -> Toc {
+    let mut slugger = Slugger::default();
+    reserve_ids(&mdx.ast, &mut slugger);
+    let mut entries = Entries {
+        options,
+        slugger,
+        flat: vec![],
+    };
+    visit(&mdx.ast, &mut entries);
+    Toc {
+        entries: nest(entries.flat),
+    }
+}
+
+/// Puts a table of contents right after the table of contents
+/// heading, like remark-toc does. Anything between that heading
+/// and the next heading of the same or a higher level is replaced.
+///
+/// Returns false if there's no table of contents heading.
+pub fn inject_toc(mdx: &mut Mdx, options: &TocOptions) -> bool {
+    let marker = mdx.ast.iter().position(|node| match node {
+        MdxAst::ATXHeading(heading) => is_marker(heading, options),
+        _ => false,
+    });
+    let marker = match marker {
+        Some(marker) => marker,
+        None => return false,
+    };
+    let level = match &mdx.ast[marker] {
+        MdxAst::ATXHeading(heading) => heading.level,
+        _ => unreachable!(),
+    };
+    let end = mdx.ast[marker + 1..]
+        .iter()
+        .position(|node| matches!(node, MdxAst::ATXHeading(heading) if heading.level <= level))
+        .map_or(mdx.ast.len(), |end| marker + 1 + end);
+    let toc = toc(mdx, options);
+    let replacement = if toc.entries.is_empty() {
+        None
+    } else {
+        Some(MdxAst::Toc(toc))
+    };
+    mdx.ast.splice(marker + 1..end, replacement);
+    true
+}
+
+/// `inject_toc` as a `Processor` plugin
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InjectToc(pub TocOptions);
+impl Plugin for InjectToc {
+    fn name(&self) -> &str {
+        "toc"
+    }
+
+    fn transform(&mut self, mdx: &mut Mdx<'_>, file: &mut VFile) {
+        if !inject_toc(mdx, &self.0) {
+            let reason = format!("missing `{}` heading", self.0.heading);
+            file.info(reason, self.name());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn entry(depth: u8, id: &str, children: Vec<TocEntry>) -> TocEntry {
+        TocEntry {
+            depth,
+            id: id.to_string(),
+            text: id.to_string(),
+            children,
+        }
+    }
+
+    #[test]
+    fn toc_nests_headings() {
+        let mdx = parse("# a\n\n## b\n\n#### c\n\n## d\n\n# e").unwrap();
+        assert_eq!(
+            toc(&mdx, &TocOptions::default()).entries,
+            vec![
+                entry(
+                    1,
+                    "a",
+                    vec![
+                        entry(2, "b", vec![entry(4, "c", vec![])]),
+                        entry(2, "d", vec![])
+                    ]
+                ),
+                entry(1, "e", vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn toc_depth_range() {
+        let mdx = parse("# a\n\n## b\n\n### c\n\n## d").unwrap();
+        let options = TocOptions {
+            min_depth: 2,
+            max_depth: 2,
+            ..TocOptions::default()
+        };
+        assert_eq!(
+            toc(&mdx, &options).entries,
+            vec![entry(2, "b", vec![]), entry(2, "d", vec![])]
+        );
+    }
+
+    #[test]
+    fn toc_strips_inline_markup() {
+        let mdx = parse("# The `parse` fn\n\n# The `parse` fn").unwrap();
+        let toc = toc(&mdx, &TocOptions::default());
+        assert_eq!(toc.entries[0].text, "The parse fn");
+        assert_eq!(toc.entries[0].id, "the-parse-fn");
+        assert_eq!(toc.entries[1].id, "the-parse-fn-1");
+    }
+
+    #[test]
+    fn stringify_toc() {
+        let toc = Toc {
+            entries: vec![
+                entry(1, "a", vec![entry(2, "b", vec![])]),
+                entry(1, "c", vec![]),
+            ],
+        };
+        assert_eq!(toc.to_string(), "- [a](#a)\n  - [b](#b)\n- [c](#c)");
+    }
+
+    #[test]
+    fn toc_includes_nested_headings() {
+        // the same ids `heading_ids` gives them, `{#a-1}` included
+        let options = TocOptions {
+            parse_options: ParseOptions {
+                heading_attributes: true,
+                ..ParseOptions::default()
+            },
+            ..TocOptions::default()
+        };
+        let mdx = crate::parse_with_options(
+            "# a\n\n> # a\n\n- # a\n\n<Note>\n# b {#a-1}\n</Note>",
+            options.parse_options,
+        )
+        .unwrap();
+        let ids: Vec<_> = toc(&mdx, &options)
+            .entries
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(ids, vec!["a", "a-2", "a-3", "a-1"]);
+    }
+
+    #[test]
+    fn stringify_toc_escapes_text() {
+        let toc = Toc {
+            entries: vec![TocEntry {
+                depth: 1,
+                id: "a-b-c".to_string(),
+                text: "[a] *b* <c>".to_string(),
+                children: vec![],
+            }],
+        };
+        assert_eq!(toc.to_string(), "- [\\[a\\] \\*b\\* \\<c\\>](#a-b-c)");
+    }
+}
@@ -0,0 +1,698 @@
+//! Traversal of the AST, so plugins don't each need their own
+//! recursive `match` over every kind of node.
+//!
+//! [`Visit`] and [`VisitMut`] get an `enter` and an `exit` hook for
+//! every node, depth first and in document order. By default those
+//! forward to a hook for each kind of node, so implementors only
+//! override what they're interested in. [`Fold`] rebuilds the tree
+//! bottom up, and can replace or remove nodes as it goes.
+use crate::{
+    ast::{
+        ATXHeading, BlockQuote, ContainerDirective, Esm, Expression, FencedCodeblock, Frontmatter,
+        JsxElement, LeafDirective, List, ListItem, Math, Paragraph, Table, ThematicBreak,
+    },
+    toc::Toc,
+    MdxAst,
+};
+
+/// What the traversal should do after a hook runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisitControl {
+    /// Keep going
+    Continue,
+    /// Don't visit the children of the node that was just entered.
+    /// The node is still exited. Returned from an exit hook, this
+    /// is the same as `Continue`.
+    SkipChildren,
+    /// Stop the traversal, without exiting any nodes
+    Stop,
+}
+
+pub trait Visit<'a> {
+    fn enter(&mut self, node: &MdxAst<'a>) -> VisitControl {
+        match node {
+            MdxAst::ATXHeading(heading) => self.enter_atx_heading(heading),
+            MdxAst::ThematicBreak(brk) => self.enter_thematic_break(brk),
+            MdxAst::Paragraph(para) => self.enter_paragraph(para),
+            MdxAst::Codeblock(codeblock) => self.enter_codeblock(codeblock),
+            MdxAst::Math(math) => self.enter_math(math),
+            MdxAst::Table(table) => self.enter_table(table),
+            MdxAst::Frontmatter(frontmatter) => self.enter_frontmatter(frontmatter),
+            MdxAst::LeafDirective(leaf) => self.enter_leaf_directive(leaf),
+            MdxAst::ContainerDirective(container) => self.enter_container_directive(container),
+            MdxAst::BlockQuote(quote) => self.enter_block_quote(quote),
+            MdxAst::List(list) => self.enter_list(list),
+            MdxAst::JsxElement(element) => self.enter_jsx_element(element),
+            MdxAst::Esm(esm) => self.enter_esm(esm),
+            MdxAst::Expression(expression) => self.enter_expression(expression),
+            MdxAst::Toc(toc) => self.enter_toc(toc),
+        }
+    }
+    fn exit(&mut self, node: &MdxAst<'a>) -> VisitControl {
+        match node {
+            MdxAst::ATXHeading(heading) => self.exit_atx_heading(heading),
+            MdxAst::ThematicBreak(brk) => self.exit_thematic_break(brk),
+            MdxAst::Paragraph(para) => self.exit_paragraph(para),
+            MdxAst::Codeblock(codeblock) => self.exit_codeblock(codeblock),
+            MdxAst::Math(math) => self.exit_math(math),
+            MdxAst::Table(table) => self.exit_table(table),
+            MdxAst::Frontmatter(frontmatter) => self.exit_frontmatter(frontmatter),
+            MdxAst::LeafDirective(leaf) => self.exit_leaf_directive(leaf),
+            MdxAst::ContainerDirective(container) => self.exit_container_directive(container),
+            MdxAst::BlockQuote(quote) => self.exit_block_quote(quote),
+            MdxAst::List(list) => self.exit_list(list),
+            MdxAst::JsxElement(element) => self.exit_jsx_element(element),
+            MdxAst::Esm(esm) => self.exit_esm(esm),
+            MdxAst::Expression(expression) => self.exit_expression(expression),
+            MdxAst::Toc(toc) => self.exit_toc(toc),
+        }
+    }
+
+    fn enter_atx_heading(&mut self, _heading: &ATXHeading<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_atx_heading(&mut self, _heading: &ATXHeading<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_thematic_break(&mut self, _brk: &ThematicBreak) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_thematic_break(&mut self, _brk: &ThematicBreak) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_paragraph(&mut self, _para: &Paragraph<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_paragraph(&mut self, _para: &Paragraph<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_codeblock(&mut self, _codeblock: &FencedCodeblock<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_codeblock(&mut self, _codeblock: &FencedCodeblock<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_math(&mut self, _math: &Math<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_math(&mut self, _math: &Math<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_table(&mut self, _table: &Table<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_table(&mut self, _table: &Table<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_frontmatter(&mut self, _frontmatter: &Frontmatter<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_frontmatter(&mut self, _frontmatter: &Frontmatter<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_leaf_directive(&mut self, _leaf: &LeafDirective<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_leaf_directive(&mut self, _leaf: &LeafDirective<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_container_directive(&mut self, _container: &ContainerDirective<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_container_directive(&mut self, _container: &ContainerDirective<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_block_quote(&mut self, _quote: &BlockQuote<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_block_quote(&mut self, _quote: &BlockQuote<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_list(&mut self, _list: &List<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_list(&mut self, _list: &List<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_list_item(&mut self, _item: &ListItem<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_list_item(&mut self, _item: &ListItem<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_jsx_element(&mut self, _element: &JsxElement<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_jsx_element(&mut self, _element: &JsxElement<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_esm(&mut self, _esm: &Esm<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_esm(&mut self, _esm: &Esm<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_expression(&mut self, _expression: &Expression<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_expression(&mut self, _expression: &Expression<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_toc(&mut self, _toc: &Toc) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_toc(&mut self, _toc: &Toc) -> VisitControl {
+        VisitControl::Continue
+    }
+}
+
+pub trait VisitMut<'a> {
+    fn enter(&mut self, node: &mut MdxAst<'a>) -> VisitControl {
+        match node {
+            MdxAst::ATXHeading(heading) => self.enter_atx_heading(heading),
+            MdxAst::ThematicBreak(brk) => self.enter_thematic_break(brk),
+            MdxAst::Paragraph(para) => self.enter_paragraph(para),
+            MdxAst::Codeblock(codeblock) => self.enter_codeblock(codeblock),
+            MdxAst::Math(math) => self.enter_math(math),
+            MdxAst::Table(table) => self.enter_table(table),
+            MdxAst::Frontmatter(frontmatter) => self.enter_frontmatter(frontmatter),
+            MdxAst::LeafDirective(leaf) => self.enter_leaf_directive(leaf),
+            MdxAst::ContainerDirective(container) => self.enter_container_directive(container),
+            MdxAst::BlockQuote(quote) => self.enter_block_quote(quote),
+            MdxAst::List(list) => self.enter_list(list),
+            MdxAst::JsxElement(element) => self.enter_jsx_element(element),
+            MdxAst::Esm(esm) => self.enter_esm(esm),
+            MdxAst::Expression(expression) => self.enter_expression(expression),
+            MdxAst::Toc(toc) => self.enter_toc(toc),
+        }
+    }
+    fn exit(&mut self, node: &mut MdxAst<'a>) -> VisitControl {
+        match node {
+            MdxAst::ATXHeading(heading) => self.exit_atx_heading(heading),
+            MdxAst::ThematicBreak(brk) => self.exit_thematic_break(brk),
+            MdxAst::Paragraph(para) => self.exit_paragraph(para),
+            MdxAst::Codeblock(codeblock) => self.exit_codeblock(codeblock),
+            MdxAst::Math(math) => self.exit_math(math),
+            MdxAst::Table(table) => self.exit_table(table),
+            MdxAst::Frontmatter(frontmatter) => self.exit_frontmatter(frontmatter),
+            MdxAst::LeafDirective(leaf) => self.exit_leaf_directive(leaf),
+            MdxAst::ContainerDirective(container) => self.exit_container_directive(container),
+            MdxAst::BlockQuote(quote) => self.exit_block_quote(quote),
+            MdxAst::List(list) => self.exit_list(list),
+            MdxAst::JsxElement(element) => self.exit_jsx_element(element),
+            MdxAst::Esm(esm) => self.exit_esm(esm),
+            MdxAst::Expression(expression) => self.exit_expression(expression),
+            MdxAst::Toc(toc) => self.exit_toc(toc),
+        }
+    }
+
+    fn enter_atx_heading(&mut self, _heading: &mut ATXHeading<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_atx_heading(&mut self, _heading: &mut ATXHeading<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_thematic_break(&mut self, _brk: &mut ThematicBreak) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_thematic_break(&mut self, _brk: &mut ThematicBreak) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_paragraph(&mut self, _para: &mut Paragraph<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_paragraph(&mut self, _para: &mut Paragraph<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_codeblock(&mut self, _codeblock: &mut FencedCodeblock<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_codeblock(&mut self, _codeblock: &mut FencedCodeblock<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_math(&mut self, _math: &mut Math<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_math(&mut self, _math: &mut Math<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_table(&mut self, _table: &mut Table<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_table(&mut self, _table: &mut Table<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_frontmatter(&mut self, _frontmatter: &mut Frontmatter<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_frontmatter(&mut self, _frontmatter: &mut Frontmatter<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_leaf_directive(&mut self, _leaf: &mut LeafDirective<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_leaf_directive(&mut self, _leaf: &mut LeafDirective<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_container_directive(
+        &mut self,
+        _container: &mut ContainerDirective<'a>,
+    ) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_container_directive(
+        &mut self,
+        _container: &mut ContainerDirective<'a>,
+    ) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_block_quote(&mut self, _quote: &mut BlockQuote<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_block_quote(&mut self, _quote: &mut BlockQuote<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_list(&mut self, _list: &mut List<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_list(&mut self, _list: &mut List<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_list_item(&mut self, _item: &mut ListItem<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_list_item(&mut self, _item: &mut ListItem<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_jsx_element(&mut self, _element: &mut JsxElement<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_jsx_element(&mut self, _element: &mut JsxElement<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_esm(&mut self, _esm: &mut Esm<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_esm(&mut self, _esm: &mut Esm<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_expression(&mut self, _expression: &mut Expression<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_expression(&mut self, _expression: &mut Expression<'a>) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn enter_toc(&mut self, _toc: &mut Toc) -> VisitControl {
+        VisitControl::Continue
+    }
+    fn exit_toc(&mut self, _toc: &mut Toc) -> VisitControl {
+        VisitControl::Continue
+    }
+}
+
+/// Rebuilds the tree bottom up: a node's children are folded
+/// before the node itself. Returning `None` removes the node.
+pub trait Fold<'a> {
+    fn fold(&mut self, node: MdxAst<'a>) -> Option<MdxAst<'a>> {
+        match node {
+            MdxAst::ATXHeading(heading) => self.fold_atx_heading(heading),
+            MdxAst::ThematicBreak(brk) => self.fold_thematic_break(brk),
+            MdxAst::Paragraph(para) => self.fold_paragraph(para),
+            MdxAst::Codeblock(codeblock) => self.fold_codeblock(codeblock),
+            MdxAst::Math(math) => self.fold_math(math),
+            MdxAst::Table(table) => self.fold_table(table),
+            MdxAst::Frontmatter(frontmatter) => self.fold_frontmatter(frontmatter),
+            MdxAst::LeafDirective(leaf) => self.fold_leaf_directive(leaf),
+            MdxAst::ContainerDirective(container) => self.fold_container_directive(container),
+            MdxAst::BlockQuote(quote) => self.fold_block_quote(quote),
+            MdxAst::List(list) => self.fold_list(list),
+            MdxAst::JsxElement(element) => self.fold_jsx_element(element),
+            MdxAst::Esm(esm) => self.fold_esm(esm),
+            MdxAst::Expression(expression) => self.fold_expression(expression),
+            MdxAst::Toc(toc) => self.fold_toc(toc),
+        }
+    }
+
+    fn fold_atx_heading(&mut self, heading: ATXHeading<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::ATXHeading(heading))
+    }
+    fn fold_thematic_break(&mut self, brk: ThematicBreak) -> Option<MdxAst<'a>> {
+        Some(MdxAst::ThematicBreak(brk))
+    }
+    fn fold_paragraph(&mut self, para: Paragraph<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::Paragraph(para))
+    }
+    fn fold_codeblock(&mut self, codeblock: FencedCodeblock<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::Codeblock(codeblock))
+    }
+    fn fold_math(&mut self, math: Math<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::Math(math))
+    }
+    fn fold_table(&mut self, table: Table<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::Table(table))
+    }
+    fn fold_frontmatter(&mut self, frontmatter: Frontmatter<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::Frontmatter(frontmatter))
+    }
+    fn fold_leaf_directive(&mut self, leaf: LeafDirective<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::LeafDirective(leaf))
+    }
+    fn fold_container_directive(
+        &mut self,
+        container: ContainerDirective<'a>,
+    ) -> Option<MdxAst<'a>> {
+        Some(MdxAst::ContainerDirective(container))
+    }
+    fn fold_block_quote(&mut self, quote: BlockQuote<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::BlockQuote(quote))
+    }
+    fn fold_list(&mut self, list: List<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::List(list))
+    }
+    /// Folds an item after its children. Returning `None` removes
+    /// the item from its list.
+    fn fold_list_item(&mut self, item: ListItem<'a>) -> Option<ListItem<'a>> {
+        Some(item)
+    }
+    fn fold_jsx_element(&mut self, element: JsxElement<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::JsxElement(element))
+    }
+    fn fold_esm(&mut self, esm: Esm<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::Esm(esm))
+    }
+    fn fold_expression(&mut self, expression: Expression<'a>) -> Option<MdxAst<'a>> {
+        Some(MdxAst::Expression(expression))
+    }
+    fn fold_toc(&mut self, toc: Toc) -> Option<MdxAst<'a>> {
+        Some(MdxAst::Toc(toc))
+    }
+}
+
+/// Visits the children of `node`. List items aren't nodes of their
+/// own, so they get their own hooks around their children.
+fn visit_children<'a, V: Visit<'a> + ?Sized>(node: &MdxAst<'a>, visitor: &mut V) -> VisitControl {
+    let children = match node {
+        MdxAst::ContainerDirective(container) => &container.children,
+        MdxAst::BlockQuote(quote) => &quote.children,
+        MdxAst::JsxElement(element) => &element.children,
+        MdxAst::List(list) => {
+            for item in &list.children {
+                match visitor.enter_list_item(item) {
+                    VisitControl::Stop => return VisitControl::Stop,
+                    VisitControl::SkipChildren => (),
+                    VisitControl::Continue => {
+                        if visit(&item.children, visitor) == VisitControl::Stop {
+                            return VisitControl::Stop;
+                        }
+                    }
+                }
+                if visitor.exit_list_item(item) == VisitControl::Stop {
+                    return VisitControl::Stop;
+                }
+            }
+            return VisitControl::Continue;
+        }
+        _ => return VisitControl::Continue,
+    };
+    visit(children, visitor)
+}
+
+fn visit_children_mut<'a, V: VisitMut<'a> + ?Sized>(
+    node: &mut MdxAst<'a>,
+    visitor: &mut V,
+) -> VisitControl {
+    let children = match node {
+        MdxAst::ContainerDirective(container) => &mut container.children,
+        MdxAst::BlockQuote(quote) => &mut quote.children,
+        MdxAst::JsxElement(element) => &mut element.children,
+        MdxAst::List(list) => {
+            for item in &mut list.children {
+                match visitor.enter_list_item(item) {
+                    VisitControl::Stop => return VisitControl::Stop,
+                    VisitControl::SkipChildren => (),
+                    VisitControl::Continue => {
+                        if visit_mut(&mut item.children, visitor) == VisitControl::Stop {
+                            return VisitControl::Stop;
+                        }
+                    }
+                }
+                if visitor.exit_list_item(item) == VisitControl::Stop {
+                    return VisitControl::Stop;
+                }
+            }
+            return VisitControl::Continue;
+        }
+        _ => return VisitControl::Continue,
+    };
+    visit_mut(children, visitor)
+}
+
+/// Visits every node in `ast`. Returns `VisitControl::Stop` if
+/// the visitor stopped early.
+pub fn visit<'a, V: Visit<'a> + ?Sized>(ast: &[MdxAst<'a>], visitor: &mut V) -> VisitControl {
+    for node in ast {
+        match visitor.enter(node) {
+            VisitControl::Stop => return VisitControl::Stop,
+            VisitControl::SkipChildren => (),
+            VisitControl::Continue => {
+                if visit_children(node, visitor) == VisitControl::Stop {
+                    return VisitControl::Stop;
+                }
+            }
+        }
+        if visitor.exit(node) == VisitControl::Stop {
+            return VisitControl::Stop;
+        }
+    }
+    VisitControl::Continue
+}
+
+/// Like [`visit`], with mutable access to every node
+pub fn visit_mut<'a, V: VisitMut<'a> + ?Sized>(
+    ast: &mut [MdxAst<'a>],
+    visitor: &mut V,
+) -> VisitControl {
+    for node in ast {
+        match visitor.enter(node) {
+            VisitControl::Stop => return VisitControl::Stop,
+            VisitControl::SkipChildren => (),
+            VisitControl::Continue => {
+                if visit_children_mut(node, visitor) == VisitControl::Stop {
+                    return VisitControl::Stop;
+                }
+            }
+        }
+        if visitor.exit(node) == VisitControl::Stop {
+            return VisitControl::Stop;
+        }
+    }
+    VisitControl::Continue
+}
+
+/// Folds every node in `ast`, children first
+pub fn fold<'a, F: Fold<'a> + ?Sized>(ast: Vec<MdxAst<'a>>, folder: &mut F) -> Vec<MdxAst<'a>> {
+    ast.into_iter()
+        .filter_map(|node| {
+            let node = match node {
+                MdxAst::ContainerDirective(mut container) => {
+                    container.children = fold(container.children, folder);
+                    MdxAst::ContainerDirective(container)
+                }
+                MdxAst::BlockQuote(mut quote) => {
+                    quote.children = fold(quote.children, folder);
+                    MdxAst::BlockQuote(quote)
+                }
+                MdxAst::JsxElement(mut element) => {
+                    element.children = fold(element.children, folder);
+                    MdxAst::JsxElement(element)
+                }
+                MdxAst::List(mut list) => {
+                    list.children = list
+                        .children
+                        .into_iter()
+                        .filter_map(|mut item| {
+                            item.children = fold(item.children, folder);
+                            folder.fold_list_item(item)
+                        })
+                        .collect();
+                    MdxAst::List(list)
+                }
+                node => node,
+            };
+            folder.fold(node)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_with_options, ParseOptions};
+
+    const INPUT: &str = "# one\n\n:::note\n## two\n\n---\n:::\n\n---\n\n# three";
+
+    fn directives() -> ParseOptions {
+        ParseOptions {
+            directives: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    #[derive(Default)]
+    struct Headings {
+        values: Vec<String>,
+        skip_containers: bool,
+        stop_at: Option<&'static str>,
+    }
+    impl<'a> Visit<'a> for Headings {
+        fn enter_atx_heading(&mut self, heading: &ATXHeading<'a>) -> VisitControl {
+            self.values.push(heading.value.to_string());
+            if self.stop_at == Some(heading.value.as_ref()) {
+                VisitControl::Stop
+            } else {
+                VisitControl::Continue
+            }
+        }
+        fn enter_container_directive(&mut self, _: &ContainerDirective<'a>) -> VisitControl {
+            if self.skip_containers {
+                VisitControl::SkipChildren
+            } else {
+                VisitControl::Continue
+            }
+        }
+    }
+
+    #[test]
+    fn visit_in_order() {
+        let mdx = parse_with_options(INPUT, directives()).unwrap();
+        let mut headings = Headings::default();
+        assert_eq!(visit(&mdx.ast, &mut headings), VisitControl::Continue);
+        assert_eq!(headings.values, vec!["one", "two", "three"]);
+    }
+
+    #[test]
+    fn visit_skip_children() {
+        let mdx = parse_with_options(INPUT, directives()).unwrap();
+        let mut headings = Headings {
+            skip_containers: true,
+            ..Headings::default()
+        };
+        visit(&mdx.ast, &mut headings);
+        assert_eq!(headings.values, vec!["one", "three"]);
+    }
+
+    #[test]
+    fn visit_stop() {
+        let mdx = parse_with_options(INPUT, directives()).unwrap();
+        let mut headings = Headings {
+            stop_at: Some("two"),
+            ..Headings::default()
+        };
+        assert_eq!(visit(&mdx.ast, &mut headings), VisitControl::Stop);
+        assert_eq!(headings.values, vec!["one", "two"]);
+    }
+
+    #[test]
+    fn visit_enter_and_exit() {
+        struct Depth(usize, usize);
+        impl<'a> Visit<'a> for Depth {
+            fn enter(&mut self, _: &MdxAst<'a>) -> VisitControl {
+                self.0 += 1;
+                self.1 = std::cmp::max(self.0, self.1);
+                VisitControl::Continue
+            }
+            fn exit(&mut self, _: &MdxAst<'a>) -> VisitControl {
+                self.0 -= 1;
+                VisitControl::Continue
+            }
+        }
+        let mdx = parse_with_options(INPUT, directives()).unwrap();
+        let mut depth = Depth(0, 0);
+        visit(&mdx.ast, &mut depth);
+        assert_eq!((depth.0, depth.1), (0, 2));
+    }
+
+    #[test]
+    fn visit_mut_demotes_headings() {
+        struct Demote;
+        impl<'a> VisitMut<'a> for Demote {
+            fn enter_atx_heading(&mut self, heading: &mut ATXHeading<'a>) -> VisitControl {
+                heading.level += 1;
+                VisitControl::Continue
+            }
+        }
+        let mut mdx = parse_with_options(INPUT, directives()).unwrap();
+        visit_mut(&mut mdx.ast, &mut Demote);
+        assert_eq!(
+            crate::stringify(mdx),
+            "## one\n\n:::note\n### two\n\n---\n:::\n\n---\n\n## three"
+        );
+    }
+
+    #[test]
+    fn fold_removes_and_replaces() {
+        struct Breaks;
+        impl<'a> Fold<'a> for Breaks {
+            fn fold_thematic_break(&mut self, _: ThematicBreak) -> Option<MdxAst<'a>> {
+                None
+            }
+            fn fold_container_directive(
+                &mut self,
+                container: ContainerDirective<'a>,
+            ) -> Option<MdxAst<'a>> {
+                Some(MdxAst::Paragraph(Paragraph {
+                    words: container.name,
+                }))
+            }
+        }
+        let mdx = parse_with_options(INPUT, directives()).unwrap();
+        let ast = fold(mdx.ast, &mut Breaks);
+        assert_eq!(
+            crate::stringify(crate::Mdx { ast }),
+            "# one\n\nnote\n\n# three"
+        );
+    }
+
+    #[test]
+    fn visit_list_items() {
+        #[derive(Default)]
+        struct Items(Vec<usize>);
+        impl<'a> Visit<'a> for Items {
+            fn enter_list_item(&mut self, item: &ListItem<'a>) -> VisitControl {
+                self.0.push(item.children.len());
+                VisitControl::Continue
+            }
+        }
+        let mdx = crate::parse("> - a
+>   - b
+>
+>     c
+> - d").unwrap();
+        let mut items = Items::default();
+        visit(&mdx.ast, &mut items);
+        assert_eq!(items.0, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn fold_removes_list_items() {
+        struct Empty;
+        impl<'a> Fold<'a> for Empty {
+            fn fold_paragraph(&mut self, para: Paragraph<'a>) -> Option<MdxAst<'a>> {
+                if para.words == "drop" {
+                    None
+                } else {
+                    Some(MdxAst::Paragraph(para))
+                }
+            }
+            fn fold_list_item(&mut self, item: ListItem<'a>) -> Option<ListItem<'a>> {
+                Some(item).filter(|item| !item.children.is_empty())
+            }
+        }
+        let mdx = crate::parse("- keep
+- drop
+- also").unwrap();
+        let ast = fold(mdx.ast, &mut Empty);
+        assert_eq!(crate::stringify(crate::Mdx { ast }), "- keep
+- also");
+    }
+}
@@ -0,0 +1,301 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+fn mdx(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mdx"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Runs `mdx` with `stdin` piped in
+fn mdx_stdin(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mdx"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// A fresh directory for one test to write files to
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mdx-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn check_finds_markdown_files() {
+    let dir = scratch("check");
+    write(&dir.join("docs/a.mdx"), "# A\n\n### C\n");
+    write(&dir.join("docs/nested/b.md"), "# B\n");
+    write(&dir.join("docs/notes.txt"), "```\nunclosed");
+    write(&dir.join("node_modules/c.md"), "```\nunclosed");
+
+    let output = mdx(&["check", "--format", "json", dir.to_str().unwrap()]);
+    assert!(output.status.success());
+    let files: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let files = files.as_array().unwrap();
+    assert_eq!(files.len(), 2);
+    assert!(files[0]["path"].as_str().unwrap().ends_with("a.mdx"));
+    assert_eq!(files[0]["diagnostics"][0]["code"], "heading-increment");
+    assert_eq!(files[0]["diagnostics"][0]["severity"], "warning");
+    assert_eq!(files[1]["diagnostics"], serde_json::json!([]));
+}
+
+#[test]
+fn check_fails_on_errors() {
+    let dir = scratch("check-errors");
+    write(&dir.join("mdx.toml"), "[lint]\nheading-increment = \"error\"\n");
+    write(&dir.join("bad.mdx"), "# A\n\n### C\n\n```js\nunclosed");
+    let glob = format!("{}/*.mdx", dir.display());
+    let output = mdx(&["check", &glob]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("error[heading-increment]"), "{}", stdout);
+    // an unclosed fence parses, but it's still worth a warning
+    assert!(stdout.contains("warning[MDX003]"), "{}", stdout);
+    assert!(stdout.ends_with("1 error, 1 warning\n"), "{}", stdout);
+}
+
+#[test]
+fn check_passes_empty_files() {
+    let dir = scratch("check-empty");
+    write(&dir.join("empty.md"), "");
+    write(&dir.join("blank.mdx"), "\n  \n");
+    let output = mdx(&["check", dir.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "0 errors, 0 warnings\n");
+}
+
+#[test]
+fn fmt_check_and_rewrite() {
+    let dir = scratch("fmt");
+    let file = dir.join("a.mdx");
+    write(&file, "* one\n* two\n");
+    let path = file.to_str().unwrap();
+
+    assert_eq!(mdx(&["fmt", "--check", path]).status.code(), Some(1));
+    assert!(mdx(&["fmt", "--check", "--bullet", "*", path])
+        .status
+        .success());
+    assert!(mdx(&["fmt", path]).status.success());
+    assert_eq!(fs::read_to_string(&file).unwrap(), "- one\n- two\n");
+}
+
+#[test]
+fn stdin_to_stdout() {
+    let output = mdx_stdin(&["stringify", "--bullet", "*"], "- one\n- two");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "* one\n* two\n");
+    assert!(output.stderr.is_empty());
+
+    let output = mdx_stdin(&["html", "-"], "# Hi & bye");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "<h1>Hi &amp; bye</h1>\n"
+    );
+
+    // emphasis isn't rendered, which the help says
+    let output = mdx_stdin(&["html", "-"], "# hi *there*");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "<h1>hi *there*</h1>\n");
+    let output = mdx_stdin(&["html", "--help"], "");
+    let help = String::from_utf8(output.stdout).unwrap();
+    assert!(help.contains("Emphasis, strong text and links aren't rendered yet"));
+}
+
+#[test]
+fn compile_to_a_file() {
+    let dir = scratch("compile");
+    let out = dir.join("out.html");
+    let output = mdx_stdin(
+        &[
+            "compile",
+            "--heading-ids",
+            "--toc",
+            "--to",
+            "html",
+            "-o",
+            out.to_str().unwrap(),
+        ],
+        "# Guide\n\n## Table of contents\n\n## Usage",
+    );
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        fs::read_to_string(&out).unwrap(),
+        "<h1 id=\"guide\">Guide</h1>
+<h2 id=\"table-of-contents\">Table of contents</h2>
+<ul>
+<li><a href=\"#guide\">Guide</a>
+<ul>
+<li><a href=\"#usage\">Usage</a></li>
+</ul>
+</li>
+</ul>
+<h2 id=\"usage\">Usage</h2>
+"
+    );
+}
+
+#[test]
+fn exit_codes() {
+    // a document without any blocks is still a document
+    let output = mdx_stdin(&["parse"], "\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+
+    assert_eq!(mdx(&["parse", "--no-such-flag"]).status.code(), Some(2));
+    assert_eq!(mdx(&[]).status.code(), Some(2));
+    assert_eq!(
+        mdx(&["fmt", "--fence-length", "2", "a.mdx"]).status.code(),
+        Some(2)
+    );
+    assert_eq!(mdx(&["ast", "/no/such/file.mdx"]).status.code(), Some(3));
+    assert!(mdx(&["--help"]).status.success());
+}
+
+/// Waits up to ten seconds for `f` to be true
+fn eventually(mut f: impl FnMut() -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(10) {
+        if f() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+#[test]
+fn compile_out_dir() {
+    let dir = scratch("out-dir");
+    write(&dir.join("src/index.mdx"), "# Home");
+    write(&dir.join("src/guide/intro.md"), "* one");
+    write(&dir.join("src/guide/notes.txt"), "not markdown");
+    let out = dir.join("out");
+    let output = mdx(&[
+        "compile",
+        dir.join("src").to_str().unwrap(),
+        "--out-dir",
+        out.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(out.join("index.mdx")).unwrap(),
+        "# Home\n"
+    );
+    assert_eq!(
+        fs::read_to_string(out.join("guide/intro.md")).unwrap(),
+        "- one\n"
+    );
+    assert!(!out.join("guide/notes.txt").exists());
+
+    let inside = dir.join("src/out");
+    let output = mdx(&[
+        "compile",
+        dir.join("src").to_str().unwrap(),
+        "--out-dir",
+        inside.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!inside.exists());
+}
+
+#[test]
+fn compile_watch() {
+    let dir = scratch("watch");
+    let (src, out) = (dir.join("src"), dir.join("out"));
+    write(&src.join("a.mdx"), "# A");
+    // neither does a file that can't be read before watching starts
+    fs::write(src.join("early.mdx"), b"# \xff\n").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mdx"))
+        .args(["compile", "--to", "html", "--watch"])
+        .arg(&src)
+        .arg("--out-dir")
+        .arg(&out)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let read = |path: &str| fs::read_to_string(out.join(path)).unwrap_or_default();
+
+    let compiled = eventually(|| read("a.html") == "<h1>A</h1>\n");
+    // errors don't stop the watcher
+    fs::write(src.join("broken.mdx"), b"# \xff\n").unwrap();
+    write(&src.join("nested/b.md"), "# B");
+    let nested = eventually(|| read("nested/b.html") == "<h1>B</h1>\n");
+    write(&src.join("a.mdx"), "# Changed");
+    let changed = eventually(|| read("a.html") == "<h1>Changed</h1>\n");
+    fs::remove_file(src.join("a.mdx")).unwrap();
+    let removed = eventually(|| !out.join("a.html").exists());
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(compiled && nested && changed && removed);
+    assert!(!out.join("broken.html").exists());
+    assert!(!out.join("early.html").exists());
+}
+
+#[test]
+fn config_files() {
+    let dir = scratch("config");
+    write(
+        &dir.join("mdx.toml"),
+        "[lint]
+heading-increment = \"error\"
+
+[stringify]
+bullet = \"*\"
+
+[[overrides]]
+files = [\"drafts/**\"]
+lint = { heading-increment = \"off\" }
+",
+    );
+    write(&dir.join("docs/a.mdx"), "# A\n\n### C\n");
+    write(&dir.join("drafts/b.mdx"), "# B\n\n### D\n");
+
+    let output = mdx(&["check", "--format", "json", dir.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let files: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(files[0]["diagnostics"][0]["code"], "heading-increment");
+    assert_eq!(files[0]["diagnostics"][0]["severity"], "error");
+    assert_eq!(files[1]["diagnostics"], serde_json::json!([]));
+
+    // flags win over the config
+    let list = dir.join("docs/list.md");
+    write(&list, "- one\n");
+    let list = list.to_str().unwrap();
+    assert_eq!(mdx(&["fmt", "--check", list]).status.code(), Some(1));
+    assert!(mdx(&["fmt", "--check", "--bullet", "-", list]).status.success());
+
+    let bad = dir.join("bad.toml");
+    write(&bad, "[parse]\nsmart-quotes = true\n");
+    let output = mdx(&["--config", bad.to_str().unwrap(), "check", list]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("unknown field `smart-quotes`"), "{}", stderr);
+}
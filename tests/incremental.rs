@@ -0,0 +1,182 @@
+//! Property tests for incremental reparsing: whatever the document
+//! and however it's edited, the result has to be what parsing the
+//! new text from scratch gives.
+mod common;
+
+use common::Rng;
+use mdx::{
+    incremental::{Document, Edit},
+    Cst, ParseOptions, Span,
+};
+
+/// Lines that start most kinds of block, or carry one on
+const LINES: &[&str] = &[
+    "# Heading",
+    "## Heading {#id}",
+    "Some *text* with `code`",
+    "more text",
+    "",
+    "",
+    "- item",
+    "  continued",
+    "1. first",
+    "> quoted",
+    "lazy line",
+    "```js",
+    "```",
+    "~~~",
+    "<Note>",
+    "</Note>",
+    "<Chart data={1} />",
+    "{props.value}",
+    "{",
+    "}",
+    "import { A } from './a'",
+    "export const b = 1",
+    "---",
+    "===",
+    "***",
+    ":::note",
+    ":::",
+    "::leaf[label]",
+    "::leaf[open",
+    "$$",
+    "x = y",
+    "    indented code",
+    "\tTabbed",
+    "| a | b |",
+    "|---|:-:|",
+    "- [x] done",
+    "~~gone~~",
+    "+++",
+];
+
+/// What an edit puts in, which is often small, like typing
+const INSERTS: &[&str] = &[
+    "",
+    "",
+    "",
+    "a",
+    " ",
+    "\n",
+    "\n\n",
+    "#",
+    "-",
+    "*",
+    "`",
+    "```",
+    "<",
+    "/",
+    ">",
+    "</Note>",
+    "{",
+    "}",
+    ":",
+    "$",
+    "=",
+    "> ",
+    "- ",
+    "\r\n",
+    "é",
+    "word ",
+    "\n# New\n",
+];
+
+fn document(rng: &mut Rng) -> String {
+    let lines = 1 + rng.below(30);
+    let mut text = String::new();
+    for _ in 0..lines {
+        text.push_str(rng.pick(LINES));
+        text.push('\n');
+    }
+    if rng.below(4) == 0 {
+        text.pop();
+    }
+    text
+}
+
+/// A char boundary somewhere in `text`
+fn boundary(rng: &mut Rng, text: &str) -> usize {
+    let mut offset = rng.below(text.len() + 1);
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+fn edit<'e>(rng: &mut Rng, text: &str) -> Edit<'e> {
+    let start = boundary(rng, text);
+    let mut end = start;
+    if rng.below(2) == 0 {
+        end = (start + rng.below(12)).min(text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+    }
+    Edit {
+        span: Span::new(start, end),
+        text: rng.pick(INSERTS),
+    }
+}
+
+fn check(options: ParseOptions, seed: u64) {
+    let mut rng = Rng(seed);
+    for case in 0..400 {
+        let original = document(&mut rng);
+        let mut document = Document::parse(original.as_str(), options);
+        let mut history = vec![];
+        for _ in 0..8 {
+            let before = document.text().to_string();
+            let edit = edit(&mut rng, &before);
+            history.push(format!("{:?} -> {:?}", edit.span, edit.text));
+            document.edit(edit);
+            assert_eq!(
+                document.cst(),
+                Cst::parse_with_options(document.text(), options),
+                "case {}: {:?}\nedited with {:#?}\nbefore the last edit: {:?}",
+                case,
+                original,
+                history,
+                before,
+            );
+        }
+    }
+}
+
+#[test]
+fn edits_match_a_full_parse() {
+    check(ParseOptions::default(), 0x9e37_79b9_7f4a_7c15);
+}
+
+#[test]
+fn edits_match_a_full_parse_with_extensions() {
+    let options = ParseOptions {
+        math: true,
+        directives: true,
+        heading_attributes: true,
+        heading_ids: true,
+        gfm: true,
+        frontmatter: true,
+    };
+    check(options, 0xdead_beef_cafe_f00d);
+}
+
+#[test]
+fn most_blocks_are_moved_over() {
+    let mut text = String::new();
+    for i in 0..1000 {
+        text.push_str(&format!("## Section {}\n\nParagraph {}.\n\n", i, i));
+    }
+    let mut document = Document::parse(text.as_str(), ParseOptions::default());
+    let at = text.find("Paragraph 500").unwrap();
+    let reparsed = document.edit(Edit {
+        span: Span::new(at, at),
+        text: "Edited ",
+    });
+    assert!(reparsed.len() <= 3, "{:?} were parsed again", reparsed);
+    assert_eq!(
+        document.cst(),
+        Cst::parse(document.text()),
+        "the edited document"
+    );
+}
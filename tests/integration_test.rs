@@ -1,5 +1,4 @@
 use mdx::{ast::MdxAst, *};
-use nom_supreme::error::{BaseErrorKind, ErrorTree};
 
 #[test]
 fn test_parse() {
@@ -16,7 +15,9 @@ fn test_parse() {
             ast: vec![
                 ast::MdxAst::ATXHeading(ast::ATXHeading {
                     level: 1,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
                 }),
                 ast::MdxAst::ThematicBreak(ast::ThematicBreak {
                     char_count: 3,
@@ -24,7 +25,9 @@ fn test_parse() {
                 }),
                 ast::MdxAst::ATXHeading(ast::ATXHeading {
                     level: 2,
-                    value: "boop 2"
+                    value: "boop 2".into(),
+                    id: None,
+                    attributes: vec![]
                 }),
             ]
         }
@@ -39,12 +42,18 @@ fn test_parse_fail_thematic_break_into_paragraph() {
             ast: vec![
                 MdxAst::ATXHeading(ast::ATXHeading {
                     level: 1,
-                    value: "boop"
+                    value: "boop".into(),
+                    id: None,
+                    attributes: vec![]
+                }),
+                MdxAst::Paragraph(ast::Paragraph {
+                    words: "-d--".into()
                 }),
-                MdxAst::Paragraph(ast::Paragraph { words: "-d--" }),
                 MdxAst::ATXHeading(ast::ATXHeading {
                     level: 2,
-                    value: "boop 2"
+                    value: "boop 2".into(),
+                    id: None,
+                    attributes: vec![]
                 })
             ]
         }
@@ -69,13 +78,14 @@ const some = {}
         Mdx {
             ast: vec![ast::MdxAst::ATXHeading(ast::ATXHeading {
                 level: 1,
-                value: "boop"
+                value: "boop".into(),
+                id: None,
+                attributes: vec![]
             })]
         }
     );
 }
 
-#[test]
 #[test]
 fn round_trip() {
     assert_eq!(
@@ -90,3 +100,326 @@ fn round_trip() {
         "# boop"
     );
 }
+
+#[test]
+fn test_parse_inline_code() {
+    let mdx = parse("call `` `parse` `` first").unwrap();
+    match &mdx.ast[0] {
+        MdxAst::Paragraph(para) => assert_eq!(
+            para.inlines(),
+            vec![
+                ast::Inline::Text("call ".into()),
+                ast::Inline::InlineCode(ast::InlineCode {
+                    value: "`parse`".into()
+                }),
+                ast::Inline::Text(" first".into()),
+            ]
+        ),
+        node => panic!("expected a paragraph, got {:?}", node),
+    }
+}
+
+#[test]
+fn test_parse_math() {
+    let input = "$$\nE = mc^2\n$$\n\nworth $5 and $10";
+    let options = ParseOptions {
+        math: true,
+        ..ParseOptions::default()
+    };
+    let mdx = parse_with_options(input, options).unwrap();
+    assert_eq!(
+        mdx.ast[0],
+        MdxAst::Math(ast::Math {
+            meta: "".into(),
+            value: "E = mc^2".into()
+        })
+    );
+    match &mdx.ast[1] {
+        MdxAst::Paragraph(para) => assert_eq!(
+            para.inlines_with_options(options),
+            vec![ast::Inline::Text("worth $5 and $10".into())]
+        ),
+        node => panic!("expected a paragraph, got {:?}", node),
+    }
+    // without the option, math is a paragraph
+    assert!(matches!(parse(input).unwrap().ast[0], MdxAst::Paragraph(_)));
+}
+
+#[test]
+fn test_parse_directives() {
+    let options = ParseOptions {
+        directives: true,
+        ..ParseOptions::default()
+    };
+    let input = ":::tip{.wide}\n# Hello\n\n::video{#intro}\n:::";
+    assert_eq!(
+        parse_with_options(input, options).unwrap(),
+        Mdx {
+            ast: vec![MdxAst::ContainerDirective(ast::ContainerDirective {
+                name: "tip".into(),
+                label: None,
+                attributes: vec![ast::Attribute {
+                    key: "class".into(),
+                    value: "wide".into()
+                }],
+                children: vec![
+                    MdxAst::ATXHeading(ast::ATXHeading {
+                        level: 1,
+                        value: "Hello".into(),
+                        id: None,
+                        attributes: vec![]
+                    }),
+                    MdxAst::LeafDirective(ast::LeafDirective {
+                        name: "video".into(),
+                        label: None,
+                        attributes: vec![ast::Attribute {
+                            key: "id".into(),
+                            value: "intro".into()
+                        }]
+                    }),
+                ]
+            })]
+        }
+    );
+    assert_eq!(
+        parse_with_options(input, options).map(stringify).unwrap(),
+        ":::tip{.wide}\n# Hello\n\n::video{#intro}\n:::"
+    );
+}
+
+#[test]
+fn test_parse_heading_ids() {
+    let options = ParseOptions {
+        heading_attributes: true,
+        heading_ids: true,
+        ..ParseOptions::default()
+    };
+    let mdx = parse_with_options(
+        "# Intro\n\n## Intro\n\n## Custom {#intro-1}\n\n### The `parse` fn\n\n# Intro",
+        options,
+    )
+    .unwrap();
+    let ids: Vec<_> = mdx
+        .ast
+        .iter()
+        .map(|node| match node {
+            MdxAst::ATXHeading(heading) => heading.id.as_deref().unwrap(),
+            node => panic!("expected a heading, got {:?}", node),
+        })
+        .collect();
+    assert_eq!(
+        ids,
+        vec!["intro", "intro-2", "intro-1", "the-parse-fn", "intro-3"]
+    );
+    assert_eq!(
+        stringify(mdx),
+        "# Intro {#intro}\n\n## Intro {#intro-2}\n\n## Custom {#intro-1}\n\n### The `parse` fn {#the-parse-fn}\n\n# Intro {#intro-3}"
+    );
+}
+
+#[test]
+fn test_inject_toc() {
+    let mut mdx = parse(
+        "# Docs\n\n## Table of contents\n\nold toc\n\n## Install\n\n### From source\n\n## Usage",
+    )
+    .unwrap();
+    assert!(inject_toc(&mut mdx, &TocOptions::default()));
+    assert_eq!(
+        stringify(mdx),
+        "# Docs
+
+## Table of contents
+
+- [Docs](#docs)
+  - [Install](#install)
+    - [From source](#from-source)
+  - [Usage](#usage)
+
+## Install
+
+### From source
+
+## Usage"
+    );
+}
+
+#[test]
+fn test_processor_toc_plugin() {
+    let mut processor = Processor::new().use_plugin(InjectToc::default());
+    let mut file = VFile::new("## Table of contents\n\n## Usage");
+    processor.process(&mut file).unwrap();
+    assert_eq!(
+        file.value,
+        "## Table of contents\n\n- [Usage](#usage)\n\n## Usage"
+    );
+
+    let mut file = VFile::new("## Usage");
+    processor.process(&mut file).unwrap();
+    assert_eq!(
+        file.messages[0].reason,
+        "missing `Table of contents` heading"
+    );
+}
+
+#[test]
+fn test_into_owned_outlives_input() {
+    let owned: Mdx<'static> = {
+        let input = String::from("# Cached\n\nsome `code` here");
+        parse(&input).unwrap().into_owned()
+    };
+    let handle = std::thread::spawn(move || stringify(owned));
+    assert_eq!(handle.join().unwrap(), "# Cached\n\nsome `code` here");
+}
+
+#[test]
+fn test_build_ast_programmatically() {
+    let title = format!("Release {}", "1.2.0");
+    let mdx = Mdx {
+        ast: vec![
+            MdxAst::ATXHeading(ast::ATXHeading {
+                level: 1,
+                value: title.into(),
+                id: None,
+                attributes: vec![],
+            }),
+            MdxAst::Paragraph(ast::Paragraph {
+                words: "Notes".into(),
+            }),
+        ],
+    };
+    assert_eq!(stringify(mdx), "# Release 1.2.0\n\nNotes");
+}
+
+#[test]
+fn test_round_trip_containers() {
+    let input = "# Changes
+
+> Read this first
+>
+> - even the list
+
+- one
+- two
+  1. nested
+  2. items
+
+<Note type=\"info\">
+Markdown *inside* a component
+
+<Chart data={[1, 2, 3]} />
+</Note>";
+    let mdx = parse(input).unwrap();
+    assert!(matches!(mdx.ast[1], MdxAst::BlockQuote(_)));
+    assert!(matches!(mdx.ast[2], MdxAst::List(_)));
+    assert!(matches!(mdx.ast[3], MdxAst::JsxElement(_)));
+    assert_eq!(stringify(mdx), input);
+}
+
+#[test]
+fn test_list_interrupts_paragraph() {
+    let mdx = parse("Things to do:\n- write\n- ship").unwrap();
+    assert_eq!(mdx.ast.len(), 2);
+    assert!(matches!(mdx.ast[1], MdxAst::List(_)));
+}
+
+#[test]
+fn test_parse_recovering() {
+    let input = "# Draft\n\n```rust\nfn main() {\n\nSome text\n\n## Next";
+    let recovered = parse_recovering(input);
+    assert_eq!(recovered.mdx, parse(input).unwrap());
+    assert_eq!(
+        recovered
+            .diagnostics
+            .iter()
+            .map(|error| error.code)
+            .collect::<Vec<_>>(),
+        vec![ErrorCode::UnclosedFence]
+    );
+    assert_eq!(recovered.diagnostics[0].severity, processor::Severity::Warning);
+    assert_eq!(recovered.mdx.ast.len(), 2);
+}
+
+#[test]
+// formatting with options is stable, so formatting twice is the
+// same as formatting once
+fn test_stringify_with_options() {
+    let options = StringifyOptions {
+        bullet: '*',
+        emphasis: Some('_'),
+        strong: Some('*'),
+        fence: '~',
+        heading_style: HeadingStyle::Setext,
+        list_item_indent: ListItemIndent::Mixed,
+        line_width: Some(20),
+        ..StringifyOptions::default()
+    };
+    let input = "# A *short* title
+
+> - __one__ and *two* and three
+> - four
+
+```rust
+let x = 1;
+```";
+    let output = stringify_with_options(&parse(input).unwrap(), options);
+    assert_eq!(
+        output,
+        "A _short_ title
+===============
+
+> * **one** and _two_
+>   and three
+> * four
+
+~~~rust
+let x = 1;
+~~~"
+    );
+    assert_eq!(
+        stringify_with_options(&parse(&output).unwrap(), options),
+        output
+    );
+}
+
+#[test]
+// `format` rewrites markdown and JSX, but never the JavaScript in ESM
+// or expressions
+fn test_format() {
+    let options = StringifyOptions {
+        bullet: '*',
+        line_width: Some(12),
+        ..StringifyOptions::default()
+    };
+    let input = "import {Chart} from './chart'
+export const meta = {title:   'Sales'}
+
+- one two three four
+
+<Chart   data={[1,2]}>
+
+-   nested
+
+</Chart>
+
+{meta.title  .toUpperCase()}";
+    let output = format(input, ParseOptions::default(), options).unwrap();
+    assert_eq!(
+        output,
+        "import {Chart} from './chart'
+export const meta = {title:   'Sales'}
+
+* one two
+  three four
+
+<Chart data={[1,2]}>
+* nested
+</Chart>
+
+{meta.title  .toUpperCase()}
+"
+    );
+    assert_eq!(
+        format(&output, ParseOptions::default(), options).unwrap(),
+        output
+    );
+}
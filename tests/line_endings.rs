@@ -0,0 +1,78 @@
+use mdx::{parse, stringify, Cst};
+use std::{fs, path::Path};
+
+fn normalize(input: &str) -> String {
+    input.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+fn fixtures() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/crlf");
+    let mut fixtures: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mdx"))
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, fs::read_to_string(&path).unwrap())
+        })
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+#[test]
+fn fixtures_are_crlf() {
+    for (name, input) in fixtures() {
+        assert!(
+            input.contains("\r\n"),
+            "{} lost its CRLF line endings",
+            name
+        );
+    }
+}
+
+#[test]
+// CRLF and lone CR documents parse to the same tree as their LF
+// version, apart from the line endings kept inside verbatim text
+fn crlf_and_cr_match_lf() {
+    for (name, crlf) in fixtures() {
+        let lf = normalize(&crlf);
+        let cr = lf.replace('\n', "\r");
+        let expected = stringify(parse(&lf).unwrap());
+        for input in [&crlf, &cr] {
+            let mdx = parse(input).unwrap_or_else(|error| panic!("{}: {}", name, error));
+            assert_eq!(normalize(&stringify(mdx)), expected, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn leading_bom_is_ignored() {
+    for (name, crlf) in fixtures() {
+        let with_bom = format!("\u{feff}{}", crlf);
+        assert_eq!(parse(&with_bom).unwrap(), parse(&crlf).unwrap(), "{}", name);
+    }
+}
+
+#[test]
+fn tab_indented_list_items() {
+    let (_, crlf) = fixtures()
+        .into_iter()
+        .find(|(name, _)| name == "tabs.mdx")
+        .unwrap();
+    assert_eq!(
+        stringify(parse(&crlf).unwrap()),
+        "- one\n\n- two\n\n  indented continuation\n\n> quoted with a tab\n\n1. first"
+    );
+}
+
+#[test]
+fn cst_round_trips_byte_for_byte() {
+    for (name, crlf) in fixtures() {
+        let cr = normalize(&crlf).replace('\n', "\r");
+        let with_bom = format!("\u{feff}{}", crlf);
+        for input in [&crlf, &cr, &with_bom] {
+            assert_eq!(&Cst::parse(input).unwrap().to_source(), input, "{}", name);
+        }
+    }
+}
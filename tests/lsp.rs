@@ -0,0 +1,400 @@
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Exit, Initialized,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{
+        DocumentSymbolRequest, FoldingRangeRequest, Formatting, GotoDefinition, Initialize,
+        References, SemanticTokensFullRequest, Shutdown,
+    },
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentFormattingParams, DocumentSymbolParams,
+    DocumentSymbolResponse, FoldingRangeParams, FormattingOptions, GotoDefinitionParams,
+    GotoDefinitionResponse, InitializeParams, InitializedParams, Location, NumberOrString,
+    Position, PublishDiagnosticsParams, Range, ReferenceContext, ReferenceParams,
+    SemanticTokensParams, SemanticTokensResult, TextDocumentContentChangeEvent,
+    TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams, Url,
+    VersionedTextDocumentIdentifier, WorkspaceFolder,
+};
+use std::{
+    fs,
+    path::PathBuf,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// An LSP client talking to a server that runs on another thread,
+/// over an in-memory connection
+struct Client {
+    connection: Connection,
+    server: Option<JoinHandle<()>>,
+    next_id: i32,
+}
+impl Client {
+    /// Starts a server and initializes it
+    fn start() -> Self {
+        Client::start_with(InitializeParams::default())
+    }
+
+    fn start_with(params: InitializeParams) -> Self {
+        let (client, server) = Connection::memory();
+        let server = thread::spawn(move || mdx::lsp::run(&server).unwrap());
+        let mut client = Client {
+            connection: client,
+            server: Some(server),
+            next_id: 0,
+        };
+        let result = client.request::<Initialize>(params);
+        assert!(result.capabilities.document_symbol_provider.is_some());
+        client.notify::<Initialized>(InitializedParams {});
+        client
+    }
+
+    fn request<R: lsp_types::request::Request>(&mut self, params: R::Params) -> R::Result {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        let request = Request::new(id.clone(), R::METHOD.to_string(), params);
+        self.connection.sender.send(request.into()).unwrap();
+        loop {
+            match self.receive() {
+                Message::Response(Response {
+                    id: response_id,
+                    result,
+                    error,
+                }) if response_id == id => {
+                    assert!(error.is_none(), "{:?}", error);
+                    return serde_json::from_value(result.unwrap_or_default()).unwrap();
+                }
+                // notifications sent before the response aren't what
+                // the test is waiting for
+                _ => (),
+            }
+        }
+    }
+
+    fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) {
+        let notification = Notification::new(N::METHOD.to_string(), params);
+        self.connection.sender.send(notification.into()).unwrap();
+    }
+
+    /// Waits for the server to publish diagnostics
+    fn diagnostics(&self) -> PublishDiagnosticsParams {
+        loop {
+            if let Message::Notification(notification) = self.receive() {
+                if notification.method == PublishDiagnostics::METHOD {
+                    return serde_json::from_value(notification.params).unwrap();
+                }
+            }
+        }
+    }
+
+    fn receive(&self) -> Message {
+        self.connection
+            .receiver
+            .recv_timeout(Duration::from_secs(10))
+            .expect("the server didn't answer")
+    }
+
+    fn open(&self, uri: &Url, text: &str) {
+        self.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(uri.clone(), "mdx".to_string(), 1, text.into()),
+        });
+    }
+
+    fn shutdown(mut self) {
+        self.request::<Shutdown>(());
+        self.notify::<Exit>(());
+        self.server.take().unwrap().join().unwrap();
+    }
+}
+
+fn document(uri: &Url) -> TextDocumentIdentifier {
+    TextDocumentIdentifier::new(uri.clone())
+}
+
+/// A directory for a test's documents, which is outside of any
+/// directory with an `mdx.toml`
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mdx-lsp-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn diagnostics_follow_changes() {
+    let client = Client::start();
+    let uri = Url::from_file_path(scratch("diagnostics").join("doc.mdx")).unwrap();
+    client.open(&uri, "# Title\n\n```js\nunclosed\n");
+    let published = client.diagnostics();
+    assert_eq!(published.uri, uri);
+    assert_eq!(published.version, Some(1));
+    assert_eq!(published.diagnostics.len(), 1);
+    let diagnostic = &published.diagnostics[0];
+    assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(
+        diagnostic.range,
+        Range::new(Position::new(2, 0), Position::new(2, 3))
+    );
+
+    client.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier::new(uri.clone(), 2),
+        content_changes: vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "# Title\n\n### Skipped\n".to_string(),
+        }],
+    });
+    let published = client.diagnostics();
+    assert_eq!(published.version, Some(2));
+    assert_eq!(
+        published.diagnostics[0].code,
+        Some(NumberOrString::String("heading-increment".to_string()))
+    );
+    assert_eq!(
+        published.diagnostics[0].severity,
+        Some(DiagnosticSeverity::WARNING)
+    );
+
+    client.notify::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+        text_document: document(&uri),
+    });
+    assert!(client.diagnostics().diagnostics.is_empty());
+    client.shutdown();
+}
+
+#[test]
+fn symbols_folding_and_formatting() {
+    let mut client = Client::start();
+    let uri = Url::from_file_path(scratch("symbols").join("doc.mdx")).unwrap();
+    let text = "# Guide\n\n* one\n* two\n\n## Usage\n\n```sh\nmdx check\n```\n";
+    client.open(&uri, text);
+    client.diagnostics();
+
+    let symbols = client.request::<DocumentSymbolRequest>(DocumentSymbolParams {
+        text_document: document(&uri),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    });
+    let symbols = match symbols {
+        Some(DocumentSymbolResponse::Nested(symbols)) => symbols,
+        symbols => panic!("expected nested symbols, got {:?}", symbols),
+    };
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0].name, "Guide");
+    assert_eq!(symbols[0].children.as_ref().unwrap()[0].name, "Usage");
+
+    let ranges = client
+        .request::<FoldingRangeRequest>(FoldingRangeParams {
+            text_document: document(&uri),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let lines: Vec<_> = ranges
+        .iter()
+        .map(|range| (range.start_line, range.end_line))
+        .collect();
+    assert_eq!(lines, vec![(0, 9), (5, 9), (7, 9)]);
+
+    let edits = client
+        .request::<Formatting>(DocumentFormattingParams {
+            text_document: document(&uri),
+            options: FormattingOptions::default(),
+            work_done_progress_params: Default::default(),
+        })
+        .unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(
+        edits[0].range,
+        Range::new(Position::new(0, 0), Position::new(10, 0))
+    );
+    assert_eq!(edits[0].new_text, text.replace('*', "-"));
+    client.shutdown();
+}
+
+#[test]
+fn edits_are_synced() {
+    let mut client = Client::start();
+    let uri = Url::from_file_path(scratch("edits").join("doc.mdx")).unwrap();
+    client.open(&uri, "# Guide\n\nSome text.\n\n## Usage\n");
+    client.diagnostics();
+    let change = |line, start, end, text: &str| TextDocumentContentChangeEvent {
+        range: Some(Range::new(
+            Position::new(line, start),
+            Position::new(line, end),
+        )),
+        range_length: None,
+        text: text.to_string(),
+    };
+    client.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier::new(uri.clone(), 2),
+        // the second change is made after the first one
+        content_changes: vec![
+            change(4, 3, 8, "Install"),
+            change(2, 0, 0, "### Skipped\n\n"),
+        ],
+    });
+    let published = client.diagnostics();
+    assert_eq!(published.version, Some(2));
+    assert_eq!(
+        published.diagnostics[0].range.start,
+        Position::new(2, 0),
+        "{:#?}",
+        published.diagnostics
+    );
+
+    let symbols = client.request::<DocumentSymbolRequest>(DocumentSymbolParams {
+        text_document: document(&uri),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    });
+    let children = match symbols {
+        Some(DocumentSymbolResponse::Nested(symbols)) => symbols[0].children.clone().unwrap(),
+        symbols => panic!("expected nested symbols, got {:?}", symbols),
+    };
+    let names: Vec<_> = children.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, vec!["Skipped", "Install"]);
+    client.shutdown();
+}
+
+#[test]
+fn settings_come_from_the_config() {
+    let dir = scratch("config");
+    fs::write(
+        dir.join("mdx.toml"),
+        "[lint]\nheading-increment = \"error\"\n\n[stringify]\nbullet = \"*\"\n",
+    )
+    .unwrap();
+    let mut client = Client::start();
+    let uri = Url::from_file_path(dir.join("docs/doc.mdx")).unwrap();
+    client.open(&uri, "# A\n\n### B\n\n* item\n");
+    let published = client.diagnostics();
+    assert_eq!(
+        published.diagnostics[0].severity,
+        Some(DiagnosticSeverity::ERROR)
+    );
+    let edits = client
+        .request::<Formatting>(DocumentFormattingParams {
+            text_document: document(&uri),
+            options: FormattingOptions::default(),
+            work_done_progress_params: Default::default(),
+        })
+        .unwrap();
+    assert!(edits.is_empty());
+    client.shutdown();
+}
+
+#[test]
+fn links_across_the_workspace() {
+    let dir = scratch("links");
+    fs::write(dir.join("guide.mdx"), "# Guide\n\n## Install\n\nRun it.\n").unwrap();
+    fs::create_dir_all(dir.join("blog")).unwrap();
+    fs::write(
+        dir.join("blog/post.md"),
+        "See [installing][install].\n\n[install]: ../guide#install\n",
+    )
+    .unwrap();
+    let root = Url::from_directory_path(&dir).unwrap();
+    let mut client = Client::start_with(InitializeParams {
+        workspace_folders: Some(vec![WorkspaceFolder {
+            uri: root.clone(),
+            name: "links".to_string(),
+        }]),
+        ..InitializeParams::default()
+    });
+    let guide = root.join("guide.mdx").unwrap();
+    let post = root.join("blog/post.md").unwrap();
+    let uri = root.join("index.mdx").unwrap();
+    client.open(
+        &uri,
+        "import { Chart } from './chart'\n\nRead [the guide](./guide.mdx#install).\n\n<Chart />\n",
+    );
+    client.diagnostics();
+
+    let mut definition = |line, character| {
+        let response = client.request::<GotoDefinition>(GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams::new(
+                document(&uri),
+                Position::new(line, character),
+            ),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        match response {
+            Some(GotoDefinitionResponse::Scalar(location)) => location,
+            response => panic!("expected a location, got {:?}", response),
+        }
+    };
+    assert_eq!(
+        definition(2, 8),
+        Location::new(
+            guide.clone(),
+            Range::new(Position::new(2, 0), Position::new(2, 10))
+        )
+    );
+    assert_eq!(
+        definition(4, 3),
+        Location::new(
+            uri.clone(),
+            Range::new(Position::new(0, 9), Position::new(0, 14))
+        )
+    );
+
+    let references = client
+        .request::<References>(ReferenceParams {
+            text_document_position: TextDocumentPositionParams::new(
+                document(&uri),
+                Position::new(2, 20),
+            ),
+            context: ReferenceContext {
+                include_declaration: true,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+    let found: Vec<_> = references
+        .iter()
+        .map(|location| (location.uri.clone(), location.range.start.line))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (guide.clone(), 2),
+            (post.clone(), 0),
+            (post.clone(), 2),
+            (uri.clone(), 2),
+        ]
+    );
+    client.shutdown();
+}
+
+#[test]
+fn semantic_tokens() {
+    let mut client = Client::start();
+    let uri = Url::from_file_path(scratch("tokens").join("doc.mdx")).unwrap();
+    client.open(&uri, "# Hi\n\n<Note kind=\"tip\">\n  *Careful*\n</Note>\n");
+    client.diagnostics();
+    let tokens = client.request::<SemanticTokensFullRequest>(SemanticTokensParams {
+        text_document: document(&uri),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    });
+    let tokens = match tokens {
+        Some(SemanticTokensResult::Tokens(tokens)) => tokens.data,
+        tokens => panic!("expected tokens, got {:?}", tokens),
+    };
+    let spans: Vec<_> = tokens
+        .iter()
+        .map(|token| (token.delta_line, token.delta_start, token.length))
+        .collect();
+    // the heading marker, the tag and attribute names, the emphasis
+    // and the closing tag
+    assert_eq!(
+        spans,
+        vec![(0, 0, 1), (2, 1, 4), (0, 5, 4), (1, 2, 9), (1, 2, 4)]
+    );
+    client.shutdown();
+}
@@ -0,0 +1,172 @@
+//! Property tests for streaming: however a document is cut into
+//! chunks, the blocks that come out are the ones a full parse finds.
+mod common;
+
+use common::Rng;
+use mdx::{
+    incremental::Block,
+    stream::{Blocks, Parser, StreamError},
+    Cst, MdxError, ParseOptions,
+};
+use std::io::{self, Read};
+
+const LINES: &[&str] = &[
+    "# Heading",
+    "Heading {#id}",
+    "===",
+    "Some *text* with `code` and ünïcödé 😀",
+    "",
+    "",
+    "   ",
+    "- item",
+    "  continued",
+    "2. second",
+    "> quoted",
+    "```js",
+    "```",
+    "<Note>",
+    "</Note>",
+    "<Chart data={1} />",
+    "{props.value}",
+    "{",
+    "}",
+    "import { A } from './a'",
+    "---",
+    ":::note",
+    ":::",
+    "::leaf[label]",
+    "$$",
+    "    indented code",
+    "| a | b |",
+    "|---|:-:|",
+    "- [ ] todo",
+    "+++",
+];
+
+fn document(rng: &mut Rng) -> String {
+    let ending = ["\n", "\r\n", "\r"][rng.below(3)];
+    let mut text = String::new();
+    if rng.below(8) == 0 {
+        text.push('\u{feff}');
+    }
+    for _ in 0..rng.below(40) {
+        text.push_str(rng.pick(LINES));
+        text.push_str(ending);
+    }
+    text
+}
+
+/// What a full parse finds, in the same shape as the stream
+fn whole(text: &str, options: ParseOptions) -> Result<Vec<Block>, MdxError> {
+    let cst = Cst::parse_with_options(text, options)?;
+    Ok(cst
+        .blocks()
+        .iter()
+        .map(|block| {
+            let span = block.span.unwrap();
+            Block {
+                leading: mdx::Span::new(span.start - block.leading.len(), span.start),
+                span,
+                node: block.node().clone().into_owned(),
+            }
+        })
+        .collect())
+}
+
+/// Hands out what it has a few bytes at a time, cutting characters
+/// in half
+struct Trickle<'a> {
+    rest: &'a [u8],
+    rng: Rng,
+}
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = (1 + self.rng.below(16)).min(self.rest.len()).min(buf.len());
+        buf[..read].copy_from_slice(&self.rest[..read]);
+        self.rest = &self.rest[read..];
+        Ok(read)
+    }
+}
+
+fn check(options: ParseOptions, seed: u64) {
+    let mut rng = Rng(seed);
+    for case in 0..300 {
+        let text = document(&mut rng);
+        let expected = whole(&text, options);
+
+        // pushed in pieces cut at random characters
+        let mut parser = Parser::new(options);
+        let mut pushed = vec![];
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            let mut at = (1 + rng.below(12)).min(rest.len());
+            while !rest.is_char_boundary(at) {
+                at += 1;
+            }
+            pushed.extend(parser.push(&rest[..at]));
+            rest = &rest[at..];
+        }
+        let pushed = parser.finish().map(|finished| {
+            pushed.extend(finished);
+            pushed
+        });
+        assert_eq!(pushed, expected, "case {}: {:?}", case, text);
+
+        // read from a reader
+        let reader = Trickle {
+            rest: text.as_bytes(),
+            rng: Rng(seed ^ case),
+        };
+        let read: Result<Vec<_>, _> = Blocks::new(reader, options).collect();
+        match (read, &expected) {
+            (Ok(read), Ok(expected)) => assert_eq!(&read, expected, "case {}", case),
+            (Err(StreamError::Parse(error)), Err(expected)) => {
+                assert_eq!(&error, expected, "case {}", case)
+            }
+            (read, _) => panic!("case {}: {:?} read as {:?}", case, text, read),
+        }
+    }
+}
+
+#[test]
+fn chunks_match_a_full_parse() {
+    check(ParseOptions::default(), 0x2545_f491_4f6c_dd1d);
+}
+
+#[test]
+fn chunks_match_a_full_parse_with_extensions() {
+    let options = ParseOptions {
+        math: true,
+        directives: true,
+        heading_attributes: true,
+        heading_ids: true,
+        gfm: true,
+        frontmatter: true,
+    };
+    check(options, 0x5851_f42d_4c95_7f2d);
+}
+
+#[test]
+fn blocks_come_out_before_the_end() {
+    /// A generated document that never ends, which has to be read a
+    /// block at a time
+    struct Endless(usize, Vec<u8>);
+    impl Read for Endless {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.1.is_empty() {
+                self.0 += 1;
+                self.1 = format!("## Entry {}\n\nGenerated text.\n\n", self.0).into_bytes();
+            }
+            let read = self.1.len().min(buf.len());
+            buf[..read].copy_from_slice(&self.1[..read]);
+            self.1.drain(..read);
+            Ok(read)
+        }
+    }
+    let blocks: Vec<_> = Blocks::new(Endless(0, vec![]), ParseOptions::default())
+        .take(1000)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(blocks.len(), 1000);
+    assert_eq!(blocks[998].span.start, blocks[997].span.end + 2);
+}